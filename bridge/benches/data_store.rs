@@ -0,0 +1,102 @@
+// Run with `cargo bench -p bridge --bench data_store`. The S3 benchmarks only run when the
+// BRIDGE_AWS_* variables are set, e.g. against LocalStack:
+// export BRIDGE_AWS_ENDPOINT_URL="http://localhost:4566"
+
+use bridge::{
+    client::data_store::{aws_s3::AwsS3, base::DataStoreDriver, memory::MemoryStore},
+    utils::{compress, decompress},
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::runtime::Runtime;
+
+const FILE_PATH: &str = "bridge_data/bench";
+const COMPRESSION_LEVELS: [i32; 4] = [1, 5, 10, 19];
+// Roughly a peg-in graph, a peg-out graph and a full client data snapshot
+const ARTIFACT_SIZES: [usize; 3] = [16 * 1024, 512 * 1024, 8 * 1024 * 1024];
+
+// JSON-like, moderately repetitive contents, similar to serialized bridge graphs
+fn artifact(size: usize) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(size);
+    let mut i: u64 = 0;
+    while contents.len() < size {
+        contents.extend_from_slice(
+            format!(
+                "{{\"txid\":\"{:064x}\",\"vout\":{},\"amount\":{}}},",
+                i * 7919,
+                i % 4,
+                i * 1000
+            )
+            .as_bytes(),
+        );
+        i += 1;
+    }
+    contents.truncate(size);
+    contents
+}
+
+fn bench_compression(c: &mut Criterion) {
+    for size in ARTIFACT_SIZES {
+        let contents = artifact(size);
+
+        let mut group = c.benchmark_group(format!("compress/{}KiB", size / 1024));
+        group.throughput(Throughput::Bytes(size as u64));
+        for level in COMPRESSION_LEVELS {
+            group.bench_with_input(BenchmarkId::from_parameter(level), &level, |b, &level| {
+                b.iter(|| compress(&contents, level).unwrap())
+            });
+        }
+        group.finish();
+
+        let mut group = c.benchmark_group(format!("decompress/{}KiB", size / 1024));
+        group.throughput(Throughput::Bytes(size as u64));
+        for level in COMPRESSION_LEVELS {
+            let compressed = compress(&contents, level).unwrap();
+            group.bench_with_input(
+                BenchmarkId::from_parameter(level),
+                &compressed,
+                |b, data| b.iter(|| decompress(data).unwrap()),
+            );
+        }
+        group.finish();
+    }
+}
+
+fn bench_driver(c: &mut Criterion, name: &str, driver: &dyn DataStoreDriver, runtime: &Runtime) {
+    for size in ARTIFACT_SIZES {
+        let contents = String::from_utf8(artifact(size)).unwrap();
+        let file_name = format!("bench-{size}.json");
+
+        let mut group = c.benchmark_group(format!("{name}/{}KiB", size / 1024));
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function("upload_object", |b| {
+            b.to_async(runtime).iter(|| async {
+                driver
+                    .upload_object(&file_name, &contents, Some(FILE_PATH))
+                    .await
+                    .unwrap()
+            })
+        });
+        group.bench_function("fetch_object", |b| {
+            b.to_async(runtime).iter(|| async {
+                driver
+                    .fetch_object(&file_name, Some(FILE_PATH))
+                    .await
+                    .unwrap()
+            })
+        });
+        group.finish();
+    }
+}
+
+fn bench_drivers(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+
+    bench_driver(c, "memory", &MemoryStore::new(), &runtime);
+    match AwsS3::new() {
+        Some(s3) => bench_driver(c, "s3", &s3, &runtime),
+        None => eprintln!("Skipping S3 benchmarks, BRIDGE_AWS_* variables are not set"),
+    }
+}
+
+criterion_group!(benches, bench_compression, bench_drivers);
+criterion_main!(benches);
@@ -264,6 +264,12 @@ impl BitVMClient {
         self.save_to_data_store().await;
     }
 
+    /// Shuts down the data store, see `DataStore::shutdown`. Unsaved changes are not saved,
+    /// call `flush` first to keep them.
+    pub async fn shutdown(self) -> Result<(), String> {
+        self.data_store.shutdown().await
+    }
+
     /*
     Expected file syncing flow with data store:
      1. Fetch the latest file                               ⎫
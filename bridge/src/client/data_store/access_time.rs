@@ -0,0 +1,363 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::clock::{Clock, SystemClock};
+use super::key::object_key;
+use super::stats::StoreStats;
+use async_trait::async_trait;
+
+// Wraps any data store driver and remembers when each object was last read or written, so the
+// least recently used artifacts can be cleaned up. S3 only tracks last-modified times, so the
+// access times are kept in a local index rather than written back to the store, which would
+// turn every read into a write. The index starts out empty: objects this instance has never
+// touched count as the least recently used.
+pub struct AccessTracked<D: DataStoreDriver> {
+    inner: D,
+    clock: Arc<dyn Clock>,
+    accessed_at: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl<D: DataStoreDriver> AccessTracked<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            clock: Arc::new(SystemClock),
+            accessed_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn record_access(&self, file_name: &str, file_path: Option<&str>) {
+        self.accessed_at
+            .lock()
+            .unwrap()
+            .insert(object_key(file_name, file_path), self.clock.now());
+    }
+
+    fn record_result<T>(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        result: Result<T, String>,
+    ) -> Result<T, String> {
+        if result.is_ok() {
+            self.record_access(file_name, file_path);
+        }
+        result
+    }
+}
+
+impl<D: DataStoreDriver + Send + Sync> AccessTracked<D> {
+    /// File names under `file_path`, least recently read or written first.
+    pub async fn list_objects_by_access_time(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<String>, String> {
+        let keys = self.inner.list_objects(file_path).await?;
+        let accessed_at = self.accessed_at.lock().unwrap();
+
+        let mut file_names: Vec<(Option<SystemTime>, String)> = keys
+            .iter()
+            .map(|key| {
+                let file_name = key.rsplit('/').next().unwrap_or(key).to_string();
+                let accessed_at = accessed_at.get(&object_key(&file_name, file_path)).copied();
+                (accessed_at, file_name)
+            })
+            .collect();
+        file_names.sort();
+
+        Ok(file_names
+            .into_iter()
+            .map(|(_, file_name)| file_name)
+            .collect())
+    }
+
+    /// Deletes every object under `file_path` except the `keep_newest` most recently used ones,
+    /// returning the names of the deleted objects.
+    pub async fn cleanup_lru(
+        &self,
+        file_path: Option<&str>,
+        keep_newest: usize,
+    ) -> Result<Vec<String>, String> {
+        let mut file_names = self.list_objects_by_access_time(file_path).await?;
+        file_names.truncate(file_names.len().saturating_sub(keep_newest));
+
+        for file_name in &file_names {
+            self.delete_object(file_name, file_path).await?;
+        }
+
+        Ok(file_names)
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for AccessTracked<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let result = self.inner.fetch_object(file_name, file_path).await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let result = self
+            .inner
+            .fetch_compressed_object(file_name, file_path)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn list_objects_with_suffix(
+        &self,
+        file_path: Option<&str>,
+        suffix: &str,
+    ) -> Result<Vec<String>, String> {
+        self.inner.list_objects_with_suffix(file_path, suffix).await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let result = self.inner.fetch_with_etag(file_name, file_path).await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let result = self
+            .inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .fetch_generation(file_name, file_path, generation)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.touch(file_name, file_path).await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let result = self.inner.fetch_raw_object(file_name, file_path).await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        let result = self.inner.fetch_range(file_name, file_path, range).await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let result = self
+            .inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await;
+        self.record_result(target_file_name, target_file_path, result)
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.delete_object(file_name, file_path).await?;
+        self.accessed_at
+            .lock()
+            .unwrap()
+            .remove(&object_key(file_name, file_path));
+
+        Ok(())
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await;
+        self.record_result(file_name, file_path, result)
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
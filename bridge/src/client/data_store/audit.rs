@@ -0,0 +1,445 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::clock::{Clock, SystemClock};
+use super::key::object_key;
+use super::stats::StoreStats;
+use async_trait::async_trait;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOp {
+    Upload,
+    UploadCompressed,
+    UploadPrecompressed,
+    UploadIfEtagMatches,
+    UploadLocked,
+    UploadIdempotent,
+    UploadIfAbsent,
+    UploadCompressedIfAbsent,
+    Touch,
+    Copy,
+    Delete,
+}
+
+// One mutation attempt against the data store, recorded whether or not it succeeded
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditEvent {
+    pub op: AuditOp,
+    pub key: String,
+    pub size: usize, // Size of the payload handed to the driver before compression, 0 for copies and deletes
+    pub actor: String,
+    pub timestamp: u128, // Unix time in milliseconds
+    pub result: Result<(), String>,
+}
+
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent) -> Result<(), String>;
+}
+
+// Appends every event as one line of JSON to `writer`, e.g. an append-only log file
+pub struct JsonLinesAuditSink<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLinesAuditSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send> AuditSink for JsonLinesAuditSink<W> {
+    fn record(&self, event: &AuditEvent) -> Result<(), String> {
+        let mut line = serde_json::to_vec(event).map_err(|err| err.to_string())?;
+        line.push(b'\n');
+
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(&line).map_err(|err| err.to_string())?;
+        writer.flush().map_err(|err| err.to_string())
+    }
+}
+
+// Wraps any data store driver and reports every write, including failed ones, to an
+// `AuditSink`. Reads are passed through unaudited.
+pub struct Audited<D: DataStoreDriver> {
+    inner: D,
+    sink: Arc<dyn AuditSink>,
+    actor: String,
+    clock: Arc<dyn Clock>,
+}
+
+impl<D: DataStoreDriver> Audited<D> {
+    pub fn new(inner: D, sink: Arc<dyn AuditSink>, actor: &str) -> Self {
+        Self {
+            inner,
+            sink,
+            actor: actor.to_string(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn audit<T>(
+        &self,
+        op: AuditOp,
+        file_name: &str,
+        file_path: Option<&str>,
+        size: usize,
+        result: &Result<T, String>,
+    ) {
+        let event = AuditEvent {
+            op,
+            key: object_key(file_name, file_path),
+            size,
+            actor: self.actor.clone(),
+            timestamp: self.clock.unix_millis(),
+            result: result.as_ref().map(|_| ()).map_err(|err| err.clone()),
+        };
+        // The mutation already happened, so failing it now would misreport its outcome
+        if let Err(err) = self.sink.record(&event) {
+            eprintln!("Failed to record audit event for {}: {err}", event.key);
+        }
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for Audited<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.inner.fetch_object(file_name, file_path).await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.audit(
+            AuditOp::Upload,
+            file_name,
+            file_path,
+            contents.len(),
+            &result,
+        );
+
+        result
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.audit(
+            AuditOp::UploadCompressed,
+            file_name,
+            file_path,
+            contents.len(),
+            &result,
+        );
+
+        result
+    }
+
+    async fn list_objects_with_suffix(
+        &self,
+        file_path: Option<&str>,
+        suffix: &str,
+    ) -> Result<Vec<String>, String> {
+        self.inner.list_objects_with_suffix(file_path, suffix).await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.inner.fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await;
+        self.audit(
+            AuditOp::UploadIfEtagMatches,
+            file_name,
+            file_path,
+            contents.len(),
+            &result,
+        );
+
+        result
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await;
+        self.audit(
+            AuditOp::UploadLocked,
+            file_name,
+            file_path,
+            contents.len(),
+            &result,
+        );
+
+        result
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let result = self
+            .inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await;
+        // Skipped retries didn't write anything, so only actual uploads are audited
+        if !matches!(result, Ok(UploadOutcome::AlreadyApplied)) {
+            self.audit(
+                AuditOp::UploadIdempotent,
+                file_name,
+                file_path,
+                contents.len(),
+                &result,
+            );
+        }
+
+        result
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.inner
+            .fetch_generation(file_name, file_path, generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.audit(
+            AuditOp::UploadIfAbsent,
+            file_name,
+            file_path,
+            contents.len(),
+            &result,
+        );
+
+        result
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.audit(
+            AuditOp::UploadCompressedIfAbsent,
+            file_name,
+            file_path,
+            contents.len(),
+            &result,
+        );
+
+        result
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.touch(file_name, file_path).await;
+        self.audit(AuditOp::Touch, file_name, file_path, 0, &result);
+
+        result
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let result = self
+            .inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await;
+        self.audit(
+            AuditOp::Copy,
+            target_file_name,
+            target_file_path,
+            0,
+            &result,
+        );
+
+        result
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.delete_object(file_name, file_path).await;
+        self.audit(AuditOp::Delete, file_name, file_path, 0, &result);
+
+        result
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await;
+        self.audit(
+            AuditOp::UploadPrecompressed,
+            file_name,
+            file_path,
+            compressed.len(),
+            &result,
+        );
+
+        result
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
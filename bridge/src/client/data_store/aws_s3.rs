@@ -1,28 +1,259 @@
-use crate::{
-    error::err_to_string,
-    utils::{compress, decompress, DEFAULT_COMPRESSION_LEVEL},
-};
+use crate::error::{err_to_string, DataStoreError};
 
-use super::base::DataStoreDriver;
+use super::base::{
+    is_not_found, ByteRange, DataStoreDriver, DriverConfig, FetchResult, ObjectMetadata,
+    RecentObjects, RetentionMode, StoreCapabilities, UploadOutcome,
+};
+use super::dns_cache::CachingDnsResolver;
+use super::format::{check_encoded, content_encoding, is_encoded};
+use super::key::{check_key_length, list_prefix, object_key, parse_key};
+use super::retry_after::{ClockTimeSource, RetryAfterClassifier, RetryAfterPlugin};
+use super::retry_budget::{RetryBudget, RetryBudgetInterceptor, DEFAULT_RETRY_BUDGET};
 use async_trait::async_trait;
 use aws_sdk_s3::{
-    config::{Credentials, Region},
-    error::SdkError,
-    operation::put_object::{PutObjectError, PutObjectOutput},
-    primitives::ByteStream,
+    config::{http::HttpResponse, AppName, Credentials, Region},
+    error::{ProvideErrorMetadata, SdkError},
+    operation::{
+        list_objects_v2::ListObjectsV2Output,
+        put_object::{builders::PutObjectFluentBuilder, PutObjectError, PutObjectOutput},
+    },
+    primitives::{ByteStream, DateTime, DateTimeFormat},
+    types::{
+        BucketVersioningStatus, CompletedMultipartUpload, CompletedPart, EncodingType,
+        LifecycleRule, MetadataDirective, Object, ObjectCannedAcl, ObjectLockMode, RequestPayer,
+        StorageClass,
+    },
     Client, Config,
 };
+use aws_smithy_http_client::{
+    tls::{self, rustls_provider::CryptoMode},
+    Builder as HttpClientBuilder,
+};
+use aws_smithy_runtime_api::client::http::HttpClient;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use dotenv;
+use futures::{stream, StreamExt, TryStreamExt};
+use md5::{Digest, Md5};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 // To use this data store, create a .env file in the base directory with the following values:
 // export BRIDGE_AWS_ACCESS_KEY_ID="..."
 // export BRIDGE_AWS_SECRET_ACCESS_KEY="..."
 // export BRIDGE_AWS_REGION="..."
 // export BRIDGE_AWS_BUCKET="..."
+// Optionally, to use an S3-compatible endpoint such as LocalStack:
+// export BRIDGE_AWS_ENDPOINT_URL="..."
+// Optionally, to read from a requester-pays bucket (requests are billed to the caller's account):
+// export BRIDGE_AWS_REQUESTER_PAYS=true
+// Optionally, to change how many retry tokens are shared by all requests (default 500, each
+// retry costs 5 and each success returns 1):
+// export BRIDGE_AWS_RETRY_BUDGET=...
+// Optionally, to cache endpoint DNS lookups for the given number of seconds:
+// export BRIDGE_AWS_DNS_CACHE_TTL=...
+// Optionally, to identify this bridge instance in the user agent of every request (default
+// `bitvm-bridge-<version>`, letters, digits and `-._~` only):
+// export BRIDGE_AWS_UA_SUFFIX=...
+// Optionally, to abort multipart uploads under the bucket that were started more than this many
+// seconds ago when the data store starts (see `abort_incomplete_uploads`):
+// export BRIDGE_AWS_ABORT_INCOMPLETE_UPLOADS_AFTER=...
+// Optionally, to tag every uploaded object with `x-amz-meta-client`:
+// export BRIDGE_AWS_CLIENT_TAG=...
+// Optionally, to record the uncompressed and stored size of compressed uploads in their metadata
+// (see `with_size_metadata`):
+// export BRIDGE_AWS_SIZE_METADATA=true
+// Optionally, when built with the `debug-logging` feature:
+// export BRIDGE_AWS_DEBUG_BODIES=true
+
+#[cfg(feature = "debug-logging")]
+const DEBUG_BODY_PREVIEW_SIZE: usize = 256;
+// S3 requires every part but the last to be at least 5 MiB, and allows at most 10,000 parts
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+pub const MAX_PARTS: u64 = 10_000;
+// S3 rejects keys longer than this many bytes
+pub const MAX_KEY_LENGTH: usize = 1024;
+// Regions accepted by `validate_region` without a custom endpoint
+pub const KNOWN_REGIONS: [&str; 37] = [
+    "af-south-1",
+    "ap-east-1",
+    "ap-east-2",
+    "ap-northeast-1",
+    "ap-northeast-2",
+    "ap-northeast-3",
+    "ap-south-1",
+    "ap-south-2",
+    "ap-southeast-1",
+    "ap-southeast-2",
+    "ap-southeast-3",
+    "ap-southeast-4",
+    "ap-southeast-5",
+    "ap-southeast-7",
+    "ca-central-1",
+    "ca-west-1",
+    "cn-north-1",
+    "cn-northwest-1",
+    "eu-central-1",
+    "eu-central-2",
+    "eu-north-1",
+    "eu-south-1",
+    "eu-south-2",
+    "eu-west-1",
+    "eu-west-2",
+    "eu-west-3",
+    "il-central-1",
+    "me-central-1",
+    "me-south-1",
+    "mx-central-1",
+    "sa-east-1",
+    "us-east-1",
+    "us-east-2",
+    "us-gov-east-1",
+    "us-gov-west-1",
+    "us-west-1",
+    "us-west-2",
+];
+// S3 rejects signatures more than 15 minutes off, a signing error with a smaller difference than
+// this is a genuine credential problem and is reported as is
+const CLOCK_SKEW_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_UA_SUFFIX: &str = concat!("bitvm-bridge-", env!("CARGO_PKG_VERSION"));
+// User-defined metadata key sent as the `x-amz-meta-client` header
+const CLIENT_TAG_METADATA_KEY: &str = "client";
+// Sent as `x-amz-meta-idempotency-key` by `upload_object_idempotent`
+const IDEMPOTENCY_KEY_METADATA_KEY: &str = "idempotency-key";
+// Sent as `x-amz-meta-generation` on every upload when generations are enabled
+const GENERATION_METADATA_KEY: &str = "generation";
+// Sent as `x-amz-meta-raw-size` and `x-amz-meta-stored-size` by compressed uploads
+const RAW_SIZE_METADATA_KEY: &str = "raw-size";
+const STORED_SIZE_METADATA_KEY: &str = "stored-size";
+// HEAD requests in flight at a time when listings read the size metadata of every object
+const METADATA_HEAD_CONCURRENCY: usize = 8;
+
+/// The bucket settings that features such as versioning-aware reads, TTLs and archival rely on.
+#[derive(Clone, Debug)]
+pub struct BucketConfigReport {
+    /// `None` if versioning was never enabled on the bucket.
+    pub versioning: Option<BucketVersioningStatus>,
+    pub lifecycle_rules: Vec<LifecycleRule>,
+}
+
+impl BucketConfigReport {
+    pub fn versioning_enabled(&self) -> bool {
+        self.versioning == Some(BucketVersioningStatus::Enabled)
+    }
+}
+
+// Upper bound on each kind of request, including the SDK's retries. Listings are bounded per page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeouts {
+    pub get: Duration,
+    pub put: Duration, // Per part for multipart uploads
+    pub list: Duration,
+    pub head: Duration,
+    pub delete: Duration,
+    pub copy: Duration, // Server-side copies, including archiving and touching
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            get: Duration::from_secs(60),
+            put: Duration::from_secs(120),
+            list: Duration::from_secs(120),
+            head: Duration::from_secs(10),
+            delete: Duration::from_secs(30),
+            copy: Duration::from_secs(300),
+        }
+    }
+}
+
+// How streamed uploads are split into multipart parts. Given the object's length, the part size
+// is picked so the upload takes between `min_parts` and `max_parts` parts, staying as close to
+// `default_part_size` as that allows. Objects of unknown length are sent in parts of
+// `default_part_size`. S3's part limits take precedence over the target range, so small objects
+// may take fewer parts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartSizing {
+    pub default_part_size: usize,
+    pub min_parts: u64,
+    pub max_parts: u64,
+}
+
+impl Default for PartSizing {
+    fn default() -> Self {
+        Self {
+            default_part_size: 8 * 1024 * 1024,
+            min_parts: 10,
+            max_parts: 100,
+        }
+    }
+}
+
+impl PartSizing {
+    /// The part size for an upload of `content_length` bytes, if known.
+    pub fn part_size(&self, content_length: Option<u64>) -> usize {
+        let mut part_size = self.default_part_size as u64;
+        if let Some(length) = content_length {
+            if self.min_parts > 0 {
+                part_size = part_size.min(length / self.min_parts);
+            }
+            part_size = part_size.max(length.div_ceil(self.max_parts.clamp(1, MAX_PARTS)));
+        }
+
+        part_size.max(MIN_PART_SIZE as u64) as usize
+    }
+}
+
+// Fails `request` with a timeout error once it has run for longer than `timeout`
+async fn timed<T, E>(
+    timeout: Duration,
+    request: impl Future<Output = Result<T, SdkError<E, HttpResponse>>>,
+) -> Result<T, SdkError<E, HttpResponse>> {
+    tokio::time::timeout(timeout, request)
+        .await
+        .unwrap_or_else(|elapsed| Err(SdkError::timeout_error(elapsed)))
+}
+
+// Like `timed`, for fetching the next page of a paginated request
+async fn timed_page<T, E>(
+    timeout: Duration,
+    page: impl Future<Output = Option<Result<T, SdkError<E, HttpResponse>>>>,
+) -> Option<Result<T, SdkError<E, HttpResponse>>> {
+    tokio::time::timeout(timeout, page)
+        .await
+        .unwrap_or_else(|elapsed| Some(Err(SdkError::timeout_error(elapsed))))
+}
 
 pub struct AwsS3 {
     client: Client,
     bucket: String,
+    config: DriverConfig,
+    requester_pays: bool,
+    retry_budget: Arc<RetryBudget>,
+    // Requests are sent unsigned, so only reads of public objects succeed
+    anonymous: bool,
+    // Stored as `x-amz-meta-client` on every uploaded object
+    client_tag: Option<String>,
+    // Whether the backend accepts storage classes other than the default, Wasabi doesn't
+    storage_classes: bool,
+    // Whether the bucket has Object Lock enabled, deletes then check the object's retention
+    object_lock: bool,
+    // Whether uploads are stamped with a generation, at the cost of one extra request each
+    generations: bool,
+    // Whether compressed uploads record their sizes, and listings read them back
+    size_metadata: bool,
+    // Canned ACL of every uploaded object, None keeps the bucket's default
+    acl: Option<ObjectCannedAcl>,
+    // Whether compressed uploads declare their codec as `Content-Encoding`
+    content_encoding: bool,
+    timeouts: Timeouts,
+    part_sizing: PartSizing,
+    #[cfg(feature = "debug-logging")]
+    debug_bodies: bool,
 }
 
 impl AwsS3 {
@@ -37,109 +268,1061 @@ impl AwsS3 {
             return None;
         }
 
+        // S3-compatible backends name their regions freely
+        let region = region.unwrap();
+        let endpoint_url = dotenv::var("BRIDGE_AWS_ENDPOINT_URL").ok();
+        if let Err(err) = validate_region(&region, endpoint_url.is_some()) {
+            eprintln!("{err}");
+            return None;
+        }
+
         let credentials =
             Credentials::new(access_key.unwrap(), secret.unwrap(), None, None, "Bridge");
 
-        let config = Config::builder()
+        let retry_budget = Arc::new(RetryBudget::new(
+            dotenv::var("BRIDGE_AWS_RETRY_BUDGET")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(DEFAULT_RETRY_BUDGET),
+        ));
+        let mut config = Config::builder()
             .credentials_provider(credentials)
-            .region(Region::new(region.unwrap()))
+            .region(Region::new(region))
+            .interceptor(RetryBudgetInterceptor::new(retry_budget.clone()))
+            .retry_classifier(RetryAfterClassifier)
+            .runtime_plugin(RetryAfterPlugin)
+            .app_name(app_name_from_env())
+            .behavior_version_latest();
+        if let Some(endpoint_url) = endpoint_url {
+            // S3-compatible endpoints generally don't support virtual-hosted-style addressing
+            config = config.endpoint_url(endpoint_url).force_path_style(true);
+        }
+        let config = config.build();
+
+        let store = Self {
+            client: Client::from_conf(config),
+            bucket: bucket.unwrap(),
+            config: DriverConfig::from_env(),
+            requester_pays: dotenv::var("BRIDGE_AWS_REQUESTER_PAYS")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+            retry_budget,
+            anonymous: false,
+            client_tag: dotenv::var("BRIDGE_AWS_CLIENT_TAG").ok(),
+            storage_classes: true,
+            object_lock: false,
+            generations: false,
+            size_metadata: dotenv::var("BRIDGE_AWS_SIZE_METADATA")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+            acl: None,
+            content_encoding: dotenv::var("BRIDGE_AWS_CONTENT_ENCODING")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+            timeouts: Timeouts::default(),
+            part_sizing: PartSizing::default(),
+            #[cfg(feature = "debug-logging")]
+            debug_bodies: dotenv::var("BRIDGE_AWS_DEBUG_BODIES")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+        };
+
+        match dotenv::var("BRIDGE_AWS_DNS_CACHE_TTL")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            Some(ttl) => Some(store.with_dns_cache_ttl(Duration::from_secs(ttl))),
+            None => Some(store),
+        }
+    }
+
+    /// Like `new`, but also probes the bucket, so missing or wrong credentials and an
+    /// inaccessible bucket are reported at startup instead of failing the first operation.
+    pub async fn new_validated() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+        if let Ok(region) = dotenv::var("BRIDGE_AWS_REGION") {
+            validate_region(&region, dotenv::var("BRIDGE_AWS_ENDPOINT_URL").is_ok())?;
+        }
+        let store = Self::new().ok_or(
+            "Missing AWS S3 settings, set BRIDGE_AWS_ACCESS_KEY_ID, BRIDGE_AWS_SECRET_ACCESS_KEY, BRIDGE_AWS_REGION and BRIDGE_AWS_BUCKET",
+        )?;
+        store.health_check().await?;
+
+        Ok(store)
+    }
+
+    /// Builds a store that reads a public-read bucket without any credentials. Requests are not
+    /// signed, so every write fails with `Unsupported`.
+    pub fn anonymous(region: &str, bucket: &str) -> Self {
+        let retry_budget = Arc::new(RetryBudget::new(DEFAULT_RETRY_BUDGET));
+        let config = Config::builder()
+            .allow_no_auth()
+            .region(Region::new(region.to_string()))
+            .interceptor(RetryBudgetInterceptor::new(retry_budget.clone()))
+            .retry_classifier(RetryAfterClassifier)
+            .runtime_plugin(RetryAfterPlugin)
+            .app_name(app_name_from_env())
             .behavior_version_latest()
             .build();
 
-        Some(Self {
+        Self {
             client: Client::from_conf(config),
-            bucket: bucket.unwrap(),
-        })
+            bucket: bucket.to_string(),
+            config: DriverConfig::from_env(),
+            requester_pays: false,
+            retry_budget,
+            anonymous: true,
+            client_tag: None,
+            storage_classes: true,
+            object_lock: false,
+            generations: false,
+            size_metadata: false,
+            acl: None,
+            content_encoding: false,
+            timeouts: Timeouts::default(),
+            part_sizing: PartSizing::default(),
+            #[cfg(feature = "debug-logging")]
+            debug_bodies: false,
+        }
     }
 
-    async fn get_object(&self, key: &str, file_path: Option<&str>) -> Result<Vec<u8>, String> {
-        let key_with_prefix;
-        if let Some(path) = file_path {
-            key_with_prefix = format! {"{path}/{key}"};
+    /// Builds a store for a bucket on Wasabi, which is S3-compatible but only reachable through
+    /// its region-specific endpoint and has no storage classes.
+    pub fn for_wasabi(region: &str, access_key: &str, secret: &str, bucket: &str) -> Self {
+        let retry_budget = Arc::new(RetryBudget::new(DEFAULT_RETRY_BUDGET));
+        let config = Config::builder()
+            .credentials_provider(Credentials::new(access_key, secret, None, None, "Bridge"))
+            .region(Region::new(region.to_string()))
+            .endpoint_url(format!("https://s3.{region}.wasabisys.com"))
+            .interceptor(RetryBudgetInterceptor::new(retry_budget.clone()))
+            .retry_classifier(RetryAfterClassifier)
+            .runtime_plugin(RetryAfterPlugin)
+            .app_name(app_name_from_env())
+            .behavior_version_latest()
+            .build();
+
+        Self {
+            client: Client::from_conf(config),
+            bucket: bucket.to_string(),
+            config: DriverConfig::from_env(),
+            requester_pays: false,
+            retry_budget,
+            anonymous: false,
+            client_tag: dotenv::var("BRIDGE_AWS_CLIENT_TAG").ok(),
+            storage_classes: false,
+            object_lock: false,
+            generations: false,
+            size_metadata: false,
+            acl: None,
+            content_encoding: false,
+            timeouts: Timeouts::default(),
+            part_sizing: PartSizing::default(),
+            #[cfg(feature = "debug-logging")]
+            debug_bodies: false,
+        }
+    }
+
+    /// Replaces the whole `DriverConfig` read from the environment. The SDK reads the time from
+    /// its clock too.
+    pub fn with_config(mut self, config: DriverConfig) -> Self {
+        let sdk_config = self
+            .client
+            .config()
+            .to_builder()
+            .time_source(ClockTimeSource(config.clock.clone()))
+            .build();
+        self.client = Client::from_conf(sdk_config);
+        self.config = config;
+        self
+    }
+
+    /// Acknowledges that reads from the bucket are billed to the caller's account, as required
+    /// to read from requester-pays buckets. Without it such buckets reject reads as AccessDenied.
+    pub fn with_requester_pays(mut self, requester_pays: bool) -> Self {
+        self.requester_pays = requester_pays;
+        self
+    }
+
+    /// Resizes the retry budget shared by every request of this store and the views returned by
+    /// `with_credentials`.
+    pub fn with_retry_budget(self, capacity: u32) -> Self {
+        self.retry_budget.reset(capacity);
+        self
+    }
+
+    /// Tags every object uploaded from now on with `x-amz-meta-client: <client_tag>`.
+    pub fn with_client_tag(mut self, client_tag: &str) -> Self {
+        self.client_tag = Some(client_tag.to_string());
+        self
+    }
+
+    /// Declares that the bucket was created with Object Lock enabled, which enables
+    /// `upload_object_locked` and makes `delete_object` fail with `Locked` for objects whose
+    /// retention period hasn't ended yet, at the cost of one extra request per delete.
+    pub fn with_object_lock(mut self, object_lock: bool) -> Self {
+        self.object_lock = object_lock;
+        self
+    }
+
+    /// Stamps every upload with `x-amz-meta-generation`, one more than the generation of the
+    /// object it replaces, so `fetch_generation` can find the version written at a known logical
+    /// version. Costs a HEAD request per upload. Generations only increase while a single writer
+    /// updates an object, and start over at 1 once the object was deleted.
+    pub fn with_generations(mut self, generations: bool) -> Self {
+        self.generations = generations;
+        self
+    }
+
+    /// Records the size before compression and the stored size of every compressed upload in
+    /// its metadata, so `list_object_metadata` and `prefix_footprint` can report the logical size
+    /// of a prefix without downloading anything. Listings then cost one HEAD request per object.
+    pub fn with_size_metadata(mut self, size_metadata: bool) -> Self {
+        self.size_metadata = size_metadata;
+        self
+    }
+
+    /// Applies the canned `acl` to every object uploaded or copied from now on, e.g.
+    /// `ObjectCannedAcl::PublicRead` for a store holding artifacts served by URL. None, the
+    /// default, sends no ACL so objects get the bucket's default, which is private.
+    ///
+    /// ACLs only take effect on buckets whose Object Ownership setting isn't "bucket owner
+    /// enforced", the default for new buckets. Such buckets reject uploads with an ACL other than
+    /// `BucketOwnerFullControl`, which surfaces as `Unsupported`. On top of that, Block Public
+    /// Access rejects public ACLs with AccessDenied unless its `BlockPublicAcls` setting is off for
+    /// both the bucket and the account. To keep private and public artifacts in one bucket, build
+    /// a second store with this setting for the public ones.
+    pub fn with_acl(mut self, acl: Option<ObjectCannedAcl>) -> Self {
+        self.acl = acl;
+        self
+    }
+
+    /// Sends compressed uploads that are plain zstd frames with `Content-Encoding: zstd`, so
+    /// browsers and CDNs serving them by URL decompress them transparently. Objects stored
+    /// uncompressed or compressed with a dictionary are uploaded without the header. This store
+    /// never asks S3 to decode anything, S3 returns objects as stored whatever their encoding.
+    ///
+    /// A CDN or proxy in front of a custom endpoint may decompress such objects before this store
+    /// receives them, so with the setting on, compressed fetches return bodies without an object
+    /// format header as they are rather than decompressing them again. This also means a corrupt
+    /// header is no longer detected. Browsers only accept zstd frames with windows of up to 8 MiB,
+    /// which the default compression level stays within.
+    pub fn with_content_encoding(mut self, content_encoding: bool) -> Self {
+        self.content_encoding = content_encoding;
+        self
+    }
+
+    // Fails with `Locked` if the object is retained until some time in the future. On versioned
+    // buckets S3 would otherwise accept the delete by hiding the object behind a delete marker.
+    async fn check_not_locked(&self, key: &str) -> Result<(), String> {
+        let output = match timed(
+            self.timeouts.head,
+            self.client
+                .get_object_retention()
+                .bucket(&self.bucket)
+                .key(key)
+                .send(),
+        )
+        .await
+        {
+            Ok(output) => output,
+            Err(err) if err.code() == Some("NoSuchObjectLockConfiguration") => return Ok(()),
+            Err(err) => match self.classify_error(key, &err) {
+                Some(DataStoreError::NotFound(_)) => return Ok(()),
+                Some(err) => return Err(err.to_string()),
+                None => return Err(format!("Failed to read retention of {}: {}", key, err)),
+            },
+        };
+
+        let retain_until = output
+            .retention()
+            .and_then(|retention| retention.retain_until_date())
+            .and_then(|date| SystemTime::try_from(*date).ok());
+        match retain_until {
+            Some(retain_until) if retain_until > self.config.clock.now() => {
+                Err(DataStoreError::Locked(key.to_string()).to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Overrides the default timeout of every kind of request.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Overrides how streamed uploads are split into parts, see `PartSizing`.
+    pub fn with_part_sizing(mut self, part_sizing: PartSizing) -> Self {
+        self.part_sizing = part_sizing;
+        self
+    }
+
+    fn check_key(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        check_key_length(file_name, file_path, self.max_key_length())
+    }
+
+    fn request_payer(&self) -> Option<RequestPayer> {
+        self.requester_pays.then_some(RequestPayer::Requester)
+    }
+
+    /// Caches endpoint DNS lookups for `ttl` instead of leaving resolution to the SDK's default
+    /// HTTP client. This smooths out latency spikes under bursty load, at the cost of only
+    /// picking up changed endpoint addresses (e.g. after a failover) once the cache expires.
+    pub fn with_dns_cache_ttl(mut self, ttl: Duration) -> Self {
+        let http_client = HttpClientBuilder::new()
+            .tls_provider(tls::Provider::Rustls(CryptoMode::AwsLc))
+            .build_with_resolver(CachingDnsResolver::new(ttl));
+        let config = self
+            .client
+            .config()
+            .to_builder()
+            .http_client(http_client)
+            .build();
+        self.client = Client::from_conf(config);
+        self
+    }
+
+    /// Sends every request through `http_client` instead of the SDK's default HTTP client, e.g.
+    /// an in-process fake of S3 in tests.
+    pub fn with_http_client(mut self, http_client: impl HttpClient + 'static) -> Self {
+        let config = self
+            .client
+            .config()
+            .to_builder()
+            .http_client(http_client)
+            .build();
+        self.client = Client::from_conf(config);
+        self
+    }
+
+    /// Enables trace-level logging of a truncated preview of every uploaded and downloaded body.
+    #[cfg(feature = "debug-logging")]
+    pub fn with_debug_bodies(mut self, debug_bodies: bool) -> Self {
+        self.debug_bodies = debug_bodies;
+        self
+    }
+
+    #[cfg(feature = "debug-logging")]
+    fn log_body(&self, operation: &str, key: &str, body: &[u8]) {
+        if !self.debug_bodies {
+            return;
+        }
+
+        let preview = &body[..body.len().min(DEBUG_BODY_PREVIEW_SIZE)];
+        let ellipsis = if body.len() > preview.len() {
+            "..."
         } else {
-            key_with_prefix = key.to_string();
+            ""
+        };
+        let text = match std::str::from_utf8(preview) {
+            Ok(text) => Some(text),
+            // The preview may cut a multi-byte character in half
+            Err(err) if err.error_len().is_none() => {
+                std::str::from_utf8(&preview[..err.valid_up_to()]).ok()
+            }
+            Err(_) => None,
+        };
+        match text {
+            Some(text) => log::trace!(
+                "{operation} {key} ({} bytes, utf8): {text}{ellipsis}",
+                body.len()
+            ),
+            None => log::trace!(
+                "{operation} {key} ({} bytes, hex): {}{ellipsis}",
+                body.len(),
+                preview
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<String>()
+            ),
         }
+    }
 
-        let mut data = self
+    /// Returns a view of this store that signs its requests with `credentials`, e.g. to escalate
+    /// from the default read-only credentials for a single privileged write. The view reuses the
+    /// existing SDK config, including its HTTP client and connection pool.
+    pub fn with_credentials(&self, credentials: Credentials) -> Self {
+        let config = self
             .client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(key_with_prefix)
-            .send()
+            .config()
+            .to_builder()
+            .credentials_provider(credentials)
+            .build();
+
+        Self {
+            client: Client::from_conf(config),
+            bucket: self.bucket.clone(),
+            config: self.config.clone(),
+            requester_pays: self.requester_pays,
+            retry_budget: self.retry_budget.clone(),
+            anonymous: false,
+            client_tag: self.client_tag.clone(),
+            storage_classes: self.storage_classes,
+            object_lock: self.object_lock,
+            generations: self.generations,
+            size_metadata: self.size_metadata,
+            acl: self.acl.clone(),
+            content_encoding: self.content_encoding,
+            timeouts: self.timeouts,
+            part_sizing: self.part_sizing,
+            #[cfg(feature = "debug-logging")]
+            debug_bodies: self.debug_bodies,
+        }
+    }
+
+    // Writes need signed requests, which anonymous stores can't send
+    fn check_signed(&self, operation: &'static str) -> Result<(), String> {
+        match self.anonymous {
+            true => Err(DataStoreError::Unsupported(operation).to_string()),
+            false => Ok(()),
+        }
+    }
+
+    /// Reads the versioning status and lifecycle rules of the bucket, so operators can check at
+    /// startup that it is configured for the features they rely on.
+    pub async fn bucket_config_report(&self) -> Result<BucketConfigReport, String> {
+        let versioning = timed(
+            self.timeouts.head,
+            self.client
+                .get_bucket_versioning()
+                .bucket(&self.bucket)
+                .send(),
+        )
+        .await
+        .map_err(|err| match self.classify_error("", &err) {
+            Some(err) => err.to_string(),
+            None => format!("Unable to read bucket versioning: {}", err),
+        })?;
+
+        let lifecycle_rules = match timed(
+            self.timeouts.head,
+            self.client
+                .get_bucket_lifecycle_configuration()
+                .bucket(&self.bucket)
+                .send(),
+        )
+        .await
+        {
+            Ok(output) => output.rules().to_vec(),
+            Err(err) if err.code() == Some("NoSuchLifecycleConfiguration") => vec![],
+            Err(err) => {
+                return Err(match self.classify_error("", &err) {
+                    Some(err) => err.to_string(),
+                    None => format!("Unable to read bucket lifecycle rules: {}", err),
+                })
+            }
+        };
+
+        Ok(BucketConfigReport {
+            versioning: versioning.status().cloned(),
+            lifecycle_rules,
+        })
+    }
+
+    /// Aborts multipart uploads under `file_path` that were started more than `older_than` ago,
+    /// e.g. by a process that crashed mid-upload, and returns how many were aborted. Their
+    /// uploaded parts are billed until the upload is aborted. A lifecycle rule with
+    /// `AbortIncompleteMultipartUpload` is the durable fix, this is an in-process cleanup for
+    /// buckets without one.
+    pub async fn abort_incomplete_uploads(
+        &self,
+        file_path: Option<&str>,
+        older_than: Duration,
+    ) -> Result<usize, String> {
+        self.check_signed("abort_incomplete_uploads")?;
+        self.config.check_write()?;
+
+        let mut stale = vec![];
+        let mut key_marker = None;
+        let mut upload_id_marker = None;
+        loop {
+            let output = timed(
+                self.timeouts.list,
+                self.client
+                    .list_multipart_uploads()
+                    .bucket(&self.bucket)
+                    .prefix(list_prefix(file_path))
+                    .set_request_payer(self.request_payer())
+                    .set_key_marker(key_marker)
+                    .set_upload_id_marker(upload_id_marker)
+                    .send(),
+            )
             .await
-            .map_err(err_to_string)?;
+            .map_err(|err| format!("Unable to list multipart uploads: {}", err))?;
 
-        let mut buffer: Vec<u8> = vec![];
-        while let Some(bytes) = data.body.try_next().await.map_err(err_to_string)? {
-            buffer.append(&mut bytes.to_vec());
+            for upload in output.uploads() {
+                let initiated = upload
+                    .initiated()
+                    .and_then(|time| SystemTime::try_from(*time).ok());
+                if let (Some(key), Some(upload_id), Some(initiated)) =
+                    (upload.key(), upload.upload_id(), initiated)
+                {
+                    if self.config.clock.elapsed_since(initiated) > older_than {
+                        stale.push((key.to_string(), upload_id.to_string()));
+                    }
+                }
+            }
+
+            if !output.is_truncated().unwrap_or(false) {
+                break;
+            }
+            key_marker = output.next_key_marker().map(String::from);
+            upload_id_marker = output.next_upload_id_marker().map(String::from);
+        }
+
+        for (key, upload_id) in &stale {
+            timed(
+                self.timeouts.delete,
+                self.client
+                    .abort_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .set_request_payer(self.request_payer())
+                    .send(),
+            )
+            .await
+            .map_err(|err| format!("Failed to abort upload of {}: {}", key, err))?;
         }
 
-        Ok(buffer)
+        Ok(stale.len())
     }
 
-    async fn upload_object(
+    /// Fails unless versioning is enabled on the bucket, so overwritten or deleted objects can
+    /// be recovered.
+    pub async fn require_versioning(&self) -> Result<(), String> {
+        match self.bucket_config_report().await?.versioning_enabled() {
+            true => Ok(()),
+            false => Err(DataStoreError::VersioningDisabled(self.bucket.clone()).to_string()),
+        }
+    }
+
+    /// Checks that the configured bucket exists and is reachable with the configured credentials.
+    pub async fn health_check(&self) -> Result<(), String> {
+        match timed(
+            self.timeouts.head,
+            self.client.head_bucket().bucket(&self.bucket).send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(()),
+            // HEAD responses carry no error code, so a 404 here can only mean a missing bucket
+            Err(err)
+                if err
+                    .raw_response()
+                    .is_some_and(|r| r.status().as_u16() == 404) =>
+            {
+                Err(DataStoreError::BucketNotFound(self.bucket.clone()).to_string())
+            }
+            Err(err)
+                if err
+                    .raw_response()
+                    .is_some_and(|r| r.status().as_u16() == 403) =>
+            {
+                Err(DataStoreError::AccessDenied(self.bucket.clone()).to_string())
+            }
+            Err(err) => match self.classify_error("", &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Health check failed: {}", err)),
+            },
+        }
+    }
+
+    /// Moves every object under `file_path` last modified more than `age` ago to
+    /// `target_class`, e.g. `StorageClass::Glacier`, by copying it onto itself with the new
+    /// storage class. Objects already in `target_class` are skipped. Returns the number of
+    /// objects transitioned.
+    pub async fn archive_older_than(
         &self,
-        key: &str,
-        data: ByteStream,
         file_path: Option<&str>,
-    ) -> Result<PutObjectOutput, SdkError<PutObjectError>> {
-        let key_with_prefix;
-        if let Some(path) = file_path {
-            key_with_prefix = format! {"{path}/{key}"};
-        } else {
-            key_with_prefix = key.to_string();
+        age: Duration,
+        target_class: StorageClass,
+    ) -> Result<usize, String> {
+        self.check_signed("archive_older_than")?;
+        if !self.storage_classes {
+            return Err(DataStoreError::Unsupported("archive_older_than").to_string());
         }
+        self.config.check_write()?;
 
-        self.client
-            .put_object()
+        let mut response = self
+            .client
+            .list_objects_v2()
+            .set_request_payer(self.request_payer())
+            .prefix(list_prefix(file_path))
             .bucket(&self.bucket)
-            .key(key_with_prefix)
-            .body(data)
-            .send()
+            .encoding_type(EncodingType::Url)
+            .into_paginator()
+            .send();
+
+        let mut candidates = vec![];
+        while let Some(result) = timed_page(self.timeouts.list, response.next()).await {
+            let output = result.map_err(|err| format!("Unable to list objects: {}", err))?;
+            for object in output.contents() {
+                let last_modified = object
+                    .last_modified()
+                    .and_then(|time| SystemTime::try_from(*time).ok());
+                if let (Some(key), Some(last_modified)) =
+                    (listed_key(&output, object)?, last_modified)
+                {
+                    if self.config.clock.elapsed_since(last_modified) > age {
+                        candidates.push(key);
+                    }
+                }
+            }
+        }
+
+        let mut archived = 0;
+        for key in candidates {
+            let head = timed(
+                self.timeouts.head,
+                self.client
+                    .head_object()
+                    .set_request_payer(self.request_payer())
+                    .bucket(&self.bucket)
+                    .key(&key)
+                    .send(),
+            )
+            .await
+            .map_err(|err| format!("Failed to check object {}: {}", key, err))?;
+            // S3 omits the storage class of STANDARD objects
+            let current_class = head.storage_class.unwrap_or(StorageClass::Standard);
+            if current_class == target_class {
+                continue;
+            }
+
+            timed(
+                self.timeouts.copy,
+                self.client
+                    .copy_object()
+                    .bucket(&self.bucket)
+                    .copy_source(self.copy_source(&key))
+                    .key(&key)
+                    .storage_class(target_class.clone())
+                    .set_acl(self.acl.clone())
+                    .send(),
+            )
             .await
+            .map_err(|err| format!("Failed to archive {}: {}", key, err))?;
+            archived += 1;
+        }
+
+        Ok(archived)
     }
-}
 
-#[async_trait]
-impl DataStoreDriver for AwsS3 {
-    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
-        let mut prefix = String::from("");
-        if let Some(path) = file_path {
-            prefix = format! {"{path}/"};
+    // Maps the S3 errors operators need to act on to typed data store errors
+    fn classify_error<E: ProvideErrorMetadata>(
+        &self,
+        key: &str,
+        err: &SdkError<E, HttpResponse>,
+    ) -> Option<DataStoreError> {
+        match err.code() {
+            Some("NoSuchBucket") => Some(DataStoreError::BucketNotFound(self.bucket.clone())),
+            Some("NoSuchKey") | Some("NotFound") => Some(DataStoreError::NotFound(key.to_string())),
+            Some("PreconditionFailed") | Some("ConditionalRequestConflict") => {
+                Some(DataStoreError::PreconditionFailed(key.to_string()))
+            }
+            Some("BadDigest") | Some("InvalidDigest") => {
+                Some(DataStoreError::IntegrityError(key.to_string()))
+            }
+            // The bucket enforces bucket owner ownership, which disables ACLs
+            Some("AccessControlListNotSupported") => Some(DataStoreError::Unsupported("acl")),
+            // Returned once the SDK's retries of a throttled request are used up
+            Some("SlowDown") => Some(DataStoreError::SlowDown(key.to_string())),
+            // A skewed clock surfaces as a bad signature, tell it apart using the server's Date header
+            Some("RequestTimeTooSkewed") | Some("SignatureDoesNotMatch") | Some("AccessDenied") => {
+                err.raw_response()
+                    .and_then(|r| r.headers().get("date"))
+                    .and_then(|date| clock_skew(date, self.config.clock.now()))
+                    .filter(|skew| skew.unsigned_abs() >= CLOCK_SKEW_THRESHOLD.as_secs())
+                    .map(|skew| DataStoreError::ClockSkew { skew })
+            }
+            _ if err
+                .raw_response()
+                .is_some_and(|r| r.status().as_u16() == 404) =>
+            {
+                Some(DataStoreError::NotFound(key.to_string()))
+            }
+            // The region or its network path is down, rather than the request being at fault
+            _ if matches!(
+                err,
+                SdkError::TimeoutError(_) | SdkError::DispatchFailure(_)
+            ) || err
+                .raw_response()
+                .is_some_and(|r| r.status().is_server_error()) =>
+            {
+                Some(DataStoreError::Unavailable(key.to_string()))
+            }
+            _ => None,
         }
+    }
+
+    async fn get_object(&self, key: &str, file_path: Option<&str>) -> Result<Vec<u8>, String> {
+        self.get_object_with_etag(key, file_path)
+            .await
+            .map(|(buffer, _)| buffer)
+    }
 
+    async fn get_object_with_etag(
+        &self,
+        key: &str,
+        file_path: Option<&str>,
+    ) -> Result<(Vec<u8>, Option<String>), String> {
+        let key_with_prefix = object_key(key, file_path);
+
+        let mut data = timed(
+            self.timeouts.get,
+            self.client
+                .get_object()
+                .set_request_payer(self.request_payer())
+                .bucket(&self.bucket)
+                .key(&key_with_prefix)
+                .send(),
+        )
+        .await
+        .map_err(|err| {
+            self.classify_error(&key_with_prefix, &err)
+                .map(|err| err.to_string())
+                .unwrap_or_else(|| err.to_string())
+        })?;
+
+        let mut buffer: Vec<u8> = vec![];
+        while let Some(bytes) = data.body.try_next().await.map_err(err_to_string)? {
+            buffer.append(&mut bytes.to_vec());
+        }
+
+        #[cfg(feature = "debug-logging")]
+        self.log_body("GET", &key_with_prefix, &buffer);
+
+        Ok((buffer, data.e_tag))
+    }
+
+    // Applies `filter` to each page as it arrives so rejected keys are never accumulated
+    async fn list_keys(
+        &self,
+        file_path: Option<&str>,
+        filter: impl Fn(&str) -> bool,
+    ) -> Result<Vec<String>, String> {
         let mut response = self
             .client
             .list_objects_v2()
-            .prefix(prefix)
+            .set_request_payer(self.request_payer())
+            .prefix(list_prefix(file_path))
             .bucket(&self.bucket)
+            .encoding_type(EncodingType::Url)
             .max_keys(50) // Paginate 50 results at a time
             .into_paginator()
             .send();
 
         let mut keys: Vec<String> = vec![];
-        while let Some(result) = response.next().await {
+        while let Some(result) = timed_page(self.timeouts.list, response.next()).await {
             match result {
                 Ok(output) => {
                     for object in output.contents() {
-                        keys.push(object.key().unwrap_or("Unknown").to_string());
+                        let Some(key) = listed_key(&output, object)? else {
+                            continue;
+                        };
+                        if filter(&key) {
+                            keys.push(key);
+                        }
+                    }
+                    if self.config.keys_exhausted(keys.len()) {
+                        break;
                     }
                 }
                 Err(err) => {
                     eprintln!("{err:?}");
-                    return Err("Unable to list objects".to_string());
+                    match self.classify_error(&list_prefix(file_path), &err) {
+                        Some(err @ DataStoreError::BucketNotFound(_))
+                        | Some(err @ DataStoreError::Unavailable(_)) => return Err(err.to_string()),
+                        _ => return Err("Unable to list objects".to_string()),
+                    }
                 }
             }
         }
 
+        self.config.cap_keys(&mut keys, file_path)?;
+
         Ok(keys)
     }
 
+    async fn upload_object(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        file_path: Option<&str>,
+        generation: Option<u64>,
+    ) -> Result<PutObjectOutput, SdkError<PutObjectError>> {
+        timed(
+            self.timeouts.put,
+            self.put_object(key, data, file_path, generation).send(),
+        )
+        .await
+    }
+
+    // Builds a PUT request so callers can add preconditions before sending it
+    fn put_object(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        file_path: Option<&str>,
+        generation: Option<u64>,
+    ) -> PutObjectFluentBuilder {
+        let key_with_prefix = object_key(key, file_path);
+
+        #[cfg(feature = "debug-logging")]
+        self.log_body("PUT", &key_with_prefix, &data);
+
+        // S3 verifies the body against this digest and rejects the PUT with `BadDigest`
+        // if it was corrupted in transit
+        let content_md5 = BASE64.encode(Md5::digest(&data));
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key_with_prefix)
+            .content_md5(content_md5)
+            .body(ByteStream::from(data))
+            .set_metadata(self.object_metadata(generation))
+            .set_acl(self.acl.clone())
+    }
+
+    // The `Content-Encoding` to declare for a compressed upload of `data`
+    fn encoding_of(&self, data: &[u8]) -> Option<String> {
+        content_encoding(data)
+            .filter(|_| self.content_encoding)
+            .map(str::to_string)
+    }
+
+    // The `x-amz-copy-source` of a copy from `key` in this bucket, which S3 requires URL-encoded
+    fn copy_source(&self, key: &str) -> String {
+        format!("{}/{}", self.bucket, encode_copy_source_key(key))
+    }
+
+    // Adds the size metadata of a compressed upload of `raw_size` bytes to its PUT request
+    fn with_sizes(
+        &self,
+        request: PutObjectFluentBuilder,
+        raw_size: Option<usize>,
+        stored_size: usize,
+    ) -> PutObjectFluentBuilder {
+        match raw_size.filter(|_| self.size_metadata) {
+            Some(raw_size) => request
+                .metadata(RAW_SIZE_METADATA_KEY, raw_size.to_string())
+                .metadata(STORED_SIZE_METADATA_KEY, stored_size.to_string()),
+            None => request,
+        }
+    }
+
+    // The size before compression recorded by a compressed upload of `key`, if any
+    async fn raw_size_of(&self, key: &str) -> Result<Option<u64>, String> {
+        let head = timed(
+            self.timeouts.head,
+            self.client
+                .head_object()
+                .set_request_payer(self.request_payer())
+                .bucket(&self.bucket)
+                .key(key)
+                .send(),
+        )
+        .await
+        .map_err(|err| format!("Failed to check object {}: {}", key, err))?;
+
+        Ok(head
+            .metadata()
+            .and_then(|metadata| metadata.get(RAW_SIZE_METADATA_KEY))
+            .and_then(|size| size.parse().ok()))
+    }
+
+    fn object_metadata(&self, generation: Option<u64>) -> Option<HashMap<String, String>> {
+        let mut metadata = HashMap::new();
+        if let Some(tag) = &self.client_tag {
+            metadata.insert(CLIENT_TAG_METADATA_KEY.to_string(), tag.clone());
+        }
+        if let Some(generation) = generation {
+            metadata.insert(GENERATION_METADATA_KEY.to_string(), generation.to_string());
+        }
+
+        (!metadata.is_empty()).then_some(metadata)
+    }
+
+    // Sends a PUT that S3 only applies if no object exists under the key yet. `raw_size` is the
+    // size before compression of compressed uploads.
+    async fn put_object_if_absent(
+        &self,
+        file_name: &str,
+        data: Vec<u8>,
+        file_path: Option<&str>,
+        raw_size: Option<usize>,
+    ) -> Result<(), String> {
+        let generation = self.next_generation(file_name, file_path).await?;
+        let key_with_prefix = object_key(file_name, file_path);
+        let stored_size = data.len();
+        let encoding = raw_size.and_then(|_| self.encoding_of(&data));
+        let request = self
+            .put_object(file_name, data, file_path, generation)
+            .set_content_encoding(encoding);
+        match timed(
+            self.timeouts.put,
+            self.with_sizes(request, raw_size, stored_size)
+                .if_none_match("*")
+                .send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(()),
+            // A conflict means another conditional write of the key is in progress
+            Err(err)
+                if matches!(
+                    err.code(),
+                    Some("PreconditionFailed") | Some("ConditionalRequestConflict")
+                ) =>
+            {
+                Err(DataStoreError::AlreadyExists(key_with_prefix).to_string())
+            }
+            Err(err) => match self.classify_error(&key_with_prefix, &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to save json file: {}", err)),
+            },
+        }
+    }
+
+    // The generation to stamp on the next upload of an object, one more than the generation of
+    // the current object, or None unless generations are enabled
+    async fn next_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Option<u64>, String> {
+        if !self.generations {
+            return Ok(None);
+        }
+
+        let key_with_prefix = object_key(file_name, file_path);
+        match timed(
+            self.timeouts.head,
+            self.client
+                .head_object()
+                .set_request_payer(self.request_payer())
+                .bucket(&self.bucket)
+                .key(&key_with_prefix)
+                .send(),
+        )
+        .await
+        {
+            Ok(head) => Ok(Some(generation_of(head.metadata()) + 1)),
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(Some(1)),
+            Err(err) => Err(format!(
+                "Failed to check object {}: {}",
+                key_with_prefix, err
+            )),
+        }
+    }
+
+    // Sends every part produced by `reader`, starting with `first_part`, to an open multipart
+    // upload and completes it. Returns the total number of bytes uploaded.
+    async fn upload_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+        first_part: Vec<u8>,
+        part_size: usize,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> Result<usize, String> {
+        let mut completed_parts = vec![];
+        let mut size = 0;
+        let mut part = first_part;
+        let mut part_number = 1;
+        while !part.is_empty() {
+            size += part.len();
+            self.config.check_upload(size)?;
+
+            let output = timed(
+                self.timeouts.put,
+                self.client
+                    .upload_part()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .content_md5(BASE64.encode(Md5::digest(&part)))
+                    .body(ByteStream::from(part))
+                    .send(),
+            )
+            .await
+            .map_err(|err| match self.classify_error(key, &err) {
+                Some(err) => err.to_string(),
+                None => format!("Failed to upload part {} of {}: {}", part_number, key, err),
+            })?;
+            completed_parts.push(
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .set_e_tag(output.e_tag)
+                    .build(),
+            );
+
+            part = read_part(reader, part_size).await?;
+            part_number += 1;
+        }
+
+        timed(
+            self.timeouts.put,
+            self.client
+                .complete_multipart_upload()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(completed_parts))
+                        .build(),
+                )
+                .send(),
+        )
+        .await
+        .map_err(|err| format!("Failed to complete upload of {}: {}", key, err))?;
+
+        Ok(size)
+    }
+}
+
+#[async_trait]
+impl DataStoreDriver for AwsS3 {
+    fn capabilities(&self) -> StoreCapabilities {
+        match self.anonymous {
+            true => {
+                StoreCapabilities::PREFIX_SIZE
+                    | StoreCapabilities::RANGE_READ
+                    | StoreCapabilities::RAW_READ
+            }
+            false => {
+                let capabilities = StoreCapabilities::CONDITIONAL_WRITES
+                    | StoreCapabilities::PREFIX_SIZE
+                    | StoreCapabilities::RANGE_READ
+                    | StoreCapabilities::RAW_READ
+                    | StoreCapabilities::SERVER_SIDE_COPY
+                    | StoreCapabilities::DELETE;
+                let capabilities = match self.storage_classes {
+                    true => capabilities | StoreCapabilities::STORAGE_CLASSES,
+                    false => capabilities,
+                };
+                match self.object_lock {
+                    true => capabilities | StoreCapabilities::OBJECT_LOCK,
+                    false => capabilities,
+                }
+            }
+        }
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        Some(MAX_KEY_LENGTH)
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.list_keys(file_path, |_| true).await
+    }
+
     async fn fetch_object(
         &self,
         file_name: &str,
         file_path: Option<&str>,
     ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
         let response = self.get_object(file_name, file_path).await;
         match response {
             Ok(buffer) => {
@@ -149,6 +1332,7 @@ impl DataStoreDriver for AwsS3 {
                     Err(err) => Err(format!("Failed to parse json: {}", err)),
                 }
             }
+            Err(err) if is_not_found(&err, file_name, file_path) => Err(err),
             Err(err) => Err(format!("Failed to get json file: {}", err)),
         }
     }
@@ -159,12 +1343,26 @@ impl DataStoreDriver for AwsS3 {
         contents: &str,
         file_path: Option<&str>,
     ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
         let size = contents.len();
-        let byte_stream = ByteStream::from(contents.as_bytes().to_vec());
+        self.check_signed("upload_object")?;
+        self.config.check_upload(size)?;
+        let generation = self.next_generation(file_name, file_path).await?;
 
-        match self.upload_object(file_name, byte_stream, file_path).await {
+        match self
+            .upload_object(
+                file_name,
+                contents.as_bytes().to_vec(),
+                file_path,
+                generation,
+            )
+            .await
+        {
             Ok(_) => Ok(size),
-            Err(err) => Err(format!("Failed to save json file: {}", err)),
+            Err(err) => match self.classify_error(&object_key(file_name, file_path), &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to save json file: {}", err)),
+            },
         }
     }
 
@@ -172,13 +1370,25 @@ impl DataStoreDriver for AwsS3 {
         &self,
         file_name: &str,
         file_path: Option<&str>,
-    ) -> Result<(Vec<u8>, usize), String> {
+    ) -> Result<FetchResult, String> {
+        self.check_key(file_name, file_path)?;
         let response = self.get_object(file_name, file_path).await;
         match response {
+            // Decompressed in transit because of its `Content-Encoding`
+            Ok(buffer) if self.content_encoding && !is_encoded(&buffer) => Ok(FetchResult {
+                stored_size: buffer.len(),
+                decompressed_size: buffer.len(),
+                data: buffer,
+                decompress_failed: false,
+            }),
             Ok(buffer) => {
-                let size = buffer.len();
-                Ok((decompress(&buffer).map_err(err_to_string)?, size))
+                self.config
+                    .decode_fetched_or_refetch(&object_key(file_name, file_path), &buffer, || {
+                        self.get_object(file_name, file_path)
+                    })
+                    .await
             }
+            Err(err) if is_not_found(&err, file_name, file_path) => Err(err),
             Err(err) => Err(format!("Failed to get json file: {}", err)),
         }
     }
@@ -189,14 +1399,872 @@ impl DataStoreDriver for AwsS3 {
         contents: &Vec<u8>,
         file_path: Option<&str>,
     ) -> Result<usize, String> {
-        let compressed_data =
-            compress(contents, DEFAULT_COMPRESSION_LEVEL).map_err(err_to_string)?;
+        self.check_key(file_name, file_path)?;
+        let compressed_data = self.config.encode_upload(contents, file_name, file_path)?;
         let size = compressed_data.len();
-        let byte_stream = ByteStream::from(compressed_data);
+        self.check_signed("upload_compressed_object")?;
+        self.config.check_upload(size)?;
+        let generation = self.next_generation(file_name, file_path).await?;
+
+        let encoding = self.encoding_of(&compressed_data);
+        let request = self
+            .put_object(file_name, compressed_data, file_path, generation)
+            .set_content_encoding(encoding);
+        match timed(
+            self.timeouts.put,
+            self.with_sizes(request, Some(contents.len()), size).send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(size),
+            Err(err) => match self.classify_error(&object_key(file_name, file_path), &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to save json file: {}", err)),
+            },
+        }
+    }
+
+    async fn list_objects_with_suffix(
+        &self,
+        file_path: Option<&str>,
+        suffix: &str,
+    ) -> Result<Vec<String>, String> {
+        self.list_keys(file_path, |key| key.ends_with(suffix)).await
+    }
+
+    async fn fetch_first(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Option<(String, String)>, String> {
+        // S3 lists keys in lexicographic order, so the first key of one page is enough
+        let output = timed(
+            self.timeouts.list,
+            self.client
+                .list_objects_v2()
+                .set_request_payer(self.request_payer())
+                .prefix(list_prefix(file_path))
+                .bucket(&self.bucket)
+                .encoding_type(EncodingType::Url)
+                .max_keys(1)
+                .send(),
+        )
+        .await
+        .map_err(
+            |err| match self.classify_error(&list_prefix(file_path), &err) {
+                Some(err) => err.to_string(),
+                None => format!("Unable to list objects: {}", err),
+            },
+        )?;
+        let Some(object) = output.contents().first() else {
+            return Ok(None);
+        };
+        let Some(key) = listed_key(&output, object)? else {
+            return Ok(None);
+        };
+
+        let parsed = parse_key(&key);
+        let contents = self
+            .fetch_object(parsed.file_name(), parsed.directory().as_deref())
+            .await?;
+        Ok(Some((key, contents)))
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.check_key(file_name, file_path)?;
+        let key_with_prefix = object_key(file_name, file_path);
+
+        match timed(
+            self.timeouts.head,
+            self.client
+                .head_object()
+                .set_request_payer(self.request_payer())
+                .bucket(&self.bucket)
+                .key(&key_with_prefix)
+                .send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(false),
+            Err(err) => Err(format!(
+                "Failed to check object {}: {}",
+                key_with_prefix, err
+            )),
+        }
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let mut response = self
+            .client
+            .list_objects_v2()
+            .set_request_payer(self.request_payer())
+            .prefix(list_prefix(file_path))
+            .bucket(&self.bucket)
+            .encoding_type(EncodingType::Url)
+            .into_paginator()
+            .send();
+
+        let mut objects = vec![];
+        while let Some(result) = timed_page(self.timeouts.list, response.next()).await {
+            let output = result.map_err(|err| format!("Unable to list objects: {}", err))?;
+            for object in output.contents() {
+                if let Some(key) = listed_key(&output, object)? {
+                    objects.push(ObjectMetadata {
+                        key,
+                        size: object.size().unwrap_or(0) as u64,
+                        etag: object.e_tag().map(str::to_string),
+                        raw_size: None,
+                    });
+                }
+            }
+        }
+
+        // Listings don't include user metadata
+        if !self.size_metadata {
+            return Ok(objects);
+        }
+        stream::iter(objects)
+            .map(|object| async move {
+                let raw_size = self.raw_size_of(&object.key).await?;
+                Ok(ObjectMetadata { raw_size, ..object })
+            })
+            .buffered(METADATA_HEAD_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        let mut response = self
+            .client
+            .list_objects_v2()
+            .set_request_payer(self.request_payer())
+            .prefix(list_prefix(file_path))
+            .bucket(&self.bucket)
+            .encoding_type(EncodingType::Url)
+            .into_paginator()
+            .send();
+
+        let mut recent = RecentObjects::new(n);
+        while let Some(result) = timed_page(self.timeouts.list, response.next()).await {
+            let output = result.map_err(|err| format!("Unable to list objects: {}", err))?;
+            for object in output.contents() {
+                let last_modified = object
+                    .last_modified()
+                    .and_then(|time| SystemTime::try_from(*time).ok());
+                if let (Some(key), Some(last_modified)) =
+                    (listed_key(&output, object)?, last_modified)
+                {
+                    recent.push(key, last_modified);
+                }
+            }
+        }
+
+        Ok(recent.into_sorted())
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        let mut response = self
+            .client
+            .list_objects_v2()
+            .set_request_payer(self.request_payer())
+            .prefix(list_prefix(file_path))
+            .bucket(&self.bucket)
+            .into_paginator()
+            .send();
+
+        // Only the running total is kept, so arbitrarily large prefixes use constant memory
+        let mut total: u64 = 0;
+        while let Some(result) = timed_page(self.timeouts.list, response.next()).await {
+            match result {
+                Ok(output) => {
+                    for object in output.contents() {
+                        total += object.size().unwrap_or(0) as u64;
+                    }
+                }
+                Err(err) => return Err(format!("Unable to compute prefix size: {}", err)),
+            }
+        }
+
+        Ok(total)
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.check_key(file_name, file_path)?;
+        let (buffer, etag) = self.get_object_with_etag(file_name, file_path).await?;
+        let etag = etag.ok_or_else(|| {
+            format!(
+                "S3 returned no etag for {}",
+                object_key(file_name, file_path)
+            )
+        })?;
+        let json =
+            String::from_utf8(buffer).map_err(|err| format!("Failed to parse json: {}", err))?;
+
+        Ok((json, etag))
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
+        self.check_signed("upload_if_etag_matches")?;
+        self.config.check_upload(contents.len())?;
+        let generation = self.next_generation(file_name, file_path).await?;
+
+        match timed(
+            self.timeouts.put,
+            self.put_object(
+                file_name,
+                contents.as_bytes().to_vec(),
+                file_path,
+                generation,
+            )
+            .if_match(etag)
+            .send(),
+        )
+        .await
+        {
+            Ok(output) => output
+                .e_tag
+                .ok_or_else(|| format!("S3 returned no etag for {}", file_name)),
+            Err(err) => match self.classify_error(&object_key(file_name, file_path), &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to save json file: {}", err)),
+            },
+        }
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.check_key(file_name, file_path)?;
+        self.get_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.check_key(file_name, file_path)?;
+        let key = object_key(file_name, file_path);
+        let mut data = match timed(
+            self.timeouts.get,
+            self.client
+                .get_object()
+                .set_request_payer(self.request_payer())
+                .bucket(&self.bucket)
+                .key(&key)
+                .range(range.header())
+                .send(),
+        )
+        .await
+        {
+            Ok(data) => data,
+            // A suffix range of an empty object can't be satisfied
+            Err(err)
+                if err.code() == Some("InvalidRange") && matches!(range, ByteRange::Last(_)) =>
+            {
+                return Ok((vec![], 0))
+            }
+            Err(err) => {
+                return Err(self
+                    .classify_error(&key, &err)
+                    .map(|err| err.to_string())
+                    .unwrap_or_else(|| err.to_string()))
+            }
+        };
+
+        let mut buffer: Vec<u8> = vec![];
+        while let Some(bytes) = data.body.try_next().await.map_err(err_to_string)? {
+            buffer.append(&mut bytes.to_vec());
+        }
+        // `bytes <start>-<end>/<size>`, missing if the whole object was returned
+        let size = data
+            .content_range()
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, size)| size.parse().ok())
+            .unwrap_or(buffer.len() as u64);
+
+        Ok((buffer, size))
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.check_key(target_file_name, target_file_path)?;
+        self.check_signed("copy_object")?;
+        self.config.check_write()?;
+        let source_key = object_key(file_name, file_path);
+
+        match timed(
+            self.timeouts.copy,
+            self.client
+                .copy_object()
+                .bucket(&self.bucket)
+                .copy_source(self.copy_source(&source_key))
+                .key(object_key(target_file_name, target_file_path))
+                .set_acl(self.acl.clone())
+                .send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => match self.classify_error(&source_key, &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to copy {}: {}", source_key, err)),
+            },
+        }
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.check_signed("delete_object")?;
+        self.config.check_write()?;
+        let key_with_prefix = object_key(file_name, file_path);
+        if self.object_lock {
+            self.check_not_locked(&key_with_prefix).await?;
+        }
+
+        match timed(
+            self.timeouts.delete,
+            self.client
+                .delete_object()
+                .bucket(&self.bucket)
+                .key(&key_with_prefix)
+                .send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => match self.classify_error(&key_with_prefix, &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to delete {}: {}", key_with_prefix, err)),
+            },
+        }
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.check_signed("upload_object_from_reader")?;
+        self.config.check_upload(size_hint.unwrap_or(0) as usize)?;
+        let generation = self.next_generation(file_name, file_path).await?;
+
+        // Small artifacts fit in a single request
+        let part_size = self.part_sizing.part_size(size_hint);
+        let first_part = read_part(reader, part_size).await?;
+        if first_part.len() < part_size {
+            let size = first_part.len();
+            self.config.check_upload(size)?;
+            return match self
+                .upload_object(file_name, first_part, file_path, generation)
+                .await
+            {
+                Ok(_) => Ok(size),
+                Err(err) => match self.classify_error(&object_key(file_name, file_path), &err) {
+                    Some(err) => Err(err.to_string()),
+                    None => Err(format!("Failed to save json file: {}", err)),
+                },
+            };
+        }
+
+        let key = object_key(file_name, file_path);
+        let upload_id = timed(
+            self.timeouts.put,
+            self.client
+                .create_multipart_upload()
+                .bucket(&self.bucket)
+                .key(&key)
+                .set_metadata(self.object_metadata(generation))
+                .set_acl(self.acl.clone())
+                .send(),
+        )
+        .await
+        .map_err(|err| match self.classify_error(&key, &err) {
+            Some(err) => err.to_string(),
+            None => format!("Failed to start upload of {}: {}", key, err),
+        })?
+        .upload_id
+        .ok_or_else(|| format!("S3 returned no upload id for {}", key))?;
+
+        match self
+            .upload_parts(&key, &upload_id, first_part, part_size, reader)
+            .await
+        {
+            Ok(size) => Ok(size),
+            Err(err) => {
+                // Otherwise the uploaded parts linger, and are billed, until a lifecycle rule
+                // removes them
+                if let Err(abort_err) = timed(
+                    self.timeouts.delete,
+                    self.client
+                        .abort_multipart_upload()
+                        .bucket(&self.bucket)
+                        .key(&key)
+                        .upload_id(&upload_id)
+                        .send(),
+                )
+                .await
+                {
+                    eprintln!("Failed to abort upload of {}: {}", key, abort_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.check_signed("upload_precompressed_object")?;
+        check_encoded(compressed).map_err(err_to_string)?;
+        let size = compressed.len();
+        self.config.check_upload(size)?;
+        let generation = self.next_generation(file_name, file_path).await?;
+
+        match timed(
+            self.timeouts.put,
+            self.put_object(file_name, compressed.to_vec(), file_path, generation)
+                .content_type("application/octet-stream")
+                .set_content_encoding(self.encoding_of(compressed))
+                .send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(size),
+            Err(err) => match self.classify_error(&object_key(file_name, file_path), &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to save json file: {}", err)),
+            },
+        }
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        if !self.object_lock {
+            return Err(DataStoreError::Unsupported("upload_object_locked").to_string());
+        }
+        let size = contents.len();
+        self.check_signed("upload_object_locked")?;
+        self.config.check_upload(size)?;
+        let generation = self.next_generation(file_name, file_path).await?;
 
-        match self.upload_object(file_name, byte_stream, file_path).await {
+        let mode = match mode {
+            RetentionMode::Governance => ObjectLockMode::Governance,
+            RetentionMode::Compliance => ObjectLockMode::Compliance,
+        };
+        match timed(
+            self.timeouts.put,
+            self.put_object(
+                file_name,
+                contents.as_bytes().to_vec(),
+                file_path,
+                generation,
+            )
+            .object_lock_mode(mode)
+            .object_lock_retain_until_date(DateTime::from(retain_until))
+            .send(),
+        )
+        .await
+        {
             Ok(_) => Ok(size),
-            Err(err) => Err(format!("Failed to save json file: {}", err)),
+            Err(err) => match self.classify_error(&object_key(file_name, file_path), &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to save locked json file: {}", err)),
+            },
+        }
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        self.check_key(file_name, file_path)?;
+        let size = contents.len();
+        self.check_signed("upload_object_idempotent")?;
+        self.config.check_upload(size)?;
+        let key_with_prefix = object_key(file_name, file_path);
+        // The ETag of the object the upload may replace, None if there is none yet
+        let etag = match timed(
+            self.timeouts.head,
+            self.client
+                .head_object()
+                .set_request_payer(self.request_payer())
+                .bucket(&self.bucket)
+                .key(&key_with_prefix)
+                .send(),
+        )
+        .await
+        {
+            Ok(head) => {
+                let applied = head
+                    .metadata()
+                    .and_then(|metadata| metadata.get(IDEMPOTENCY_KEY_METADATA_KEY))
+                    .is_some_and(|key| key == idempotency_key);
+                if applied {
+                    return Ok(UploadOutcome::AlreadyApplied);
+                }
+                head.e_tag
+            }
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_not_found()) => None,
+            Err(err) => {
+                return Err(format!(
+                    "Failed to check object {}: {}",
+                    key_with_prefix, err
+                ))
+            }
+        };
+        let generation = self.next_generation(file_name, file_path).await?;
+
+        // Only replaces the object that was checked, so a concurrent upload with another
+        // idempotency key fails with `PreconditionFailed` instead of being overwritten
+        let request = self
+            .put_object(
+                file_name,
+                contents.as_bytes().to_vec(),
+                file_path,
+                generation,
+            )
+            .metadata(IDEMPOTENCY_KEY_METADATA_KEY, idempotency_key);
+        let request = match etag {
+            Some(etag) => request.if_match(etag),
+            None => request.if_none_match("*"),
+        };
+        match timed(self.timeouts.put, request.send()).await {
+            Ok(_) => Ok(UploadOutcome::Uploaded(size)),
+            Err(err) => match self.classify_error(&object_key(file_name, file_path), &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to save json file: {}", err)),
+            },
         }
     }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
+        let key_with_prefix = object_key(file_name, file_path);
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+        loop {
+            let output = timed(
+                self.timeouts.list,
+                self.client
+                    .list_object_versions()
+                    .bucket(&self.bucket)
+                    .prefix(&key_with_prefix)
+                    .set_request_payer(self.request_payer())
+                    .set_key_marker(key_marker)
+                    .set_version_id_marker(version_id_marker)
+                    .send(),
+            )
+            .await
+            .map_err(|err| format!("Unable to list versions of {}: {}", key_with_prefix, err))?;
+
+            // The prefix also matches longer keys, whose versions are skipped
+            let versions = output
+                .versions()
+                .iter()
+                .filter(|version| version.key() == Some(key_with_prefix.as_str()))
+                .filter_map(|version| version.version_id());
+            for version_id in versions {
+                let head = timed(
+                    self.timeouts.head,
+                    self.client
+                        .head_object()
+                        .set_request_payer(self.request_payer())
+                        .bucket(&self.bucket)
+                        .key(&key_with_prefix)
+                        .version_id(version_id)
+                        .send(),
+                )
+                .await
+                .map_err(|err| format!("Failed to check object {}: {}", key_with_prefix, err))?;
+                if generation_of(head.metadata()) != generation {
+                    continue;
+                }
+
+                let mut data = timed(
+                    self.timeouts.get,
+                    self.client
+                        .get_object()
+                        .set_request_payer(self.request_payer())
+                        .bucket(&self.bucket)
+                        .key(&key_with_prefix)
+                        .version_id(version_id)
+                        .send(),
+                )
+                .await
+                .map_err(|err| {
+                    self.classify_error(&key_with_prefix, &err)
+                        .map(|err| err.to_string())
+                        .unwrap_or_else(|| err.to_string())
+                })?;
+                let mut buffer: Vec<u8> = vec![];
+                while let Some(bytes) = data.body.try_next().await.map_err(err_to_string)? {
+                    buffer.append(&mut bytes.to_vec());
+                }
+
+                return String::from_utf8(buffer)
+                    .map_err(|err| format!("Failed to parse json: {}", err));
+            }
+
+            if !output.is_truncated().unwrap_or(false) {
+                break;
+            }
+            key_marker = output.next_key_marker().map(String::from);
+            version_id_marker = output.next_version_id_marker().map(String::from);
+        }
+
+        Err(DataStoreError::NotFound(key_with_prefix).to_string())
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        let size = contents.len();
+        self.check_signed("upload_object_if_absent")?;
+        self.config.check_upload(size)?;
+
+        self.put_object_if_absent(file_name, contents.as_bytes().to_vec(), file_path, None)
+            .await?;
+        Ok(size)
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        let compressed_data = self.config.encode_upload(contents, file_name, file_path)?;
+        let size = compressed_data.len();
+        self.check_signed("upload_compressed_object_if_absent")?;
+        self.config.check_upload(size)?;
+
+        self.put_object_if_absent(file_name, compressed_data, file_path, Some(contents.len()))
+            .await?;
+        Ok(size)
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.check_signed("touch")?;
+        self.config.check_write()?;
+        let key_with_prefix = object_key(file_name, file_path);
+
+        let head = match timed(
+            self.timeouts.head,
+            self.client
+                .head_object()
+                .set_request_payer(self.request_payer())
+                .bucket(&self.bucket)
+                .key(&key_with_prefix)
+                .send(),
+        )
+        .await
+        {
+            Ok(head) => head,
+            Err(err) => {
+                return match self.classify_error(&key_with_prefix, &err) {
+                    Some(err) => Err(err.to_string()),
+                    None => Err(format!(
+                        "Failed to check object {}: {}",
+                        key_with_prefix, err
+                    )),
+                }
+            }
+        };
+
+        // S3 rejects copying an object onto itself without changing anything, so the copy
+        // replaces the metadata with its current values. Copies get the store's ACL rather than
+        // the source's
+        match timed(
+            self.timeouts.copy,
+            self.client
+                .copy_object()
+                .bucket(&self.bucket)
+                .copy_source(self.copy_source(&key_with_prefix))
+                .key(&key_with_prefix)
+                .metadata_directive(MetadataDirective::Replace)
+                .set_metadata(head.metadata)
+                .set_content_type(head.content_type)
+                .set_content_encoding(head.content_encoding)
+                .set_cache_control(head.cache_control)
+                .set_storage_class(head.storage_class)
+                .set_acl(self.acl.clone())
+                .send(),
+        )
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => match self.classify_error(&key_with_prefix, &err) {
+                Some(err) => Err(err.to_string()),
+                None => Err(format!("Failed to touch {}: {}", key_with_prefix, err)),
+            },
+        }
+    }
+}
+
+/// Rejects a region that isn't a known AWS region, e.g. a typo such as `us-east-1a`, which the
+/// SDK would only fail on at the first request. Any region passes with a `custom_endpoint`, as
+/// S3-compatible backends name their regions freely.
+pub fn validate_region(region: &str, custom_endpoint: bool) -> Result<(), String> {
+    if custom_endpoint || KNOWN_REGIONS.contains(&region) {
+        return Ok(());
+    }
+
+    let suggestion = KNOWN_REGIONS
+        .iter()
+        .find(|known| region.starts_with(*known))
+        .map(|known| format!(", did you mean {known:?}?"))
+        .unwrap_or(".".to_string());
+    Err(format!(
+        "Unknown AWS region {region:?}{suggestion} Regions of S3-compatible backends need \
+         BRIDGE_AWS_ENDPOINT_URL to be set"
+    ))
+}
+
+// Percent-encodes a key for the `x-amz-copy-source` header, keeping unreserved characters and the
+// `/` separating path segments
+fn encode_copy_source_key(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Decodes a key of a listing requested with `EncodingType::Url`. S3 percent-encodes such keys
+/// like form data, with spaces encoded as `+`.
+pub fn decode_listed_key(key: &str) -> Result<String, String> {
+    let invalid = || format!("Listed key {key:?} is not validly URL-encoded");
+    let mut decoded = Vec::with_capacity(key.len());
+    let mut bytes = key.bytes();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = [bytes.next(), bytes.next()];
+                let [Some(high), Some(low)] = hex else {
+                    return Err(invalid());
+                };
+                let digit = |byte: u8| (byte as char).to_digit(16).ok_or_else(invalid);
+                decoded.push((digit(high)? * 16 + digit(low)?) as u8);
+            }
+            byte => decoded.push(byte),
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+// The key of a listed object. Listings are requested URL-encoded, as keys with characters XML
+// can't hold otherwise come back mangled, but backends that don't support it return plain keys
+// and say so by leaving out the encoding type.
+fn listed_key(output: &ListObjectsV2Output, object: &Object) -> Result<Option<String>, String> {
+    let Some(key) = object.key() else {
+        return Ok(None);
+    };
+    match output.encoding_type() {
+        Some(EncodingType::Url) => decode_listed_key(key).map(Some),
+        _ => Ok(Some(key.to_string())),
+    }
+}
+
+/// Seconds the local clock at `local_now` is ahead of the server that sent `server_date`, an
+/// HTTP `Date` header value. Negative if the local clock is behind, `None` if the date is invalid.
+pub fn clock_skew(server_date: &str, local_now: SystemTime) -> Option<i64> {
+    let server_now = DateTime::from_str(server_date, DateTimeFormat::HttpDate).ok()?;
+    Some(DateTime::from(local_now).secs() - server_now.secs())
+}
+
+// The generation an object was stamped with, 0 for objects uploaded without one
+fn generation_of(metadata: Option<&HashMap<String, String>>) -> u64 {
+    metadata
+        .and_then(|metadata| metadata.get(GENERATION_METADATA_KEY))
+        .and_then(|generation| generation.parse().ok())
+        .unwrap_or(0)
+}
+
+// The SDK appends the app name to the user agent of every request
+fn app_name_from_env() -> AppName {
+    let suffix = dotenv::var("BRIDGE_AWS_UA_SUFFIX").unwrap_or(DEFAULT_UA_SUFFIX.to_string());
+    AppName::new(suffix.clone()).unwrap_or_else(|_| {
+        eprintln!("Ignoring invalid BRIDGE_AWS_UA_SUFFIX {suffix:?}, using {DEFAULT_UA_SUFFIX}");
+        AppName::new(DEFAULT_UA_SUFFIX).unwrap()
+    })
+}
+
+// Reads up to one multipart part from `reader`, returning fewer bytes only at the end of input
+async fn read_part(
+    reader: &mut (dyn AsyncRead + Send + Unpin),
+    part_size: usize,
+) -> Result<Vec<u8>, String> {
+    let mut part = Vec::with_capacity(part_size);
+    reader
+        .take(part_size as u64)
+        .read_to_end(&mut part)
+        .await
+        .map_err(err_to_string)?;
+
+    Ok(part)
 }
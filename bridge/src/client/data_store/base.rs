@@ -1,7 +1,570 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    future::Future,
+    ops::{BitOr, BitOrAssign},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
 use async_trait::async_trait;
+use futures::{stream, StreamExt, TryStreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    error::{err_to_string, BatchError, DataStoreError},
+    utils::DEFAULT_COMPRESSION_LEVEL,
+};
+
+use super::{
+    clock::{Clock, SystemClock},
+    compression_policy::{CompressionPolicy, CompressionRule},
+    compression_tuner::CompressionTuner,
+    format::{
+        check_codec_available, compress_once, decode_object, decode_object_lossy,
+        decode_object_spilling, encode_object_multithreaded, encode_object_uncompressed,
+        is_encoded, DecompressedOutput, DEFAULT_MIN_COMPRESS_SIZE,
+    },
+    integrity::HashAlgorithm,
+    key::{graph_path, list_prefix, object_key, parse_key, ParsedKey},
+    stats::StoreStats,
+};
+
+const VERIFY_AFTER_WRITE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const FETCH_OBJECTS_CONCURRENCY: usize = 8;
+// Read-modify-write cycles `update_object` tries before giving up on a contended object
+const UPDATE_OBJECT_ATTEMPTS: usize = 5;
+const LATEST_POINTER_FILE_NAME: &str = "latest";
+const SWAP_TEMP_SUFFIX: &str = ".swap";
+const ACCESS_PROBE_PREFIX: &str = ".access-probe-";
+// Bytes `read_last_lines` fetches per range request, scanning backward from the end
+const READ_LAST_LINES_CHUNK_SIZE: u64 = 64 * 1024;
+
+// Settings shared by every driver. They can be set in the .env file:
+// export BRIDGE_DATA_STORE_READ_ONLY=true
+// export BRIDGE_DATA_STORE_MAX_UPLOAD_SIZE=... (in bytes)
+// export BRIDGE_DATA_STORE_MAX_KEYS_TOTAL=...
+// export BRIDGE_DATA_STORE_STRICT_MAX_KEYS=true
+// export BRIDGE_DATA_STORE_STRICT_DOUBLE_COMPRESSION=true
+// export BRIDGE_DATA_STORE_MIN_COMPRESS_SIZE=... (in bytes, default 256)
+// export BRIDGE_DATA_STORE_COMPRESSION_POLICY=... (e.g. "suffix:.bin=skip,kind:peg_out=auto")
+// export BRIDGE_DATA_STORE_COMPRESSION_THREADS=... (default 1)
+// export BRIDGE_DATA_STORE_REFETCH_CORRUPT_OBJECTS=true
+#[derive(Clone, Debug)]
+pub struct DriverConfig {
+    // Reject every write, e.g. on replica or verifier nodes
+    pub read_only: bool,
+    // Reject payloads (after compression, where applicable) larger than this many bytes
+    pub max_upload_size: Option<usize>,
+    // Stop listing after this many keys, so a huge prefix can't exhaust memory
+    pub max_keys_total: Option<usize>,
+    // Fail listings that hit `max_keys_total` instead of returning the first keys only
+    pub strict_max_keys: bool,
+    // Fail compressed uploads of contents that are already compressed instead of warning
+    pub strict_double_compression: bool,
+    // Compressed uploads smaller than this many bytes are stored uncompressed
+    pub min_compress_size: usize,
+    // Compression level, or no compression, per artifact class for compressed uploads
+    pub compression_policy: CompressionPolicy,
+    // Worker threads per compressed upload. More than one is faster for large objects, but
+    // compressed output then differs between runs.
+    pub compression_threads: u32,
+    // Levels learned for `CompressionRule::Auto`, shared by every clone of the config
+    pub compression_tuner: Arc<CompressionTuner>,
+    // What compressed fetches return when the stored object can't be decompressed
+    pub decompress_failure_mode: DecompressFailureMode,
+    // Download objects that fail to decompress once more, in case they were corrupted in
+    // flight. If they still fail, the error includes the checksum of the stored bytes.
+    pub refetch_corrupt_objects: bool,
+    // Source of every wall-clock read, replaced by a `MockClock` in tests
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for DriverConfig {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            max_upload_size: None,
+            max_keys_total: None,
+            strict_max_keys: false,
+            strict_double_compression: false,
+            min_compress_size: DEFAULT_MIN_COMPRESS_SIZE,
+            compression_policy: CompressionPolicy::default(),
+            compression_threads: 1,
+            compression_tuner: Arc::new(CompressionTuner::new()),
+            decompress_failure_mode: DecompressFailureMode::default(),
+            refetch_corrupt_objects: false,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl DriverConfig {
+    pub fn from_env() -> Self {
+        dotenv::dotenv().ok();
+        Self {
+            read_only: dotenv::var("BRIDGE_DATA_STORE_READ_ONLY")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+            max_upload_size: dotenv::var("BRIDGE_DATA_STORE_MAX_UPLOAD_SIZE")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok()),
+            max_keys_total: dotenv::var("BRIDGE_DATA_STORE_MAX_KEYS_TOTAL")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok()),
+            strict_max_keys: dotenv::var("BRIDGE_DATA_STORE_STRICT_MAX_KEYS")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+            strict_double_compression: dotenv::var("BRIDGE_DATA_STORE_STRICT_DOUBLE_COMPRESSION")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+            min_compress_size: dotenv::var("BRIDGE_DATA_STORE_MIN_COMPRESS_SIZE")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_MIN_COMPRESS_SIZE),
+            compression_policy: dotenv::var("BRIDGE_DATA_STORE_COMPRESSION_POLICY")
+                .ok()
+                .and_then(|v| {
+                    CompressionPolicy::parse(&v)
+                        .inspect_err(|err| eprintln!("Ignoring compression policy: {err}"))
+                        .ok()
+                })
+                .unwrap_or_default(),
+            compression_threads: dotenv::var("BRIDGE_DATA_STORE_COMPRESSION_THREADS")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(1),
+            decompress_failure_mode: dotenv::var("BRIDGE_DATA_STORE_DECOMPRESS_FAILURE_MODE")
+                .ok()
+                .and_then(|v| {
+                    DecompressFailureMode::parse(&v)
+                        .inspect_err(|err| eprintln!("Ignoring decompress failure mode: {err}"))
+                        .ok()
+                })
+                .unwrap_or_default(),
+            refetch_corrupt_objects: dotenv::var("BRIDGE_DATA_STORE_REFETCH_CORRUPT_OBJECTS")
+                .is_ok_and(|v| v.parse::<bool>().unwrap_or(false)),
+            ..Default::default()
+        }
+    }
+
+    // Called by drivers to decode every object fetched by `fetch_compressed_object`
+    pub(crate) fn decode_fetched(&self, stored: &[u8]) -> Result<FetchResult, String> {
+        FetchResult::decode_with(stored, self.decompress_failure_mode)
+    }
+
+    // Like `decode_fetched`, but with `refetch_corrupt_objects`, objects that fail to decompress
+    // are downloaded again with `refetch`. Objects that still fail are handled according to
+    // `decompress_failure_mode`, failing with `CorruptObject` in the default mode.
+    pub(crate) async fn decode_fetched_or_refetch<F>(
+        &self,
+        key: &str,
+        stored: &[u8],
+        refetch: impl FnOnce() -> F,
+    ) -> Result<FetchResult, String>
+    where
+        F: Future<Output = Result<Vec<u8>, String>>,
+    {
+        // Downloading again doesn't help if the codec is missing from this build
+        if !self.refetch_corrupt_objects || check_codec_available(stored).is_err() {
+            return self.decode_fetched(stored);
+        }
+        if let Ok(fetched) = FetchResult::decode(stored) {
+            return Ok(fetched);
+        }
+
+        let stored = refetch().await?;
+        match (FetchResult::decode(&stored), self.decompress_failure_mode) {
+            (Ok(fetched), _) => Ok(fetched),
+            (Err(_), DecompressFailureMode::Error) => Err(DataStoreError::CorruptObject {
+                key: key.to_string(),
+                checksum: HashAlgorithm::default().checksum(&stored),
+            }
+            .to_string()),
+            (Err(_), mode) => FetchResult::decode_with(&stored, mode),
+        }
+    }
+
+    // Whether a listing that has accumulated `count` keys can stop, as one more key than
+    // `max_keys_total` is enough for `cap_keys` to tell that the listing was cut short
+    pub(crate) fn keys_exhausted(&self, count: usize) -> bool {
+        self.max_keys_total.is_some_and(|max| count > max)
+    }
+
+    // Called by drivers on every listing before returning it. Listings over `max_keys_total`
+    // are cut down to it, or rejected in strict mode.
+    pub(crate) fn cap_keys(
+        &self,
+        keys: &mut Vec<String>,
+        file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let Some(max) = self.max_keys_total else {
+            return Ok(());
+        };
+        if keys.len() <= max {
+            return Ok(());
+        }
+
+        let err = DataStoreError::TooManyKeys {
+            prefix: list_prefix(file_path),
+            max,
+        };
+        if self.strict_max_keys {
+            return Err(err.to_string());
+        }
+        eprintln!("{err}, returning the first {max} keys only");
+        keys.truncate(max);
+
+        Ok(())
+    }
+
+    // Called by drivers before compressing the contents of a compressed upload, to catch
+    // callers that pass in output of `compress` or `encode_object` by mistake
+    pub(crate) fn check_not_compressed(
+        &self,
+        contents: &[u8],
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(), String> {
+        if !is_encoded(contents) {
+            return Ok(());
+        }
+
+        let err = DataStoreError::AlreadyCompressed(object_key(file_name, file_path));
+        if self.strict_double_compression {
+            return Err(err.to_string());
+        }
+        eprintln!("Warning: {err}");
+
+        Ok(())
+    }
+
+    // Called by drivers to encode the contents of a compressed upload according to the
+    // compression policy. Read-only drivers fail before spending time on compression.
+    pub(crate) fn encode_upload(
+        &self,
+        contents: &[u8],
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.check_write()?;
+        self.check_not_compressed(contents, file_name, file_path)?;
+        let level = match self.compression_policy.rule_for(file_name, file_path) {
+            Some(CompressionRule::Skip) => return Ok(encode_object_uncompressed(contents)),
+            Some(CompressionRule::Compress { level }) => level,
+            Some(CompressionRule::Auto) => {
+                return self.compression_tuner.encode(
+                    contents,
+                    file_name,
+                    file_path,
+                    self.min_compress_size,
+                    self.clock.as_ref(),
+                )
+            }
+            None => DEFAULT_COMPRESSION_LEVEL,
+        };
+
+        encode_object_multithreaded(
+            contents,
+            level,
+            self.min_compress_size,
+            self.compression_threads,
+        )
+        .map_err(err_to_string)
+    }
+
+    // Called by drivers right before an object would be copied or deleted
+    pub(crate) fn check_write(&self) -> Result<(), String> {
+        match self.read_only {
+            true => Err(DataStoreError::ReadOnly.to_string()),
+            false => Ok(()),
+        }
+    }
+
+    // Called by drivers right before a payload of `size` bytes would be written
+    pub(crate) fn check_upload(&self, size: usize) -> Result<(), String> {
+        self.check_write()?;
+        if let Some(max) = self.max_upload_size {
+            if size > max {
+                return Err(DataStoreError::TooLarge { size, max }.to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Optional features a driver supports natively, so generic code can branch up front instead of
+// trying an operation and catching `Unsupported`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StoreCapabilities(u32);
+
+impl StoreCapabilities {
+    pub const SERVER_SIDE_COPY: Self = Self(1 << 0);
+    pub const PRESIGNED_URLS: Self = Self(1 << 1);
+    pub const TTL: Self = Self(1 << 2);
+    pub const VERSIONING: Self = Self(1 << 3);
+    pub const RANGE_READ: Self = Self(1 << 4);
+    // `fetch_with_etag`, `upload_if_etag_matches` and the `_if_absent` uploads
+    pub const CONDITIONAL_WRITES: Self = Self(1 << 5);
+    pub const PREFIX_SIZE: Self = Self(1 << 6);
+    pub const RAW_READ: Self = Self(1 << 7);
+    pub const DELETE: Self = Self(1 << 8);
+    // Objects can be moved to other storage classes, e.g. by `AwsS3::archive_older_than`
+    pub const STORAGE_CLASSES: Self = Self(1 << 9);
+    // Objects can be made immutable for a retention period with `upload_object_locked`
+    pub const OBJECT_LOCK: Self = Self(1 << 10);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl BitOr for StoreCapabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for StoreCapabilities {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+// Listing metadata of a single object, available without downloading it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectMetadata {
+    pub key: String,
+    pub size: u64,
+    pub etag: Option<String>, // None where the backend has no etag, e.g. local files
+    // Size before compression of compressed objects, where the backend recorded it
+    pub raw_size: Option<u64>,
+}
+
+// How much space the objects under a prefix take as stored and before compression
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrefixFootprint {
+    pub stored_size: u64,
+    pub raw_size: u64, // Objects without a recorded raw size count with their stored size
+}
+
+impl PrefixFootprint {
+    /// Size before compression per stored byte, 1.0 for an empty prefix.
+    pub fn compression_ratio(&self) -> f64 {
+        match self.stored_size {
+            0 => 1.0,
+            stored_size => self.raw_size as f64 / stored_size as f64,
+        }
+    }
+}
+
+// Result of `upload_object_idempotent`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadOutcome {
+    Uploaded(usize), // usize: the number of bytes uploaded
+    // The object was already uploaded with the same idempotency key, nothing was written
+    AlreadyApplied,
+}
+
+// Result of `fetch_compressed_object`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FetchResult {
+    pub data: Vec<u8>,      // The decompressed contents
+    pub stored_size: usize, // Size of the object as stored, i.e. after compression
+    pub decompressed_size: usize,
+    // The object couldn't be decompressed and `data` holds what `DecompressFailureMode` returns
+    pub decompress_failed: bool,
+}
+
+impl FetchResult {
+    /// Decodes an object as stored by a compressed upload.
+    pub fn decode(stored: &[u8]) -> Result<Self, String> {
+        Self::decode_with(stored, DecompressFailureMode::Error)
+    }
+
+    /// Like `decode`, but objects that fail to decompress are handled according to `mode`.
+    pub fn decode_with(stored: &[u8], mode: DecompressFailureMode) -> Result<Self, String> {
+        let (data, decompress_failed) = match (decode_object(stored), mode) {
+            (Ok(data), _) => (data, false),
+            (Err(err), DecompressFailureMode::Error) => return Err(err_to_string(err)),
+            (Err(_), DecompressFailureMode::ReturnRaw) => (stored.to_vec(), true),
+            (Err(_), DecompressFailureMode::ReturnLossy) => (decode_object_lossy(stored), true),
+        };
+        Ok(Self {
+            decompressed_size: data.len(),
+            stored_size: stored.len(),
+            data,
+            decompress_failed,
+        })
+    }
+}
+
+// What a compressed fetch returns when the stored object fails to decompress. The non-failing
+// modes keep corrupt artifacts inspectable instead of leaving only an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DecompressFailureMode {
+    #[default]
+    Error,
+    ReturnRaw,   // Return the stored bytes as they are
+    ReturnLossy, // Return the contents decoded up to the corrupt part
+}
+
+impl DecompressFailureMode {
+    /// Parses `error`, `raw` or `lossy`.
+    pub fn parse(mode: &str) -> Result<Self, String> {
+        match mode.trim() {
+            "error" => Ok(Self::Error),
+            "raw" => Ok(Self::ReturnRaw),
+            "lossy" => Ok(Self::ReturnLossy),
+            _ => Err(format!("Invalid decompress failure mode {mode:?}")),
+        }
+    }
+}
+
+// Per-call choice of how `upload` stores an object, so one driver can serve both artifacts that
+// should be compressed and ones that other tooling reads byte for byte
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionOverride {
+    // Follow the driver's compression policy, like `upload_compressed_object`
+    #[default]
+    Default,
+    // Always compress, regardless of the policy and the minimum compression size
+    Compressed,
+    // Store the contents as they are, without the object format header
+    Raw,
+}
+
+// Part of an object to fetch with `fetch_range`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    // Bytes from `start` up to but excluding `end`, cut off at the end of the object
+    Span { start: u64, end: u64 },
+    // The last this many bytes, or the whole object if it is shorter
+    Last(u64),
+}
+
+impl ByteRange {
+    /// The start and end offset of this range in an object of `size` bytes.
+    pub fn bounds(&self, size: u64) -> (u64, u64) {
+        match *self {
+            ByteRange::Span { start, end } => {
+                let start = start.min(size);
+                (start, end.clamp(start, size))
+            }
+            ByteRange::Last(len) => (size.saturating_sub(len), size),
+        }
+    }
+
+    /// The value of an HTTP `Range` header requesting this range.
+    pub fn header(&self) -> String {
+        match *self {
+            ByteRange::Span { start, end } => format!("bytes={start}-{}", end.saturating_sub(1)),
+            ByteRange::Last(len) => format!("bytes=-{len}"),
+        }
+    }
+}
+
+// The kind of access `check_access` probes for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn includes_read(self) -> bool {
+        matches!(self, Access::Read | Access::ReadWrite)
+    }
+
+    fn includes_write(self) -> bool {
+        matches!(self, Access::Write | Access::ReadWrite)
+    }
+}
+
+// How strictly a locked object is protected until its retention period ends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetentionMode {
+    // Users with the `s3:BypassGovernanceRetention` permission can still delete or shorten it
+    Governance,
+    // Nobody can delete the object or shorten its retention, not even the root account
+    Compliance,
+}
+
+// Keeps the `n` most recently modified of the objects pushed into it in a bounded min-heap, so
+// drivers can answer `list_recent` while streaming a listing of any size.
+pub(crate) struct RecentObjects {
+    heap: BinaryHeap<Reverse<(SystemTime, String)>>,
+    n: usize,
+}
+
+impl RecentObjects {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            heap: BinaryHeap::with_capacity(n + 1),
+            n,
+        }
+    }
+
+    pub(crate) fn push(&mut self, key: String, last_modified: SystemTime) {
+        self.heap.push(Reverse((last_modified, key)));
+        if self.heap.len() > self.n {
+            self.heap.pop();
+        }
+    }
+
+    /// The kept objects, newest first.
+    pub(crate) fn into_sorted(self) -> Vec<(String, SystemTime)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((last_modified, key))| (key, last_modified))
+            .collect()
+    }
+}
+
+// Result of `diff_prefixes`. Keys are relative to the compared prefixes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrefixDiff {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    pub different: Vec<String>,
+}
+
+impl PrefixDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.different.is_empty()
+    }
+}
 
 #[async_trait]
 pub trait DataStoreDriver {
+    /// Optional features this driver supports. Operations outside of these fail with
+    /// `Unsupported`.
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities::empty()
+    }
+
+    /// The longest object key in bytes this store accepts, or `None` if it has no limit.
+    /// Drivers reject longer keys with `KeyTooLong` before making any request.
+    fn max_key_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// A snapshot of the activity counters of this store and the wrappers below it. Calls are
+    /// counted by a `CountingStore` in the stack, every count is zero without one.
+    fn stats(&self) -> StoreStats {
+        StoreStats::default()
+    }
+
     async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String>;
     async fn fetch_object(
         &self,
@@ -18,11 +581,774 @@ pub trait DataStoreDriver {
         &self,
         file_name: &str,
         file_path: Option<&str>,
-    ) -> Result<(Vec<u8>, usize), String>;
+    ) -> Result<FetchResult, String>;
     async fn upload_compressed_object(
         &self,
         file_name: &str,
         contents: &Vec<u8>,
         file_path: Option<&str>,
     ) -> Result<usize, String>;
+
+    /// Lists the keys under `file_path` that end with `suffix` (e.g. `.json`).
+    async fn list_objects_with_suffix(
+        &self,
+        file_path: Option<&str>,
+        suffix: &str,
+    ) -> Result<Vec<String>, String> {
+        let keys = self.list_objects(file_path).await?;
+        Ok(keys
+            .into_iter()
+            .filter(|key| key.ends_with(suffix))
+            .collect())
+    }
+
+    /// Lists the keys under `file_path` split into their path segments and file name.
+    async fn list_parsed_objects(&self, file_path: Option<&str>) -> Result<Vec<ParsedKey>, String> {
+        let keys = self.list_objects(file_path).await?;
+        Ok(keys.iter().map(|key| parse_key(key)).collect())
+    }
+
+    /// Lists every artifact stored for `graph_id`, ordered by artifact kind and then by key.
+    async fn list_graph_artifacts(&self, graph_id: &str) -> Result<Vec<ParsedKey>, String> {
+        let mut artifacts: Vec<ParsedKey> = self
+            .list_parsed_objects(Some(&graph_path(graph_id)))
+            .await?
+            .into_iter()
+            .filter(|artifact| artifact.artifact_kind().is_some())
+            .collect();
+        artifacts.sort_by(|a, b| (a.artifact_kind(), a.key()).cmp(&(b.artifact_kind(), b.key())));
+
+        Ok(artifacts)
+    }
+
+    /// Checks whether `file_name` exists under `file_path` without downloading it.
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        let keys = self.list_objects(file_path).await?;
+        Ok(keys
+            .iter()
+            .any(|key| key.rsplit("/").next() == Some(file_name)))
+    }
+
+    /// Like `fetch_object`, but returns None for a missing object instead of failing, so callers
+    /// don't have to recognize a missing object by its error message.
+    async fn try_fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        match self.fetch_object(file_name, file_path).await {
+            Ok(contents) => Ok(Some(contents)),
+            Err(err) if is_not_found(&err, file_name, file_path) => Ok(None),
+            // Drivers word their errors differently, so ask the store whether the failure was
+            // caused by the object being missing
+            Err(err) => match self.object_exists(file_name, file_path).await? {
+                true => Err(err),
+                false => Ok(None),
+            },
+        }
+    }
+
+    /// Like `fetch_compressed_object`, but returns None for a missing object instead of failing.
+    async fn try_fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Option<FetchResult>, String> {
+        match self.fetch_compressed_object(file_name, file_path).await {
+            Ok(fetched) => Ok(Some(fetched)),
+            Err(err) if is_not_found(&err, file_name, file_path) => Ok(None),
+            Err(err) => match self.object_exists(file_name, file_path).await? {
+                true => Err(err),
+                false => Ok(None),
+            },
+        }
+    }
+
+    /// Fetches the object with the lexicographically first key under `file_path`, e.g. a marker
+    /// object when any one of them will do, returning its key and contents. None if there are no
+    /// objects under `file_path`.
+    async fn fetch_first(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Option<(String, String)>, String> {
+        let Some(key) = self.list_objects(file_path).await?.into_iter().min() else {
+            return Ok(None);
+        };
+
+        let parsed = parse_key(&key);
+        let contents = self
+            .fetch_object(parsed.file_name(), parsed.directory().as_deref())
+            .await?;
+        Ok(Some((key, contents)))
+    }
+
+    /// Fails early with a clear message if the current credentials can't read or write objects
+    /// under `file_path`, e.g. to diagnose IAM policies scoped to specific prefixes at startup.
+    /// Reads are probed with a lookup of a missing object, writes with an empty probe object
+    /// that is deleted again right away. Stores without deletes keep the probe object.
+    async fn check_access(&self, file_path: Option<&str>, access: Access) -> Result<(), String> {
+        let probe = format!("{ACCESS_PROBE_PREFIX}{:016x}", rand::random::<u64>());
+        let prefix = list_prefix(file_path);
+
+        if access.includes_read() {
+            self.object_exists(&probe, file_path)
+                .await
+                .map_err(|err| format!("No read access to {prefix:?}: {err}"))?;
+        }
+        if access.includes_write() {
+            let conditional = self
+                .capabilities()
+                .contains(StoreCapabilities::CONDITIONAL_WRITES);
+            match conditional {
+                true => self.upload_object_if_absent(&probe, "", file_path).await,
+                false => self.upload_object(&probe, "", file_path).await,
+            }
+            .map_err(|err| format!("No write access to {prefix:?}: {err}"))?;
+            if self.capabilities().contains(StoreCapabilities::DELETE) {
+                self.delete_object(&probe, file_path)
+                    .await
+                    .map_err(|err| format!("No delete access to {prefix:?}: {err}"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Polls until a freshly written object becomes visible, for stores that only offer
+    /// eventual read-after-write consistency. Fails once `timeout` has elapsed.
+    async fn verify_after_write(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if self.object_exists(file_name, file_path).await? {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "{file_name} was not visible within {timeout:?} of being written"
+                ));
+            }
+            tokio::time::sleep(VERIFY_AFTER_WRITE_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Total size in bytes of every object under `file_path`, without downloading any of them.
+    async fn prefix_size(&self, _file_path: Option<&str>) -> Result<u64, String> {
+        Err(DataStoreError::Unsupported("prefix_size").to_string())
+    }
+
+    /// The stored and uncompressed size of every object under `file_path` in total, from
+    /// `list_object_metadata` alone. Only as accurate as the raw sizes the backend recorded.
+    async fn prefix_footprint(&self, file_path: Option<&str>) -> Result<PrefixFootprint, String> {
+        let objects = self.list_object_metadata(file_path).await?;
+        Ok(objects
+            .iter()
+            .fold(PrefixFootprint::default(), |footprint, object| {
+                PrefixFootprint {
+                    stored_size: footprint.stored_size + object.size,
+                    raw_size: footprint.raw_size + object.raw_size.unwrap_or(object.size),
+                }
+            }))
+    }
+
+    /// Lists every object under `file_path` with its size and etag, without downloading any.
+    async fn list_object_metadata(
+        &self,
+        _file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        Err(DataStoreError::Unsupported("list_object_metadata").to_string())
+    }
+
+    /// The `n` most recently modified objects under `file_path` with their modification time,
+    /// newest first. No object is downloaded and only `n` keys are held at a time.
+    async fn list_recent(
+        &self,
+        _file_path: Option<&str>,
+        _n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        Err(DataStoreError::Unsupported("list_recent").to_string())
+    }
+
+    /// Compares the objects under two prefixes, e.g. a staging and a production deployment,
+    /// by their listed size and etag. No object is downloaded, so objects with equal sizes are
+    /// only reported as different when both sides have etags and they differ.
+    async fn diff_prefixes(
+        &self,
+        prefix_a: Option<&str>,
+        prefix_b: Option<&str>,
+    ) -> Result<PrefixDiff, String> {
+        let relative = |objects: Vec<ObjectMetadata>, prefix: Option<&str>| {
+            let prefix = list_prefix(prefix);
+            objects
+                .into_iter()
+                .map(|object| {
+                    let key = object
+                        .key
+                        .strip_prefix(&prefix)
+                        .unwrap_or(&object.key)
+                        .to_string();
+                    (key, object)
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let objects_a = relative(self.list_object_metadata(prefix_a).await?, prefix_a);
+        let mut objects_b = relative(self.list_object_metadata(prefix_b).await?, prefix_b);
+
+        let mut diff = PrefixDiff::default();
+        for (key, a) in objects_a {
+            match objects_b.remove(&key) {
+                None => diff.only_in_a.push(key),
+                Some(b) => {
+                    let etags_differ = matches!((&a.etag, &b.etag), (Some(x), Some(y)) if x != y);
+                    if a.size != b.size || etags_differ {
+                        diff.different.push(key);
+                    }
+                }
+            }
+        }
+        diff.only_in_b = objects_b.into_keys().collect();
+
+        Ok(diff)
+    }
+
+    /// Fetches several objects concurrently, returning one result per file name in input order.
+    async fn fetch_objects(
+        &self,
+        file_names: &[String],
+        file_path: Option<&str>,
+    ) -> Vec<Result<String, String>> {
+        stream::iter(file_names)
+            .map(|file_name| self.fetch_object(file_name, file_path))
+            .buffered(FETCH_OBJECTS_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Checks which of `file_names` exist under `file_path`, e.g. which artifacts of a graph are
+    /// present, with several `object_exists` lookups in flight at a time.
+    async fn objects_exist(
+        &self,
+        file_names: &[String],
+        file_path: Option<&str>,
+    ) -> Result<HashMap<String, bool>, String> {
+        stream::iter(file_names)
+            .map(|file_name| async move {
+                let exists = self.object_exists(file_name, file_path).await?;
+                Ok((file_name.clone(), exists))
+            })
+            .buffer_unordered(FETCH_OBJECTS_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// Like `fetch_objects`, but succeeds only if every object was fetched. Otherwise the error
+    /// lists each failed file name with its cause.
+    async fn fetch_objects_strict(
+        &self,
+        file_names: &[String],
+        file_path: Option<&str>,
+    ) -> Result<Vec<String>, BatchError> {
+        let results = self.fetch_objects(file_names, file_path).await;
+
+        let mut objects = Vec::with_capacity(results.len());
+        let mut failures = vec![];
+        for (file_name, result) in file_names.iter().zip(results) {
+            match result {
+                Ok(object) => objects.push(object),
+                Err(err) => failures.push((file_name.clone(), err)),
+            }
+        }
+
+        match failures.is_empty() {
+            true => Ok(objects),
+            false => Err(BatchError { failures }),
+        }
+    }
+
+    /// Fetches an object together with its etag, for read-modify-write cycles guarded by
+    /// `upload_if_etag_matches`.
+    async fn fetch_with_etag(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        Err(DataStoreError::Unsupported("fetch_with_etag").to_string())
+    }
+
+    /// Overwrites an object only if its etag still equals `etag`, returning the new etag.
+    /// Fails with `PreconditionFailed` if another writer got there first, in which case the
+    /// caller should fetch again and retry.
+    async fn upload_if_etag_matches(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+        _etag: &str,
+    ) -> Result<String, String> {
+        Err(DataStoreError::Unsupported("upload_if_etag_matches").to_string())
+    }
+
+    /// Replaces an object with `update` applied to its current contents, or to None if it
+    /// doesn't exist yet, returning the contents written. The upload only succeeds if no other
+    /// writer changed the object since it was fetched, otherwise the whole cycle is retried, up
+    /// to `UPDATE_OBJECT_ATTEMPTS` times. Needs `CONDITIONAL_WRITES`.
+    async fn update_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        update: &(dyn Fn(Option<String>) -> String + Send + Sync),
+    ) -> Result<String, String> {
+        try_update_object(self, file_name, file_path, &|current| Ok(update(current))).await
+    }
+
+    /// Adds `by` to the integer counter stored in an object, e.g. an attempt number, starting
+    /// from 0 if it doesn't exist yet, and returns the new value. Increments are read-modify-write
+    /// cycles like `update_object`, so concurrent increments are never lost. Needs
+    /// `CONDITIONAL_WRITES`.
+    async fn increment(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        by: i64,
+    ) -> Result<i64, String> {
+        let key = object_key(file_name, file_path);
+        let updated = try_update_object(self, file_name, file_path, &|current| {
+            let count = match current {
+                Some(count) => count
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|_| format!("{key} does not hold an integer counter"))?,
+                None => 0,
+            };
+            count
+                .checked_add(by)
+                .map(|count| count.to_string())
+                .ok_or_else(|| format!("Incrementing {key} by {by} overflows"))
+        })
+        .await?;
+
+        updated.parse().map_err(err_to_string)
+    }
+
+    /// Fetches an object's stored bytes as is, without decoding or decompressing them.
+    async fn fetch_raw_object(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        Err(DataStoreError::Unsupported("fetch_raw_object").to_string())
+    }
+
+    /// Fetches `range` of an object's stored bytes without downloading the rest of it,
+    /// returning them with the size of the whole object. Needs `RANGE_READ`.
+    async fn fetch_range(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+        _range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        Err(DataStoreError::Unsupported("fetch_range").to_string())
+    }
+
+    /// The last `k` lines of a plain text object, e.g. an append-only log, oldest first. With
+    /// `RANGE_READ`, the object is read backward from its end in chunks until `k` lines were
+    /// found, so a short tail of a large log downloads little more than the tail. Otherwise the
+    /// whole object is fetched. Lines are split like `str::lines` splits them.
+    async fn read_last_lines(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        k: usize,
+    ) -> Result<Vec<String>, String> {
+        if k == 0 {
+            return Ok(vec![]);
+        }
+        if !self.capabilities().contains(StoreCapabilities::RANGE_READ) {
+            let contents = self.fetch_object(file_name, file_path).await?;
+            return last_lines(contents.as_bytes(), k);
+        }
+
+        let range = ByteRange::Last(READ_LAST_LINES_CHUNK_SIZE);
+        let (mut tail, size) = self.fetch_range(file_name, file_path, range).await?;
+        let mut start = size - tail.len() as u64;
+        // A line is only complete once the newline in front of it was read
+        while start > 0 && count_line_breaks(&tail) < k {
+            let range = ByteRange::Span {
+                start: start.saturating_sub(READ_LAST_LINES_CHUNK_SIZE),
+                end: start,
+            };
+            let (mut chunk, _) = self.fetch_range(file_name, file_path, range).await?;
+            if chunk.is_empty() {
+                return Err(format!(
+                    "{} shrank while its last lines were read",
+                    object_key(file_name, file_path)
+                ));
+            }
+            start -= chunk.len() as u64;
+            chunk.append(&mut tail);
+            tail = chunk;
+        }
+
+        last_lines(&tail, k)
+    }
+
+    /// Like `fetch_compressed_object`, but decompressed contents larger than `spill_threshold`
+    /// bytes are written to a temporary file instead of being held in memory.
+    async fn fetch_compressed_object_spilling(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        spill_threshold: usize,
+    ) -> Result<(DecompressedOutput, usize), String> {
+        let buffer = self.fetch_raw_object(file_name, file_path).await?;
+        let size = buffer.len();
+        let output = decode_object_spilling(&buffer, spill_threshold).map_err(err_to_string)?;
+
+        Ok((output, size))
+    }
+
+    /// Uploads `contents` stored as chosen by `compression`. Objects uploaded with any setting
+    /// are read back with `fetch`. Raw uploads go through `upload_object`, so their contents
+    /// must be valid UTF-8.
+    async fn upload(
+        &self,
+        file_name: &str,
+        contents: &[u8],
+        file_path: Option<&str>,
+        compression: CompressionOverride,
+    ) -> Result<usize, String> {
+        match compression {
+            CompressionOverride::Default => {
+                self.upload_compressed_object(file_name, &contents.to_vec(), file_path)
+                    .await
+            }
+            CompressionOverride::Compressed => {
+                let compressed = compress_once(contents).map_err(err_to_string)?;
+                self.upload_precompressed_object(file_name, &compressed, file_path)
+                    .await
+            }
+            CompressionOverride::Raw => {
+                let contents = std::str::from_utf8(contents).map_err(err_to_string)?;
+                self.upload_object(file_name, contents, file_path).await
+            }
+        }
+    }
+
+    /// Fetches an object uploaded with any compression setting, telling compressed and raw
+    /// objects apart by the object format header, so a prefix holding a mix of both, e.g. in
+    /// the middle of a migration, reads uniformly. Drivers without raw reads get the object
+    /// through the compressed path, and through the plain path if it isn't compressed.
+    async fn fetch(&self, file_name: &str, file_path: Option<&str>) -> Result<Vec<u8>, String> {
+        if !self.capabilities().contains(StoreCapabilities::RAW_READ) {
+            return match self.fetch_compressed_object(file_name, file_path).await {
+                Ok(fetched) if !fetched.decompress_failed => Ok(fetched.data),
+                Err(err) if is_not_found(&err, file_name, file_path) => Err(err),
+                _ => self
+                    .fetch_object(file_name, file_path)
+                    .await
+                    .map(String::into_bytes),
+            };
+        }
+
+        let stored = self.fetch_raw_object(file_name, file_path).await?;
+        match is_encoded(&stored) {
+            true => decode_object(&stored).map_err(err_to_string),
+            false => Ok(stored),
+        }
+    }
+
+    /// Points the `latest` object under `file_path` at `target_key`, e.g. to promote a new
+    /// immutable snapshot. Existing pointers are updated with an etag-guarded write so a
+    /// concurrent promotion fails with `PreconditionFailed` instead of being silently lost.
+    async fn set_latest(&self, file_path: Option<&str>, target_key: &str) -> Result<(), String> {
+        if self
+            .object_exists(LATEST_POINTER_FILE_NAME, file_path)
+            .await?
+        {
+            let (_, etag) = self
+                .fetch_with_etag(LATEST_POINTER_FILE_NAME, file_path)
+                .await?;
+            self.upload_if_etag_matches(LATEST_POINTER_FILE_NAME, target_key, file_path, &etag)
+                .await?;
+        } else {
+            self.upload_object(LATEST_POINTER_FILE_NAME, target_key, file_path)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the key the `latest` object under `file_path` points at.
+    async fn resolve_latest(&self, file_path: Option<&str>) -> Result<String, String> {
+        let target_key = self
+            .fetch_object(LATEST_POINTER_FILE_NAME, file_path)
+            .await?;
+        Ok(target_key.trim().to_string())
+    }
+
+    /// Copies an object within the store without downloading it.
+    async fn copy_object(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+        _target_file_name: &str,
+        _target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        Err(DataStoreError::Unsupported("copy_object").to_string())
+    }
+
+    /// Deletes an object. Deleting an object that does not exist is not an error.
+    async fn delete_object(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+    ) -> Result<(), String> {
+        Err(DataStoreError::Unsupported("delete_object").to_string())
+    }
+
+    /// Exchanges the contents of two objects under `file_path`, e.g. to promote a candidate
+    /// artifact to canonical. This is not atomic: the objects are swapped through server-side
+    /// copies via a temporary `{file_name_a}.swap` object, and earlier steps are rolled back if
+    /// a later one fails. If the process dies mid-swap the temporary object holds the original
+    /// contents of `file_name_a`, and the next swap of the same pair refuses to start until it
+    /// has been restored and removed.
+    async fn swap_objects(
+        &self,
+        file_name_a: &str,
+        file_name_b: &str,
+        file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let temp_file_name = format!("{file_name_a}{SWAP_TEMP_SUFFIX}");
+        if self.object_exists(&temp_file_name, file_path).await? {
+            return Err(format!(
+                "{temp_file_name} already exists, a previous swap of {file_name_a} was interrupted"
+            ));
+        }
+
+        self.copy_object(file_name_a, file_path, &temp_file_name, file_path)
+            .await?;
+        if let Err(err) = self
+            .copy_object(file_name_b, file_path, file_name_a, file_path)
+            .await
+        {
+            self.delete_object(&temp_file_name, file_path).await?;
+            return Err(err);
+        }
+        if let Err(err) = self
+            .copy_object(&temp_file_name, file_path, file_name_b, file_path)
+            .await
+        {
+            self.copy_object(&temp_file_name, file_path, file_name_a, file_path)
+                .await?;
+            self.delete_object(&temp_file_name, file_path).await?;
+            return Err(err);
+        }
+
+        self.delete_object(&temp_file_name, file_path).await
+    }
+
+    /// Uploads everything `reader` produces, so producers don't have to materialize an artifact
+    /// before storing it. `size_hint` lets drivers pick an upload strategy up front. Drivers
+    /// that can't stream read the whole artifact into memory and upload it with `upload_object`.
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        _size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .await
+            .map_err(err_to_string)?;
+        self.upload_object(file_name, &contents, file_path).await
+    }
+
+    /// Uploads a payload already encoded by `compress_once`, skipping compression. Reading it
+    /// back with `fetch_compressed_object` returns the original contents.
+    async fn upload_precompressed_object(
+        &self,
+        _file_name: &str,
+        _compressed: &[u8],
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::Unsupported("upload_precompressed_object").to_string())
+    }
+
+    /// Uploads an object that can't be overwritten or deleted until `retain_until`, for
+    /// records that must be kept tamper-proof (write-once-read-many). Only works on buckets
+    /// that were created with Object Lock enabled.
+    async fn upload_object_locked(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+        _retain_until: SystemTime,
+        _mode: RetentionMode,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::Unsupported("upload_object_locked").to_string())
+    }
+
+    /// Uploads an object tagged with `idempotency_key`, unless the object already exists with
+    /// the same idempotency key, e.g. because an earlier attempt whose response got lost went
+    /// through. Retrying with the same idempotency key therefore writes the object at most once.
+    async fn upload_object_idempotent(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+        _idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        Err(DataStoreError::Unsupported("upload_object_idempotent").to_string())
+    }
+
+    /// Fetches the version of an object that was uploaded as `generation`, e.g. to inspect the
+    /// artifact the bridge had at a known logical version. Fails with `NotFound` if no version
+    /// has that generation. Needs a versioned store that stamps uploads with generations.
+    async fn fetch_generation(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+        _generation: u64,
+    ) -> Result<String, String> {
+        Err(DataStoreError::Unsupported("fetch_generation").to_string())
+    }
+
+    /// Like `upload_object`, but fails with `AlreadyExists` instead of overwriting an existing
+    /// object, atomically with respect to concurrent writers of the same key.
+    async fn upload_object_if_absent(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::Unsupported("upload_object_if_absent").to_string())
+    }
+
+    /// Like `upload_compressed_object`, but fails with `AlreadyExists` instead of overwriting an
+    /// existing object, atomically with respect to concurrent writers of the same key.
+    async fn upload_compressed_object_if_absent(
+        &self,
+        _file_name: &str,
+        _contents: &Vec<u8>,
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::Unsupported("upload_compressed_object_if_absent").to_string())
+    }
+
+    /// Bumps the last-modified time of an object to now without changing its contents, so
+    /// lifecycle rules and `archive_older_than` don't treat an often read but never written
+    /// object as stale. Not free: S3 implements it as a server-side copy of the object onto
+    /// itself, which rewrites the whole object, is billed as a copy request and adds a version
+    /// on versioned buckets.
+    async fn touch(&self, _file_name: &str, _file_path: Option<&str>) -> Result<(), String> {
+        Err(DataStoreError::Unsupported("touch").to_string())
+    }
+
+    /// Flushes anything buffered, stops background work and closes connections, for a clean
+    /// exit without losing queued writes. Wrappers shut down their own state first and then
+    /// their inner store, so a stack of wrappers is drained from the outside in.
+    async fn shutdown(self) -> Result<(), String>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+}
+
+// The read-modify-write cycle of `update_object`, with an `update` that can reject the current
+// contents
+async fn try_update_object<D: DataStoreDriver + Sync + ?Sized>(
+    driver: &D,
+    file_name: &str,
+    file_path: Option<&str>,
+    update: &(dyn Fn(Option<String>) -> Result<String, String> + Send + Sync),
+) -> Result<String, String> {
+    let mut last_err = String::new();
+    for _ in 0..UPDATE_OBJECT_ATTEMPTS {
+        let current = match driver.fetch_with_etag(file_name, file_path).await {
+            Ok((contents, etag)) => Some((contents, etag)),
+            Err(err) if is_not_found(&err, file_name, file_path) => None,
+            Err(err) => match driver.object_exists(file_name, file_path).await? {
+                true => return Err(err),
+                false => None,
+            },
+        };
+
+        let result = match current {
+            Some((contents, etag)) => {
+                let updated = update(Some(contents))?;
+                driver
+                    .upload_if_etag_matches(file_name, &updated, file_path, &etag)
+                    .await
+                    .map(|_| updated)
+            }
+            None => {
+                let updated = update(None)?;
+                driver
+                    .upload_object_if_absent(file_name, &updated, file_path)
+                    .await
+                    .map(|_| updated)
+            }
+        };
+        match result {
+            // The object may also have been deleted since it was fetched
+            Err(err)
+                if is_conflict(&err, file_name, file_path)
+                    || is_not_found(&err, file_name, file_path) =>
+            {
+                last_err = err
+            }
+            result => return result,
+        }
+    }
+
+    Err(last_err)
+}
+
+// Line breaks in `data` that separate two lines, i.e. not counting a trailing one
+fn count_line_breaks(data: &[u8]) -> usize {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    data.iter().filter(|&&byte| byte == b'\n').count()
+}
+
+// The last `k` lines of `data`, which must hold the end of an object
+fn last_lines(data: &[u8], k: usize) -> Result<Vec<String>, String> {
+    if data.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    let mut lines = data
+        .rsplit(|&byte| byte == b'\n')
+        .take(k)
+        .map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            String::from_utf8(line.to_vec()).map_err(err_to_string)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    lines.reverse();
+
+    Ok(lines)
+}
+
+// Whether `err` is the typed `NotFound` error of the object, as returned by most drivers
+pub(crate) fn is_not_found(err: &str, file_name: &str, file_path: Option<&str>) -> bool {
+    err == DataStoreError::NotFound(object_key(file_name, file_path)).to_string()
+}
+
+// Whether a conditional write of the object failed because another writer got there first
+fn is_conflict(err: &str, file_name: &str, file_path: Option<&str>) -> bool {
+    let key = object_key(file_name, file_path);
+    err == DataStoreError::PreconditionFailed(key.clone()).to_string()
+        || err == DataStoreError::AlreadyExists(key).to_string()
 }
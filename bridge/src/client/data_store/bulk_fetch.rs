@@ -0,0 +1,32 @@
+use futures::{stream, Stream, StreamExt};
+
+use super::base::DataStoreDriver;
+use super::key::parse_key;
+
+/// Lists every object under `file_path` and fetches them with at most `concurrency` fetches in
+/// flight, so everything under a prefix can be loaded in one call without holding more than
+/// `concurrency` objects at a time. Objects are read with `fetch`, so compressed and raw objects
+/// are both returned as their original bytes.
+///
+/// Returns each object key with its contents, in the order the fetches complete. A failed
+/// listing is returned as the only item. Nothing is fetched until the returned stream is polled.
+pub fn fetch_prefix_stream<'a>(
+    driver: &'a dyn DataStoreDriver,
+    file_path: Option<&'a str>,
+    concurrency: usize,
+) -> impl Stream<Item = Result<(String, Vec<u8>), String>> + 'a {
+    stream::once(driver.list_objects(file_path))
+        .flat_map(|listing| match listing {
+            Ok(keys) => stream::iter(keys.into_iter().map(Ok)).left_stream(),
+            Err(err) => stream::iter([Err(err)]).right_stream(),
+        })
+        .map(move |key: Result<String, String>| async move {
+            let key = key?;
+            let parsed = parse_key(&key);
+            let contents = driver
+                .fetch(parsed.file_name(), parsed.directory().as_deref())
+                .await?;
+            Ok((key, contents))
+        })
+        .buffer_unordered(concurrency.max(1))
+}
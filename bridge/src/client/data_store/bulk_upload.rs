@@ -0,0 +1,29 @@
+use futures::{Stream, StreamExt};
+
+use super::base::DataStoreDriver;
+
+/// Uploads every `(file_name, contents)` item of `items` under `file_path` as a compressed
+/// object, with at most `concurrency` uploads in flight. The next item is only pulled from
+/// `items` once an upload slot is free, so a producer generating artifacts on demand is
+/// throttled to the upload rate instead of piling them up in memory.
+///
+/// Returns each file name with the result of its upload, in the order the uploads complete.
+/// Nothing is uploaded until the returned stream is polled.
+pub fn upload_stream<'a, S>(
+    driver: &'a dyn DataStoreDriver,
+    items: S,
+    concurrency: usize,
+    file_path: Option<&'a str>,
+) -> impl Stream<Item = (String, Result<usize, String>)> + 'a
+where
+    S: Stream<Item = (String, Vec<u8>)> + 'a,
+{
+    items
+        .map(move |(file_name, contents)| async move {
+            let result = driver
+                .upload_compressed_object(&file_name, &contents, file_path)
+                .await;
+            (file_name, result)
+        })
+        .buffer_unordered(concurrency.max(1))
+}
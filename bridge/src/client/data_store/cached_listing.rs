@@ -0,0 +1,351 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::clock::{Clock, SystemClock};
+use super::key::{list_prefix, object_key};
+use super::stats::{CacheCounters, StoreStats};
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// Serves `list_objects` from a cached listing of the same file path for `ttl` after it was
+// fetched, which saves the LIST requests of callers polling a prefix that rarely changes.
+// Writes and deletes through this wrapper drop every cached listing that could contain the
+// written key, so the wrapper's own changes are visible right away. Changes made by other
+// clients only show up once the cached listing expires.
+pub struct CachedListing<D: DataStoreDriver> {
+    inner: D,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    listings: Mutex<HashMap<String, (SystemTime, Vec<String>)>>, // By list prefix
+    cache: CacheCounters,
+}
+
+impl<D: DataStoreDriver> CachedListing<D> {
+    pub fn new(inner: D, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            clock: Arc::new(SystemClock),
+            listings: Mutex::new(HashMap::new()),
+            cache: CacheCounters::default(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// Drops every cached listing, e.g. after the store was changed by another client.
+    pub fn invalidate_all(&self) {
+        self.listings.lock().unwrap().clear();
+    }
+
+    fn cached(&self, prefix: &str) -> Option<Vec<String>> {
+        let keys = self
+            .listings
+            .lock()
+            .unwrap()
+            .get(prefix)
+            .filter(|(fetched_at, _)| self.clock.elapsed_since(*fetched_at) < self.ttl)
+            .map(|(_, keys)| keys.clone());
+        self.cache.record(keys.is_some());
+        keys
+    }
+
+    // Listings include keys in nested directories, so a write invalidates the listing of every
+    // prefix of its key, not only of its own file path
+    fn invalidate(&self, file_name: &str, file_path: Option<&str>) {
+        let key = object_key(file_name, file_path);
+        self.listings
+            .lock()
+            .unwrap()
+            .retain(|prefix, _| !key.starts_with(prefix.as_str()));
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for CachedListing<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats() + self.cache.snapshot()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let prefix = list_prefix(file_path);
+        if let Some(keys) = self.cached(&prefix) {
+            return Ok(keys);
+        }
+
+        let fetched_at = self.clock.now();
+        let keys = self.inner.list_objects(file_path).await?;
+        self.listings
+            .lock()
+            .unwrap()
+            .insert(prefix, (fetched_at, keys.clone()));
+        Ok(keys)
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.inner.fetch_object(file_name, file_path).await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.inner.fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let result = self
+            .inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.inner
+            .fetch_generation(file_name, file_path, generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let result = self
+            .inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await;
+        self.invalidate(target_file_name, target_file_path);
+        result
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.delete_object(file_name, file_path).await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
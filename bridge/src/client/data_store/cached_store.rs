@@ -0,0 +1,346 @@
+use std::{num::NonZeroUsize, sync::Mutex, time::SystemTime};
+
+use lru::LruCache;
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::key::object_key;
+use super::stats::{CacheCounters, StoreStats};
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// Keeps the contents of up to `capacity` recently fetched objects in memory and serves
+// `fetch_object` from them, evicting the least recently used object once full. Bridge artifacts
+// are written once and read many times, so the cache trades a little memory for most GET requests.
+//
+// Every write and delete through this wrapper drops the cached contents of its key. With
+// `populate_on_write`, plain uploads instead cache the contents they wrote, so a read right after
+// a write needs no request at all. Changes made by other clients are not noticed, so only use it
+// for keys that are never overwritten by anyone else.
+pub struct CachedStore<D: DataStoreDriver> {
+    inner: D,
+    populate_on_write: bool,
+    objects: Mutex<LruCache<String, String>>, // By object key
+    cache: CacheCounters,
+}
+
+impl<D: DataStoreDriver> CachedStore<D> {
+    pub fn new(inner: D, capacity: usize) -> Self {
+        Self {
+            inner,
+            populate_on_write: false,
+            objects: Mutex::new(LruCache::new(NonZeroUsize::new(capacity.max(1)).unwrap())),
+            cache: CacheCounters::default(),
+        }
+    }
+
+    pub fn with_populate_on_write(mut self, populate_on_write: bool) -> Self {
+        self.populate_on_write = populate_on_write;
+        self
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// Drops every cached object, e.g. after the store was changed by another client.
+    pub fn invalidate_all(&self) {
+        self.objects.lock().unwrap().clear();
+    }
+
+    fn invalidate(&self, file_name: &str, file_path: Option<&str>) {
+        self.objects
+            .lock()
+            .unwrap()
+            .pop(&object_key(file_name, file_path));
+    }
+
+    // Called after every plain upload with the contents it wrote, or None if it failed
+    fn written(&self, file_name: &str, file_path: Option<&str>, contents: Option<&str>) {
+        match contents.filter(|_| self.populate_on_write) {
+            Some(contents) => {
+                self.objects
+                    .lock()
+                    .unwrap()
+                    .put(object_key(file_name, file_path), contents.to_string());
+            }
+            None => self.invalidate(file_name, file_path),
+        }
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for CachedStore<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats() + self.cache.snapshot()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let key = object_key(file_name, file_path);
+        let cached = self.objects.lock().unwrap().get(&key).cloned();
+        self.cache.record(cached.is_some());
+        if let Some(contents) = cached {
+            return Ok(contents);
+        }
+
+        let contents = self.inner.fetch_object(file_name, file_path).await?;
+        self.objects.lock().unwrap().put(key, contents.clone());
+        Ok(contents)
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.written(file_name, file_path, result.is_ok().then_some(contents));
+        result
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.inner.fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await;
+        self.written(file_name, file_path, result.is_ok().then_some(contents));
+        result
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await;
+        self.written(file_name, file_path, result.is_ok().then_some(contents));
+        result
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let result = self
+            .inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await;
+        let uploaded = matches!(result, Ok(UploadOutcome::Uploaded(_)));
+        self.written(file_name, file_path, uploaded.then_some(contents));
+        result
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.inner
+            .fetch_generation(file_name, file_path, generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.written(file_name, file_path, result.is_ok().then_some(contents));
+        result
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let result = self
+            .inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await;
+        self.invalidate(target_file_name, target_file_path);
+        result
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.delete_object(file_name, file_path).await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await;
+        self.invalidate(file_name, file_path);
+        result
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
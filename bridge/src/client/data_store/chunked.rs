@@ -0,0 +1,342 @@
+use std::time::SystemTime;
+
+use crate::error::DataStoreError;
+
+use super::base::{
+    DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities, UploadOutcome,
+};
+use super::integrity::{split_checksum, HashAlgorithm};
+use super::key::object_key;
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use fastcdc::v2020::FastCDC;
+
+const MANIFEST_HEADER: &str = "bitvm-chunked-manifest-v1\n";
+const CHUNKS_FILE_PATH: &str = "chunks";
+const MIN_CHUNK_SIZE: u32 = 16 * 1024;
+const AVG_CHUNK_SIZE: u32 = 64 * 1024;
+const MAX_CHUNK_SIZE: u32 = 256 * 1024;
+
+// Wraps any data store driver and can store large objects as content-defined chunks, so
+// successive versions of a graph only add the chunks that changed. Each chunk is stored once
+// under `chunks/` named by its checksum, and the object itself becomes a manifest listing its
+// chunk checksums. `fetch_object` transparently reassembles manifests and verifies every chunk
+// against its name. This trades storage for more, smaller objects and one request per chunk on
+// reads, so only use it for large objects.
+//
+// Chunk names record their hash algorithm, so changing `hash_algorithm` only affects new chunks
+// and manifests written with another algorithm keep reading fine. The same data hashed with two
+// algorithms is stored twice though.
+pub struct Chunked<D: DataStoreDriver> {
+    inner: D,
+    hash_algorithm: HashAlgorithm,
+}
+
+impl<D: DataStoreDriver> Chunked<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            hash_algorithm: HashAlgorithm::default(),
+        }
+    }
+
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: DataStoreDriver + Send + Sync> Chunked<D> {
+    /// Uploads `contents` as deduplicated chunks plus a manifest stored under `file_name`.
+    /// Returns the number of bytes newly written, excluding chunks that were already stored.
+    pub async fn upload_object_chunked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let data = contents.as_bytes();
+        let mut manifest = MANIFEST_HEADER.to_string();
+        let mut size = 0;
+
+        for chunk in FastCDC::new(data, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE) {
+            let bytes = &data[chunk.offset..chunk.offset + chunk.length];
+            let hash = self.hash_algorithm.checksum(bytes);
+
+            if !self
+                .inner
+                .object_exists(&hash, Some(CHUNKS_FILE_PATH))
+                .await?
+            {
+                size += self
+                    .inner
+                    .upload_compressed_object(&hash, &bytes.to_vec(), Some(CHUNKS_FILE_PATH))
+                    .await?;
+            }
+            manifest.push_str(&hash);
+            manifest.push('\n');
+        }
+
+        // Written last so a manifest never refers to chunks that are not stored yet
+        size += self
+            .inner
+            .upload_object(file_name, &manifest, file_path)
+            .await?;
+
+        Ok(size)
+    }
+
+    async fn reassemble(&self, manifest: &str) -> Result<String, String> {
+        let mut data = vec![];
+        for hash in manifest.lines().filter(|line| !line.is_empty()) {
+            let mut chunk = self
+                .inner
+                .fetch_compressed_object(hash, Some(CHUNKS_FILE_PATH))
+                .await?;
+            let (algorithm, digest) = split_checksum(hash)?;
+            if algorithm.digest_hex(&chunk.data) != digest {
+                let key = object_key(hash, Some(CHUNKS_FILE_PATH));
+                return Err(DataStoreError::IntegrityError(key).to_string());
+            }
+            data.append(&mut chunk.data);
+        }
+
+        String::from_utf8(data).map_err(|err| format!("Failed to parse json: {}", err))
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for Chunked<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        // Ranges and raw reads of a chunked object would be those of its manifest
+        self.inner
+            .capabilities()
+            .difference(StoreCapabilities::RANGE_READ | StoreCapabilities::RAW_READ)
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let contents = self.inner.fetch_object(file_name, file_path).await?;
+        match contents.strip_prefix(MANIFEST_HEADER) {
+            Some(manifest) => self.reassemble(manifest).await,
+            None => Ok(contents),
+        }
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object(file_name, contents, file_path)
+            .await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await
+    }
+
+    async fn list_objects_with_suffix(
+        &self,
+        file_path: Option<&str>,
+        suffix: &str,
+    ) -> Result<Vec<String>, String> {
+        self.inner.list_objects_with_suffix(file_path, suffix).await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let (contents, etag) = self.inner.fetch_with_etag(file_name, file_path).await?;
+        match contents.strip_prefix(MANIFEST_HEADER) {
+            Some(manifest) => Ok((self.reassemble(manifest).await?, etag)),
+            None => Ok((contents, etag)),
+        }
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        self.inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let contents = self
+            .inner
+            .fetch_generation(file_name, file_path, generation)
+            .await?;
+        match contents.strip_prefix(MANIFEST_HEADER) {
+            Some(manifest) => self.reassemble(manifest).await,
+            None => Ok(contents),
+        }
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await
+    }
+
+    // Chunks may be shared with other manifests, so only the manifest itself is deleted
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.delete_object(file_name, file_path).await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
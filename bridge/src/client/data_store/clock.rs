@@ -0,0 +1,63 @@
+use std::{
+    fmt::Debug,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// Every wall-clock read in the data store goes through a `Clock` so that time-dependent
+// behaviour can be tested deterministically with a `MockClock`.
+pub trait Clock: Send + Sync + Debug {
+    fn now(&self) -> SystemTime;
+
+    fn unix_millis(&self) -> u128 {
+        self.now().duration_since(UNIX_EPOCH).unwrap().as_millis()
+    }
+
+    /// Time elapsed since `earlier`, or zero if `earlier` is in the future.
+    fn elapsed_since(&self, earlier: SystemTime) -> Duration {
+        self.now().duration_since(earlier).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+// A clock that only moves when told to. Only meant for tests.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<SystemTime>,
+}
+
+impl MockClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(UNIX_EPOCH)
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
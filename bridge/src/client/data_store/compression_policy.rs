@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use super::key::{object_key, parse_key};
+
+// How the compressed upload of one artifact class is encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionRule {
+    // Store as is, e.g. for proof blobs that are already binary and dense
+    Skip,
+    Compress { level: i32 },
+    // Learn the level per artifact type from the first uploads, see `CompressionTuner`
+    Auto,
+}
+
+// Picks a `CompressionRule` per artifact class for compressed uploads. A rule matching the file
+// name's suffix wins over one matching the artifact kind of a graph artifact key, and the longest
+// matching suffix wins among suffixes. Uploads matching no rule use the default level.
+//
+// Only the writer's side is configurable: every object carries a format header, so reads decode
+// any object the same way regardless of the policy it was written with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompressionPolicy {
+    by_suffix: Vec<(String, CompressionRule)>,
+    by_artifact_kind: HashMap<String, CompressionRule>,
+}
+
+impl CompressionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_suffix(mut self, suffix: &str, rule: CompressionRule) -> Self {
+        self.by_suffix.push((suffix.to_string(), rule));
+        self
+    }
+
+    pub fn with_artifact_kind(mut self, kind: &str, rule: CompressionRule) -> Self {
+        self.by_artifact_kind.insert(kind.to_string(), rule);
+        self
+    }
+
+    /// Parses a comma separated list of `suffix:<suffix>=<rule>` and `kind:<artifact kind>=<rule>`
+    /// entries, where `<rule>` is `skip`, `auto` or a compression level, e.g.
+    /// `suffix:.bin=skip,kind:peg_out=19,kind:peg_in=auto`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut policy = Self::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let invalid = || format!("Invalid compression policy entry {entry:?}");
+            let (selector, rule) = entry.split_once('=').ok_or_else(invalid)?;
+            let rule = match rule.trim() {
+                "skip" => CompressionRule::Skip,
+                "auto" => CompressionRule::Auto,
+                level => CompressionRule::Compress {
+                    level: level.parse().map_err(|_| invalid())?,
+                },
+            };
+            policy = match selector.trim().split_once(':') {
+                Some(("suffix", suffix)) => policy.with_suffix(suffix, rule),
+                Some(("kind", kind)) => policy.with_artifact_kind(kind, rule),
+                _ => return Err(invalid()),
+            };
+        }
+
+        Ok(policy)
+    }
+
+    /// The rule configured for an object, or None if the default applies.
+    pub fn rule_for(&self, file_name: &str, file_path: Option<&str>) -> Option<CompressionRule> {
+        let by_suffix = self
+            .by_suffix
+            .iter()
+            .filter(|(suffix, _)| file_name.ends_with(suffix.as_str()))
+            .max_by_key(|(suffix, _)| suffix.len())
+            .map(|(_, rule)| *rule);
+        by_suffix.or_else(|| {
+            let key = parse_key(&object_key(file_name, file_path));
+            key.artifact_kind()
+                .and_then(|kind| self.by_artifact_kind.get(kind))
+                .copied()
+        })
+    }
+}
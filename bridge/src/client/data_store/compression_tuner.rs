@@ -0,0 +1,151 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use crate::error::err_to_string;
+
+use super::clock::Clock;
+use super::format::encode_object_with_min_size;
+use super::key::{object_key, parse_key};
+
+// Levels tried on each sample upload, from fastest to smallest output
+pub const CANDIDATE_LEVELS: [i32; 4] = [1, 3, 9, 19];
+// Sample uploads per artifact type before its level is settled
+pub const SAMPLE_UPLOADS: usize = 3;
+// A level's total output may be this much larger than the smallest one and still win on speed
+const SIZE_TOLERANCE: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct LevelStats {
+    compressed_size: usize,
+    elapsed: Duration,
+}
+
+#[derive(Debug, Default)]
+struct TypeStats {
+    samples: usize,
+    levels: HashMap<i32, LevelStats>,
+    learned_level: Option<i32>,
+}
+
+// Learns a compression level per artifact type for uploads under `CompressionRule::Auto`. The
+// first `SAMPLE_UPLOADS` uploads of a type are compressed at every candidate level, measuring the
+// output size and compression time. The type then settles on the fastest level whose total output
+// is within `SIZE_TOLERANCE` of the smallest, which every later upload of the type uses.
+// Compression is timed with the caller's clock, so a `MockClock` makes the choice reproducible:
+// every level then takes no time and the lowest level within the size tolerance wins.
+//
+// Learned levels live in memory only, so every process learns them again after a restart.
+#[derive(Debug, Default)]
+pub struct CompressionTuner {
+    types: Mutex<HashMap<String, TypeStats>>,
+}
+
+impl CompressionTuner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The artifact type uploads are tuned by: the artifact kind of a graph artifact key, e.g.
+    /// `peg_out`, or else the file name extension.
+    pub fn artifact_type(file_name: &str, file_path: Option<&str>) -> String {
+        let key = parse_key(&object_key(file_name, file_path));
+        key.artifact_kind()
+            .or(key.extension())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// The level learned for `artifact_type`, or None while it is still being sampled.
+    pub fn learned_level(&self, artifact_type: &str) -> Option<i32> {
+        self.types
+            .lock()
+            .unwrap()
+            .get(artifact_type)
+            .and_then(|stats| stats.learned_level)
+    }
+
+    /// Every level learned so far, by artifact type.
+    pub fn learned_levels(&self) -> HashMap<String, i32> {
+        self.types
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(artifact_type, stats)| {
+                Some((artifact_type.clone(), stats.learned_level?))
+            })
+            .collect()
+    }
+
+    /// Encodes the contents of a compressed upload at the learned level, or samples the
+    /// candidate levels, timed with `clock`, if the object's artifact type hasn't settled on one
+    /// yet.
+    pub fn encode(
+        &self,
+        contents: &[u8],
+        file_name: &str,
+        file_path: Option<&str>,
+        min_compress_size: usize,
+        clock: &dyn Clock,
+    ) -> Result<Vec<u8>, String> {
+        let artifact_type = Self::artifact_type(file_name, file_path);
+        if let Some(level) = self.learned_level(&artifact_type) {
+            return encode_object_with_min_size(contents, level, min_compress_size)
+                .map_err(err_to_string);
+        }
+
+        let mut outputs = HashMap::new();
+        let mut sample = HashMap::new();
+        for level in CANDIDATE_LEVELS {
+            let started = clock.now();
+            let output = encode_object_with_min_size(contents, level, min_compress_size)
+                .map_err(err_to_string)?;
+            sample.insert(
+                level,
+                LevelStats {
+                    compressed_size: output.len(),
+                    elapsed: clock.elapsed_since(started),
+                },
+            );
+            outputs.insert(level, output);
+        }
+
+        // The sample itself is stored at the level that would win on it alone
+        let level = best_level(&sample);
+        self.record_sample(artifact_type, sample);
+
+        Ok(outputs.remove(&level).unwrap())
+    }
+
+    fn record_sample(&self, artifact_type: String, sample: HashMap<i32, LevelStats>) {
+        let mut types = self.types.lock().unwrap();
+        let stats = types.entry(artifact_type).or_default();
+        // Concurrent uploads of a type may all have sampled before the first one settled it
+        if stats.learned_level.is_some() {
+            return;
+        }
+
+        for (level, level_sample) in sample {
+            let total = stats.levels.entry(level).or_default();
+            total.compressed_size += level_sample.compressed_size;
+            total.elapsed += level_sample.elapsed;
+        }
+        stats.samples += 1;
+        if stats.samples >= SAMPLE_UPLOADS {
+            stats.learned_level = Some(best_level(&stats.levels));
+        }
+    }
+}
+
+fn best_level(levels: &HashMap<i32, LevelStats>) -> i32 {
+    let smallest = levels
+        .values()
+        .map(|stats| stats.compressed_size)
+        .min()
+        .unwrap_or_default();
+    let max_size = smallest as f64 * (1.0 + SIZE_TOLERANCE);
+    levels
+        .iter()
+        .filter(|(_, stats)| stats.compressed_size as f64 <= max_size)
+        .min_by_key(|(level, stats)| (stats.elapsed, **level))
+        .map(|(level, _)| *level)
+        .unwrap_or(CANDIDATE_LEVELS[0])
+}
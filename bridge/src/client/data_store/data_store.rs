@@ -1,9 +1,12 @@
 use regex::Regex;
 use std::cmp::Ordering;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::Duration;
 
 use super::base::DataStoreDriver;
+use super::clock::{Clock, SystemClock};
 use super::local_file::LocalFile;
+use super::timestamped::{upload_compressed_timestamped, upload_timestamped};
 use super::{
     aws_s3::AwsS3,
     ftp::{ftp::Ftp, ftps::Ftps},
@@ -23,6 +26,7 @@ pub struct DataStore {
     ftps: Option<Ftps>,
     sftp: Option<Sftp>,
     local_file: Option<LocalFile>,
+    clock: Arc<dyn Clock>,
 }
 
 impl DataStore {
@@ -32,17 +36,44 @@ impl DataStore {
             Ok(suffix) => suffix,
             Err(_) => String::from(DEFAULT_CLIENT_DATA_SUFFIX),
         };
+        let aws_s3 = AwsS3::new();
+        if let (Some(aws_s3), Some(older_than)) = (
+            &aws_s3,
+            dotenv::var("BRIDGE_AWS_ABORT_INCOMPLETE_UPLOADS_AFTER")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok()),
+        ) {
+            if let Err(err) = aws_s3
+                .abort_incomplete_uploads(None, Duration::from_secs(older_than))
+                .await
+            {
+                eprintln!("Failed to abort incomplete uploads: {err}");
+            }
+        }
+
         Self {
             client_data_suffix: client_data_suffix.clone(),
-            client_data_regex: Regex::new(&format!(r"(\d{{13}}){}", client_data_suffix)).unwrap(),
-            aws_s3: AwsS3::new(),
+            // Files written before names got a random part have the timestamp only
+            client_data_regex: Regex::new(&format!(
+                r"(\d{{13}})(-[0-9a-f]{{8}})?{}",
+                client_data_suffix
+            ))
+            .unwrap(),
+            aws_s3,
             ftp: Ftp::new().await,
             ftps: Ftps::new().await,
             sftp: Sftp::new().await,
             local_file: LocalFile::new(),
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Replaces the clock used to timestamp data files, e.g. with a `MockClock` in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn get_file_timestamp(&self, file_name: &str) -> Result<u64, String> {
         if self.client_data_regex.is_match(file_name) {
             let mut timestamp_string = file_name.to_owned();
@@ -92,16 +123,18 @@ impl DataStore {
         file_path: Option<&str>,
     ) -> Result<Option<String>, String> {
         match self.get_driver() {
-            Ok(driver) => {
-                let json = driver.fetch_object(key, file_path).await;
-                if let Ok(data) = json {
-                    // println!("Fetched data file: {}", key);
-                    return Ok(Some(data));
+            // An unreadable data file is skipped like a missing one, so it can't abort a sync
+            Ok(driver) => match driver.try_fetch_object(key, file_path).await {
+                Ok(Some(json)) => Ok(Some(json)),
+                Ok(None) => {
+                    println!("No data file {} found", key);
+                    Ok(None)
                 }
-
-                println!("No data file {} found", key);
-                Ok(None)
-            }
+                Err(err) => {
+                    eprintln!("Failed to read data file {}: {}", key, err);
+                    Ok(None)
+                }
+            },
             Err(err) => Err(err.to_string()),
         }
     }
@@ -113,15 +146,13 @@ impl DataStore {
     ) -> Result<String, String> {
         match self.get_driver() {
             Ok(driver) => {
-                let time = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis();
-                let file_name = self.create_file_name(time);
-                let response = driver.upload_object(&file_name, contents, file_path).await;
+                let time = self.clock.unix_millis();
+                let response =
+                    upload_timestamped(driver, time, &self.client_data_suffix, contents, file_path)
+                        .await;
 
                 match response {
-                    Ok(_) => Ok(file_name),
+                    Ok(file_name) => Ok(file_name),
                     Err(_) => Err(String::from("Failed to save data file")),
                 }
             }
@@ -135,16 +166,17 @@ impl DataStore {
         file_path: Option<&str>,
     ) -> Result<(Option<Vec<u8>>, usize), String> {
         match self.get_driver() {
-            Ok(driver) => {
-                let json = driver.fetch_compressed_object(key, file_path).await;
-                if let Ok((data, size)) = json {
-                    // println!("Fetched data file: {}", key);
-                    return Ok((Some(data), size));
+            Ok(driver) => match driver.try_fetch_compressed_object(key, file_path).await {
+                Ok(Some(fetched)) => Ok((Some(fetched.data), fetched.stored_size)),
+                Ok(None) => {
+                    println!("No data file {} found", key);
+                    Ok((None, 0))
                 }
-
-                println!("No data file {} found", key);
-                Ok((None, 0))
-            }
+                Err(err) => {
+                    eprintln!("Failed to read data file {}: {}", key, err);
+                    Ok((None, 0))
+                }
+            },
             Err(err) => Err(err.to_string()),
         }
     }
@@ -156,17 +188,18 @@ impl DataStore {
     ) -> Result<(String, usize), String> {
         match self.get_driver() {
             Ok(driver) => {
-                let time = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis();
-                let file_name = self.create_file_name(time);
-                let response = driver
-                    .upload_compressed_object(&file_name, contents, file_path)
-                    .await;
+                let time = self.clock.unix_millis();
+                let response = upload_compressed_timestamped(
+                    driver,
+                    time,
+                    &self.client_data_suffix,
+                    contents,
+                    file_path,
+                )
+                .await;
 
                 match response {
-                    Ok(size) => Ok((file_name, size)),
+                    Ok((file_name, size)) => Ok((file_name, size)),
                     Err(_) => Err(String::from("Failed to save data file")),
                 }
             }
@@ -188,6 +221,27 @@ impl DataStore {
         format!("{}{}", timestamp, self.client_data_suffix)
     }
 
+    /// Shuts down every configured driver, see `DataStoreDriver::shutdown`. Call it before
+    /// exiting so no writes are left queued.
+    pub async fn shutdown(self) -> Result<(), String> {
+        if let Some(local_file) = self.local_file {
+            local_file.shutdown().await?;
+        }
+        if let Some(aws_s3) = self.aws_s3 {
+            aws_s3.shutdown().await?;
+        }
+        if let Some(ftp) = self.ftp {
+            ftp.shutdown().await?;
+        }
+        if let Some(ftps) = self.ftps {
+            ftps.shutdown().await?;
+        }
+        if let Some(sftp) = self.sftp {
+            sftp.shutdown().await?;
+        }
+        Ok(())
+    }
+
     fn get_driver(&self) -> Result<&dyn DataStoreDriver, &str> {
         if self.local_file.is_some() {
             Ok(self.local_file.as_ref().unwrap())
@@ -0,0 +1,364 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use crate::error::DataStoreError;
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::clock::{Clock, SystemClock};
+use super::key::object_key;
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// Test double that simulates an eventually consistent store: for `window` after an upload
+// the new object is reported as missing even though the write went through to `inner`.
+// Only meant for tests, DO NOT use in production.
+pub struct DelayedConsistencyStore<D: DataStoreDriver> {
+    inner: D,
+    window: Duration,
+    clock: Arc<dyn Clock>,
+    written_at: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl<D: DataStoreDriver> DelayedConsistencyStore<D> {
+    pub fn new(inner: D, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            clock: Arc::new(SystemClock),
+            written_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn record_write(&self, file_name: &str, file_path: Option<&str>) {
+        self.written_at
+            .lock()
+            .unwrap()
+            .insert(object_key(file_name, file_path), self.clock.now());
+    }
+
+    fn is_hidden(&self, key: &str) -> bool {
+        self.written_at
+            .lock()
+            .unwrap()
+            .get(key)
+            .is_some_and(|written_at| self.clock.elapsed_since(*written_at) < self.window)
+    }
+
+    fn check_visible(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let key = object_key(file_name, file_path);
+        match self.is_hidden(&key) {
+            true => Err(DataStoreError::NotFound(key).to_string()),
+            false => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for DelayedConsistencyStore<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let keys = self.inner.list_objects(file_path).await?;
+        let written_at = self.written_at.lock().unwrap();
+        Ok(keys
+            .into_iter()
+            .filter(|key| {
+                !written_at.iter().any(|(hidden_key, written_at)| {
+                    key.ends_with(hidden_key.as_str())
+                        && self.clock.elapsed_since(*written_at) < self.window
+                })
+            })
+            .collect())
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner.fetch_object(file_name, file_path).await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(size)
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(size)
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        if self.is_hidden(&object_key(file_name, file_path)) {
+            return Ok(false);
+        }
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let objects = self.inner.list_object_metadata(file_path).await?;
+        Ok(objects
+            .into_iter()
+            .filter(|object| !self.is_hidden(&object.key))
+            .collect())
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        let objects = self.inner.list_recent(file_path, n).await?;
+        Ok(objects
+            .into_iter()
+            .filter(|(key, _)| !self.is_hidden(key))
+            .collect())
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner.fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let etag = self
+            .inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(etag)
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let size = self
+            .inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(size)
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let outcome = self
+            .inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await?;
+        if let UploadOutcome::Uploaded(_) = outcome {
+            self.record_write(file_name, file_path);
+        }
+
+        Ok(outcome)
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner
+            .fetch_generation(file_name, file_path, generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(size)
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(size)
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.check_visible(file_name, file_path)?;
+        self.inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await?;
+        self.record_write(target_file_name, target_file_path);
+
+        Ok(())
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.delete_object(file_name, file_path).await
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(size)
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await?;
+        self.record_write(file_name, file_path);
+
+        Ok(size)
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
@@ -0,0 +1,56 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use aws_smithy_runtime_api::client::dns::{DnsFuture, ResolveDns, ResolveDnsError};
+
+// Resolves host names with the system resolver and remembers the answers for `ttl`.
+// A longer ttl means fewer lookups under bursty load, but also that a failover to new
+// endpoint addresses is only noticed once the cached entry expires.
+#[derive(Clone, Debug)]
+pub struct CachingDnsResolver {
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, (Instant, Vec<IpAddr>)>>>,
+}
+
+impl CachingDnsResolver {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn cached(&self, name: &str) -> Option<Vec<IpAddr>> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(name)
+            .filter(|(resolved_at, _)| resolved_at.elapsed() < self.ttl)
+            .map(|(_, addresses)| addresses.clone())
+    }
+}
+
+impl ResolveDns for CachingDnsResolver {
+    fn resolve_dns<'a>(&'a self, name: &'a str) -> DnsFuture<'a> {
+        DnsFuture::new(async move {
+            if let Some(addresses) = self.cached(name) {
+                return Ok(addresses);
+            }
+
+            let addresses: Vec<IpAddr> = tokio::net::lookup_host((name, 0))
+                .await
+                .map_err(ResolveDnsError::new)?
+                .map(|address| address.ip())
+                .collect();
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), (Instant::now(), addresses.clone()));
+
+            Ok(addresses)
+        })
+    }
+}
@@ -0,0 +1,391 @@
+use std::{collections::HashMap, sync::Mutex, time::SystemTime};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Wraps any data store driver and replaces every path segment and file name with its
+// HMAC-SHA256 under `secret`, so bucket listings don't reveal graph ids or other metadata
+// encoded in names. Callers keep using logical names: the opaque names are translated back
+// through a mapping of every name this instance has seen. Objects written by another instance
+// are only listed under their logical names once that instance's mapping is imported.
+//
+// Names are hashed deterministically so objects can be looked up without the mapping, which
+// means equal logical names still produce equal stored names and that equality is visible.
+pub struct EncryptedKeys<D: DataStoreDriver> {
+    inner: D,
+    secret: Vec<u8>,
+    names: Mutex<HashMap<String, String>>, // opaque name -> logical name
+}
+
+impl<D: DataStoreDriver> EncryptedKeys<D> {
+    pub fn new(inner: D, secret: &[u8]) -> Self {
+        Self {
+            inner,
+            secret: secret.to_vec(),
+            names: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// The opaque to logical name mapping, to be persisted by the owner and handed to
+    /// `import_mapping` by later instances.
+    pub fn export_mapping(&self) -> HashMap<String, String> {
+        self.names.lock().unwrap().clone()
+    }
+
+    pub fn import_mapping(&self, mapping: HashMap<String, String>) {
+        self.names.lock().unwrap().extend(mapping);
+    }
+
+    fn opaque_name(&self, name: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key size");
+        mac.update(name.as_bytes());
+        let opaque: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        self.names
+            .lock()
+            .unwrap()
+            .insert(opaque.clone(), name.to_string());
+        opaque
+    }
+
+    fn opaque_path(&self, file_path: Option<&str>) -> Option<String> {
+        file_path.map(|path| {
+            path.split('/')
+                .map(|segment| match segment.is_empty() {
+                    true => segment.to_string(),
+                    false => self.opaque_name(segment),
+                })
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+    }
+
+    fn logical_key(&self, key: &str) -> String {
+        let names = self.names.lock().unwrap();
+        key.split('/')
+            .map(|segment| names.get(segment).map_or(segment, String::as_str))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for EncryptedKeys<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    // Stored keys have a fixed length per path segment whatever the logical key's length, so
+    // no limit applies to logical keys. The inner store still rejects stored keys over its own.
+    fn max_key_length(&self) -> Option<usize> {
+        None
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let file_path = self.opaque_path(file_path);
+        let keys = self.inner.list_objects(file_path.as_deref()).await?;
+        Ok(keys.iter().map(|key| self.logical_key(key)).collect())
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .fetch_object(&self.opaque_name(file_name), file_path.as_deref())
+            .await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_object(&self.opaque_name(file_name), contents, file_path.as_deref())
+            .await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .fetch_compressed_object(&self.opaque_name(file_name), file_path.as_deref())
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_compressed_object(&self.opaque_name(file_name), contents, file_path.as_deref())
+            .await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .object_exists(&self.opaque_name(file_name), file_path.as_deref())
+            .await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner.prefix_size(file_path.as_deref()).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let file_path = self.opaque_path(file_path);
+        let objects = self
+            .inner
+            .list_object_metadata(file_path.as_deref())
+            .await?;
+        Ok(objects
+            .into_iter()
+            .map(|object| ObjectMetadata {
+                key: self.logical_key(&object.key),
+                ..object
+            })
+            .collect())
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        let file_path = self.opaque_path(file_path);
+        let objects = self.inner.list_recent(file_path.as_deref(), n).await?;
+        Ok(objects
+            .into_iter()
+            .map(|(key, last_modified)| (self.logical_key(&key), last_modified))
+            .collect())
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .fetch_with_etag(&self.opaque_name(file_name), file_path.as_deref())
+            .await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_if_etag_matches(
+                &self.opaque_name(file_name),
+                contents,
+                file_path.as_deref(),
+                etag,
+            )
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_object_locked(
+                &self.opaque_name(file_name),
+                contents,
+                file_path.as_deref(),
+                retain_until,
+                mode,
+            )
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_object_idempotent(
+                &self.opaque_name(file_name),
+                contents,
+                file_path.as_deref(),
+                idempotency_key,
+            )
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .fetch_generation(
+                &self.opaque_name(file_name),
+                file_path.as_deref(),
+                generation,
+            )
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_object_if_absent(&self.opaque_name(file_name), contents, file_path.as_deref())
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_compressed_object_if_absent(
+                &self.opaque_name(file_name),
+                contents,
+                file_path.as_deref(),
+            )
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .touch(&self.opaque_name(file_name), file_path.as_deref())
+            .await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .fetch_raw_object(&self.opaque_name(file_name), file_path.as_deref())
+            .await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .fetch_range(&self.opaque_name(file_name), file_path.as_deref(), range)
+            .await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let file_path = self.opaque_path(file_path);
+        let target_file_path = self.opaque_path(target_file_path);
+        self.inner
+            .copy_object(
+                &self.opaque_name(file_name),
+                file_path.as_deref(),
+                &self.opaque_name(target_file_name),
+                target_file_path.as_deref(),
+            )
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .delete_object(&self.opaque_name(file_name), file_path.as_deref())
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let file_path = self.opaque_path(file_path);
+        self.inner
+            .upload_precompressed_object(
+                &self.opaque_name(file_name),
+                compressed,
+                file_path.as_deref(),
+            )
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
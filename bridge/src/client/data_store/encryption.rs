@@ -0,0 +1,475 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::error::{err_to_string, DataStoreError};
+
+use super::base::{
+    DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities, UploadOutcome,
+};
+use super::format::{check_encoded, compress_once};
+use super::key::{object_key, parse_key};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+
+// Encrypted objects are stored as `BVME1:<key id>:<base64 of nonce and ciphertext>`, so they
+// pass through every driver method that takes or returns a string, and the key needed to
+// decrypt them can be told from the object alone
+const ENVELOPE_PREFIX: &str = "BVME1:";
+const NONCE_SIZE: usize = 12;
+
+// The keys of an `EncryptedStore`: new objects are encrypted with the current key, objects
+// encrypted with any key of the ring can be decrypted. Rotating means adding a new current key,
+// keeping the previous ones until `EncryptedStore::rotate` re-encrypted every object under them.
+#[derive(Clone)]
+pub struct Keyring {
+    current: String,
+    keys: HashMap<String, [u8; 32]>, // By key id
+}
+
+impl Keyring {
+    /// A keyring encrypting with `key`, stored in object headers as `key_id`.
+    pub fn new(key_id: &str, key: [u8; 32]) -> Result<Self, String> {
+        check_key_id(key_id)?;
+        Ok(Self {
+            current: key_id.to_string(),
+            keys: HashMap::from([(key_id.to_string(), key)]),
+        })
+    }
+
+    /// Adds a previous key, only used to decrypt objects encrypted with it.
+    pub fn with_old_key(mut self, key_id: &str, key: [u8; 32]) -> Result<Self, String> {
+        check_key_id(key_id)?;
+        if key_id == self.current {
+            return Err(format!("Key id {key_id:?} is already the current key"));
+        }
+        self.keys.insert(key_id.to_string(), key);
+        Ok(self)
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current
+    }
+
+    fn cipher(&self, key_id: &str) -> Option<ChaCha20Poly1305> {
+        self.keys
+            .get(key_id)
+            .map(|key| ChaCha20Poly1305::new(Key::from_slice(key)))
+    }
+}
+
+fn check_key_id(key_id: &str) -> Result<(), String> {
+    match !key_id.is_empty()
+        && key_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    {
+        true => Ok(()),
+        false => Err(format!(
+            "Invalid key id {key_id:?}, only letters, digits and dashes are allowed"
+        )),
+    }
+}
+
+// The key id of an encrypted object, or None for objects stored without encryption
+fn envelope_key_id(stored: &str) -> Option<&str> {
+    stored
+        .strip_prefix(ENVELOPE_PREFIX)?
+        .split_once(':')
+        .map(|(key_id, _)| key_id)
+}
+
+// Wraps any data store driver and encrypts the contents of every object with ChaCha20-Poly1305
+// before it leaves the process, so the store's operators and anyone reading the bucket only see
+// ciphertext. Keys, sizes and modification times are not hidden, combine it with `EncryptedKeys`
+// to also hide names.
+//
+// Compressed uploads are compressed here with the default level before encryption, as ciphertext
+// doesn't compress, so the inner driver's compression policy doesn't apply to them. Objects
+// stored without encryption, e.g. before the wrapper was introduced, are read as they are.
+// Decryption authenticates the contents, objects modified at rest fail with
+// `DecryptionFailed`, but plaintext objects can't be told apart from ones that were replaced by
+// someone without the key.
+pub struct EncryptedStore<D: DataStoreDriver> {
+    inner: D,
+    keyring: Keyring,
+}
+
+impl<D: DataStoreDriver> EncryptedStore<D> {
+    pub fn new(inner: D, keyring: Keyring) -> Self {
+        Self { inner, keyring }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn seal(&self, contents: &[u8]) -> String {
+        let cipher = self
+            .keyring
+            .cipher(&self.keyring.current)
+            .expect("The current key is always in the keyring");
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: contents,
+            aad: self.keyring.current.as_bytes(),
+        };
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            cipher
+                .encrypt(&nonce, payload)
+                .expect("Encryption only fails for inputs of hundreds of GiB"),
+        );
+
+        format!(
+            "{ENVELOPE_PREFIX}{}:{}",
+            self.keyring.current,
+            BASE64.encode(sealed)
+        )
+    }
+
+    // Decrypts an encrypted object, returning None for objects stored without encryption
+    fn open(&self, key: &str, stored: &str) -> Result<Option<Vec<u8>>, String> {
+        let Some(key_id) = envelope_key_id(stored) else {
+            return Ok(None);
+        };
+        let cipher = self.keyring.cipher(key_id).ok_or_else(|| {
+            DataStoreError::UnknownEncryptionKey {
+                key: key.to_string(),
+                key_id: key_id.to_string(),
+            }
+            .to_string()
+        })?;
+
+        let encoded = &stored[ENVELOPE_PREFIX.len() + key_id.len() + 1..];
+        let failed = || DataStoreError::DecryptionFailed(key.to_string()).to_string();
+        let sealed = BASE64.decode(encoded.trim_end()).map_err(|_| failed())?;
+        if sealed.len() < NONCE_SIZE {
+            return Err(failed());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: key_id.as_bytes(),
+        };
+        cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map(Some)
+            .map_err(|_| failed())
+    }
+
+    fn open_string(&self, key: &str, stored: String) -> Result<String, String> {
+        match self.open(key, &stored)? {
+            Some(contents) => String::from_utf8(contents).map_err(err_to_string),
+            None => Ok(stored),
+        }
+    }
+}
+
+impl<D: DataStoreDriver + Send + Sync> EncryptedStore<D> {
+    /// Re-encrypts every object under `file_path` that is encrypted with an old key of the
+    /// keyring with the current key, returning how many objects were re-encrypted. Objects
+    /// stored without encryption are left as they are. Objects written concurrently may be
+    /// overwritten with their previous contents, so run it while nothing else writes to
+    /// `file_path`. Safe to rerun after a partial failure.
+    pub async fn rotate(&self, file_path: Option<&str>) -> Result<usize, String> {
+        let mut rotated = 0;
+        for key in self.inner.list_objects(file_path).await? {
+            let parsed = parse_key(&key);
+            let directory = parsed.directory();
+            let stored = self
+                .inner
+                .fetch_object(parsed.file_name(), directory.as_deref())
+                .await?;
+            match envelope_key_id(&stored) {
+                Some(key_id) if key_id != self.keyring.current => {}
+                _ => continue,
+            }
+
+            let contents = self.open(&key, &stored)?.unwrap_or_default();
+            self.inner
+                .upload_object(
+                    parsed.file_name(),
+                    &self.seal(&contents),
+                    directory.as_deref(),
+                )
+                .await?;
+            rotated += 1;
+        }
+
+        Ok(rotated)
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for EncryptedStore<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        // Ranges of the ciphertext can't be decrypted on their own
+        self.inner
+            .capabilities()
+            .difference(StoreCapabilities::RANGE_READ)
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let stored = self.inner.fetch_object(file_name, file_path).await?;
+        self.open_string(&object_key(file_name, file_path), stored)
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object(file_name, &self.seal(contents.as_bytes()), file_path)
+            .await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        // Plaintext compressed objects may not be readable as strings, so anything that isn't
+        // an encrypted object is fetched again through the compressed path
+        if let Ok(stored) = self.inner.fetch_object(file_name, file_path).await {
+            if let Some(encoded) = self.open(&object_key(file_name, file_path), &stored)? {
+                return FetchResult::decode(&encoded).map(|fetched| FetchResult {
+                    stored_size: stored.len(),
+                    ..fetched
+                });
+            }
+        }
+
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let encoded = compress_once(contents).map_err(err_to_string)?;
+        self.inner
+            .upload_object(file_name, &self.seal(&encoded), file_path)
+            .await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let (stored, etag) = self.inner.fetch_with_etag(file_name, file_path).await?;
+        let contents = self.open_string(&object_key(file_name, file_path), stored)?;
+        Ok((contents, etag))
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.inner
+            .upload_if_etag_matches(file_name, &self.seal(contents.as_bytes()), file_path, etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_locked(
+                file_name,
+                &self.seal(contents.as_bytes()),
+                file_path,
+                retain_until,
+                mode,
+            )
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        self.inner
+            .upload_object_idempotent(
+                file_name,
+                &self.seal(contents.as_bytes()),
+                file_path,
+                idempotency_key,
+            )
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let stored = self
+            .inner
+            .fetch_generation(file_name, file_path, generation)
+            .await?;
+        self.open_string(&object_key(file_name, file_path), stored)
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_if_absent(file_name, &self.seal(contents.as_bytes()), file_path)
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let encoded = compress_once(contents).map_err(err_to_string)?;
+        self.inner
+            .upload_object_if_absent(file_name, &self.seal(&encoded), file_path)
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        // Encrypted objects are returned as they were before encryption
+        let stored = self.inner.fetch_raw_object(file_name, file_path).await?;
+        let key = object_key(file_name, file_path);
+        match std::str::from_utf8(&stored).map(|stored| self.open(&key, stored)) {
+            Ok(opened) => Ok(opened?.unwrap_or(stored)),
+            Err(_) => Ok(stored),
+        }
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.delete_object(file_name, file_path).await
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        _size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        // The whole object is encrypted at once, so it can't be streamed
+        let mut contents = Vec::new();
+        reader
+            .read_to_end(&mut contents)
+            .await
+            .map_err(err_to_string)?;
+        self.inner
+            .upload_object(file_name, &self.seal(&contents), file_path)
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        check_encoded(compressed).map_err(err_to_string)?;
+        self.inner
+            .upload_object(file_name, &self.seal(compressed), file_path)
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
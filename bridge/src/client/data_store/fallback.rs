@@ -0,0 +1,463 @@
+use std::time::SystemTime;
+
+use futures::future::{join_all, BoxFuture};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// A driver that can be shut down from behind a `Box`, which `DataStoreDriver::shutdown` can't as
+// it takes the driver by value. Implemented for every driver.
+pub trait Tier: DataStoreDriver + Send + Sync {
+    fn shutdown_boxed(self: Box<Self>) -> BoxFuture<'static, Result<(), String>>;
+}
+
+impl<D: DataStoreDriver + Send + Sync + 'static> Tier for D {
+    fn shutdown_boxed(self: Box<Self>) -> BoxFuture<'static, Result<(), String>> {
+        (*self).shutdown()
+    }
+}
+
+// Tiered storage behind a single driver, e.g. a fast local cache, then S3, then a slow archival
+// backend. Reads try each tier in order and move on to the next one when the object is missing,
+// writes, conditional reads and listings only go to the primary tier.
+//
+// So that tiers read before the primary don't keep serving an object's previous version, writes
+// delete it from them once the primary accepted the write, and deletes remove it from every tier.
+// A write whose object can't be deleted from such a tier fails even though the primary has it.
+pub struct FallbackStore {
+    tiers: Vec<Box<dyn Tier>>,
+    primary: usize,
+    // Also try the next tier when a read fails for any other reason than a missing object
+    fall_through_errors: bool,
+    // Copy objects read from a lower tier into every tier above it
+    populate: bool,
+}
+
+impl FallbackStore {
+    /// Reads try `tiers` in the given order, writes go to the first tier unless changed with
+    /// `with_primary`.
+    pub fn new(tiers: Vec<Box<dyn Tier>>) -> Self {
+        assert!(!tiers.is_empty(), "FallbackStore needs at least one tier");
+        Self {
+            tiers,
+            primary: 0,
+            fall_through_errors: false,
+            populate: false,
+        }
+    }
+
+    pub fn with_primary(mut self, primary: usize) -> Self {
+        assert!(primary < self.tiers.len(), "Primary tier out of range");
+        self.primary = primary;
+        self
+    }
+
+    pub fn with_fall_through_errors(mut self, fall_through_errors: bool) -> Self {
+        self.fall_through_errors = fall_through_errors;
+        self
+    }
+
+    pub fn with_populate(mut self, populate: bool) -> Self {
+        self.populate = populate;
+        self
+    }
+
+    pub fn tier(&self, index: usize) -> &(dyn DataStoreDriver + Send + Sync) {
+        self.tiers[index].as_ref()
+    }
+
+    fn primary(&self) -> &(dyn DataStoreDriver + Send + Sync) {
+        self.tier(self.primary)
+    }
+
+    // Drops the object from the tiers read before the primary, once a write to the primary
+    // succeeded
+    async fn written<T>(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        result: Result<T, String>,
+    ) -> Result<T, String> {
+        let value = result?;
+        for tier in &self.tiers[..self.primary] {
+            tier.delete_object(file_name, file_path).await?;
+        }
+        Ok(value)
+    }
+
+    // Whether a failed read from `tier` should be retried on the next tier. Drivers word their
+    // errors differently, so ask the tier whether the failure was caused by the object missing.
+    async fn falls_through(
+        &self,
+        tier: &(dyn DataStoreDriver + Send + Sync),
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> bool {
+        self.fall_through_errors
+            || matches!(tier.object_exists(file_name, file_path).await, Ok(false))
+    }
+}
+
+#[async_trait]
+impl DataStoreDriver for FallbackStore {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.primary().capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.primary().max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.tiers
+            .iter()
+            .fold(StoreStats::default(), |stats, tier| stats + tier.stats())
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.primary().list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let mut last_err = String::new();
+        for (index, tier) in self.tiers.iter().enumerate() {
+            match tier.fetch_object(file_name, file_path).await {
+                Ok(object) => {
+                    if self.populate {
+                        for higher in &self.tiers[..index] {
+                            if let Err(err) =
+                                higher.upload_object(file_name, &object, file_path).await
+                            {
+                                eprintln!("Failed to populate higher tier with {file_name}: {err}");
+                            }
+                        }
+                    }
+                    return Ok(object);
+                }
+                Err(err)
+                    if self
+                        .falls_through(tier.as_ref(), file_name, file_path)
+                        .await =>
+                {
+                    last_err = err
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .primary()
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let mut last_err = String::new();
+        for (index, tier) in self.tiers.iter().enumerate() {
+            match tier.fetch_compressed_object(file_name, file_path).await {
+                Ok(fetched) => {
+                    // A payload that failed to decompress would be stored as a valid object
+                    if self.populate && !fetched.decompress_failed {
+                        for higher in &self.tiers[..index] {
+                            if let Err(err) = higher
+                                .upload_compressed_object(file_name, &fetched.data, file_path)
+                                .await
+                            {
+                                eprintln!("Failed to populate higher tier with {file_name}: {err}");
+                            }
+                        }
+                    }
+                    return Ok(fetched);
+                }
+                Err(err)
+                    if self
+                        .falls_through(tier.as_ref(), file_name, file_path)
+                        .await =>
+                {
+                    last_err = err
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .primary()
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        for tier in &self.tiers {
+            if tier.object_exists(file_name, file_path).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.primary().prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.primary().list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.primary().list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.primary().fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let result = self
+            .primary()
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let mut last_err = String::new();
+        for tier in &self.tiers {
+            match tier.fetch_raw_object(file_name, file_path).await {
+                Ok(object) => return Ok(object),
+                Err(err)
+                    if self
+                        .falls_through(tier.as_ref(), file_name, file_path)
+                        .await =>
+                {
+                    last_err = err
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        let mut last_err = String::new();
+        for tier in &self.tiers {
+            match tier.fetch_range(file_name, file_path, range).await {
+                Ok(object) => return Ok(object),
+                Err(err)
+                    if self
+                        .falls_through(tier.as_ref(), file_name, file_path)
+                        .await =>
+                {
+                    last_err = err
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let result = self
+            .primary()
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let result = self
+            .primary()
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let mut last_err = String::new();
+        for tier in &self.tiers {
+            match tier
+                .fetch_generation(file_name, file_path, generation)
+                .await
+            {
+                Ok(object) => return Ok(object),
+                Err(err)
+                    if self
+                        .falls_through(tier.as_ref(), file_name, file_path)
+                        .await =>
+                {
+                    last_err = err
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .primary()
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .primary()
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.primary().touch(file_name, file_path).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let result = self
+            .primary()
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await;
+        self.written(target_file_name, target_file_path, result)
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        for tier in &self.tiers {
+            tier.delete_object(file_name, file_path).await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .primary()
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .primary()
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await;
+        self.written(file_name, file_path, result).await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        let results = join_all(self.tiers.into_iter().map(Tier::shutdown_boxed)).await;
+        results.into_iter().collect()
+    }
+}
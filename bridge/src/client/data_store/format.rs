@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Error, ErrorKind, Seek, Write},
+};
+
+use crate::{
+    error::DataStoreError,
+    utils::{
+        compress, compress_multithreaded, compress_with_dictionary, decompress, decompress_to,
+        decompress_with_dictionary, dictionary_id, frame_dictionary_id, DEFAULT_COMPRESSION_LEVEL,
+        ZSTD_AVAILABLE,
+    },
+};
+
+// Objects written through the compressed path are self-describing: zstd output starts with the
+// zstd frame magic number, while payloads that were not worth compressing are prefixed with
+// STORED_MAGIC and kept as is. Objects written before the stored marker existed are plain zstd
+// frames, so they are still read correctly.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const STORED_MAGIC: [u8; 4] = *b"BVMS";
+
+// Entropy is estimated from up to ENTROPY_SAMPLE_CHUNKS evenly spaced chunks of the input
+const ENTROPY_SAMPLE_CHUNKS: usize = 16;
+const ENTROPY_SAMPLE_CHUNK_SIZE: usize = 4 * 1024;
+// Inputs smaller than this always get compressed, their entropy estimate is unreliable
+const ENTROPY_MIN_INPUT_SIZE: usize = 1024;
+// Bits per byte above which the input is treated as already compressed or random
+const INCOMPRESSIBLE_ENTROPY: f64 = 7.8;
+// Compressed uploads smaller than this are stored as is, as the zstd frame overhead would make
+// them larger
+pub const DEFAULT_MIN_COMPRESS_SIZE: usize = 256;
+// Upper bound on the size of trained dictionaries, zstd's own default
+const MAX_DICTIONARY_SIZE: usize = 110 * 1024;
+
+pub fn encode_object(contents: &[u8], level: i32) -> std::io::Result<Vec<u8>> {
+    if is_incompressible(contents) {
+        return Ok(store(contents));
+    }
+
+    compress(contents, level)
+}
+
+/// Like `encode_object`, but stores inputs shorter than `min_compress_size` uncompressed.
+pub fn encode_object_with_min_size(
+    contents: &[u8],
+    level: i32,
+    min_compress_size: usize,
+) -> std::io::Result<Vec<u8>> {
+    if contents.len() < min_compress_size {
+        return Ok(store(contents));
+    }
+
+    encode_object(contents, level)
+}
+
+/// Like `encode_object_with_min_size`, but compresses with `threads` worker threads, see
+/// `compress_multithreaded`.
+pub fn encode_object_multithreaded(
+    contents: &[u8],
+    level: i32,
+    min_compress_size: usize,
+    threads: u32,
+) -> std::io::Result<Vec<u8>> {
+    if contents.len() < min_compress_size || is_incompressible(contents) {
+        return Ok(store(contents));
+    }
+
+    compress_multithreaded(contents, level, threads)
+}
+
+/// Wraps `contents` in the object format without compressing them.
+pub fn encode_object_uncompressed(contents: &[u8]) -> Vec<u8> {
+    store(contents)
+}
+
+/// Encodes `contents` once so the result can be handed to `upload_precompressed_object` of
+/// several stores, instead of every store compressing the same contents again.
+pub fn compress_once(contents: &[u8]) -> std::io::Result<Vec<u8>> {
+    encode_object(contents, DEFAULT_COMPRESSION_LEVEL)
+}
+
+/// Whether `data` starts with an object format header, i.e. was most likely produced by
+/// `encode_object` or `compress`.
+pub fn is_encoded(data: &[u8]) -> bool {
+    data.starts_with(&STORED_MAGIC) || data.starts_with(&ZSTD_MAGIC)
+}
+
+/// The HTTP `Content-Encoding` under which clients can decode `data` to its contents without
+/// knowing the object format, i.e. `zstd` for zstd frames compressed without a dictionary. Stored
+/// payloads and frames that need a dictionary have none.
+pub fn content_encoding(data: &[u8]) -> Option<&'static str> {
+    match data.starts_with(&ZSTD_MAGIC) && frame_dictionary_id(data).is_none() {
+        true => Some("zstd"),
+        false => None,
+    }
+}
+
+/// Rejects data that was not produced by `encode_object`, so it can't be stored as if it was.
+pub fn check_encoded(data: &[u8]) -> std::io::Result<()> {
+    match is_encoded(data) {
+        true => Ok(()),
+        false => Err(Error::new(
+            ErrorKind::InvalidData,
+            "Precompressed payload has no object format header",
+        )),
+    }
+}
+
+/// Fails with `DataStoreError::CompressionUnavailable` if `data` was compressed with a codec this
+/// build was compiled without, so callers can tell that apart from corrupt data. Stored and
+/// unrecognized data always passes.
+pub fn check_codec_available(data: &[u8]) -> std::io::Result<()> {
+    match data.starts_with(&ZSTD_MAGIC) && !ZSTD_AVAILABLE {
+        true => Err(Error::new(
+            ErrorKind::Unsupported,
+            DataStoreError::CompressionUnavailable("zstd").to_string(),
+        )),
+        false => Ok(()),
+    }
+}
+
+pub fn decode_object(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    decode_object_with_dictionaries(data, &Dictionaries::default())
+}
+
+/// Decodes as much of `data` as possible, returning the contents up to the first corrupt part of
+/// the payload. Data in an unknown format decodes to nothing.
+pub fn decode_object_lossy(data: &[u8]) -> Vec<u8> {
+    if let Some(payload) = data.strip_prefix(&STORED_MAGIC) {
+        return payload.to_vec();
+    }
+
+    let mut decoded = vec![];
+    if data.starts_with(&ZSTD_MAGIC) {
+        // zstd writes out every block as it is decoded, so this keeps what came before the error
+        let _ = decompress_to(data, &mut decoded);
+    }
+    decoded
+}
+
+/// Trains a zstd dictionary on sample objects of one artifact type, for use with
+/// `encode_object_with_dictionary`. Needs a few dozen samples at least to be effective.
+pub fn train_dictionary(samples: &[Vec<u8>]) -> std::io::Result<Vec<u8>> {
+    crate::utils::train_dictionary(samples, MAX_DICTIONARY_SIZE)
+}
+
+/// Like `encode_object`, but compresses with `dictionary`. zstd records the dictionary id in the
+/// frame header, so `decode_object_with_dictionaries` finds the right dictionary on its own.
+pub fn encode_object_with_dictionary(
+    contents: &[u8],
+    level: i32,
+    dictionary: &[u8],
+) -> std::io::Result<Vec<u8>> {
+    if is_incompressible(contents) {
+        return Ok(store(contents));
+    }
+
+    compress_with_dictionary(contents, level, dictionary)
+}
+
+/// Trained dictionaries by id, for decoding objects compressed with any of them.
+#[derive(Clone, Debug, Default)]
+pub struct Dictionaries {
+    dictionaries: HashMap<u32, Vec<u8>>,
+}
+
+impl Dictionaries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a dictionary produced by `train_dictionary`, returning its id.
+    pub fn insert(&mut self, dictionary: Vec<u8>) -> std::io::Result<u32> {
+        let id = dictionary_id(&dictionary)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Dictionary has no id"))?;
+        self.dictionaries.insert(id, dictionary);
+        Ok(id)
+    }
+}
+
+pub fn decode_object_with_dictionaries(
+    data: &[u8],
+    dictionaries: &Dictionaries,
+) -> std::io::Result<Vec<u8>> {
+    if let Some(payload) = data.strip_prefix(&STORED_MAGIC) {
+        return Ok(payload.to_vec());
+    }
+    if data.starts_with(&ZSTD_MAGIC) {
+        check_codec_available(data)?;
+        return match frame_dictionary_id(data) {
+            None => decompress(data),
+            Some(id) => match dictionaries.dictionaries.get(&id) {
+                Some(dictionary) => decompress_with_dictionary(data, dictionary),
+                None => Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Object was compressed with unknown dictionary {id}"),
+                )),
+            },
+        };
+    }
+
+    Err(Error::new(ErrorKind::InvalidData, "Unknown object format"))
+}
+
+/// Decoded object contents, either held in memory or spilled to an anonymous temporary file
+/// (positioned at its start) because they were larger than the spill threshold.
+pub enum DecompressedOutput {
+    Bytes(Vec<u8>),
+    File(File),
+}
+
+/// Like `decode_object`, but streams the decoded contents to a temporary file once they grow
+/// beyond `spill_threshold` bytes instead of holding them all in memory.
+pub fn decode_object_spilling(
+    data: &[u8],
+    spill_threshold: usize,
+) -> std::io::Result<DecompressedOutput> {
+    let mut sink = SpillWriter::new(spill_threshold);
+    if let Some(payload) = data.strip_prefix(&STORED_MAGIC) {
+        sink.write_all(payload)?;
+    } else if data.starts_with(&ZSTD_MAGIC) {
+        check_codec_available(data)?;
+        decompress_to(data, &mut sink)?;
+    } else {
+        return Err(Error::new(ErrorKind::InvalidData, "Unknown object format"));
+    }
+
+    sink.finish()
+}
+
+// Buffers writes in memory until they exceed `threshold`, then moves them to a temporary file
+struct SpillWriter {
+    threshold: usize,
+    buffer: Vec<u8>,
+    file: Option<File>,
+}
+
+impl SpillWriter {
+    fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            buffer: vec![],
+            file: None,
+        }
+    }
+
+    fn finish(self) -> std::io::Result<DecompressedOutput> {
+        match self.file {
+            Some(mut file) => {
+                file.flush()?;
+                file.rewind()?;
+                Ok(DecompressedOutput::File(file))
+            }
+            None => Ok(DecompressedOutput::Bytes(self.buffer)),
+        }
+    }
+}
+
+impl Write for SpillWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.file.is_none() && self.buffer.len() + buf.len() > self.threshold {
+            let mut file = tempfile::tempfile()?;
+            file.write_all(&self.buffer)?;
+            self.buffer = vec![];
+            self.file = Some(file);
+        }
+
+        match self.file.as_mut() {
+            Some(file) => file.write(buf),
+            None => self.buffer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn store(contents: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(STORED_MAGIC.len() + contents.len());
+    data.extend_from_slice(&STORED_MAGIC);
+    data.extend_from_slice(contents);
+    data
+}
+
+fn is_incompressible(data: &[u8]) -> bool {
+    if data.len() < ENTROPY_MIN_INPUT_SIZE {
+        return false;
+    }
+
+    let mut histogram = [0usize; 256];
+    let mut sample_size = 0;
+    let stride = (data.len() / ENTROPY_SAMPLE_CHUNKS).max(ENTROPY_SAMPLE_CHUNK_SIZE);
+    for start in (0..data.len()).step_by(stride) {
+        let end = (start + ENTROPY_SAMPLE_CHUNK_SIZE).min(data.len());
+        for byte in &data[start..end] {
+            histogram[*byte as usize] += 1;
+        }
+        sample_size += end - start;
+    }
+
+    let entropy: f64 = histogram
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / sample_size as f64;
+            -p * p.log2()
+        })
+        .sum();
+
+    entropy >= INCOMPRESSIBLE_ENTROPY
+}
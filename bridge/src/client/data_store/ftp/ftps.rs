@@ -1,5 +1,5 @@
 use super::{
-    super::base::DataStoreDriver,
+    super::base::{DataStoreDriver, DriverConfig, FetchResult, StoreCapabilities},
     lib::{self, FtpCredentials},
 };
 use async_trait::async_trait;
@@ -16,6 +16,7 @@ use dotenv;
 
 pub struct Ftps {
     credentials: lib::FtpCredentials,
+    config: DriverConfig,
 }
 
 impl Ftps {
@@ -46,19 +47,35 @@ impl Ftps {
         };
 
         match lib::test_connection(&credentials).await {
-            Ok(_) => Some(Self { credentials }),
+            Ok(_) => Some(Self {
+                credentials,
+                config: DriverConfig::from_env(),
+            }),
             Err(err) => {
                 eprintln!("{err:?}");
                 None
             }
         }
     }
+
+    /// Replaces the whole `DriverConfig` read from the environment.
+    pub fn with_config(mut self, config: DriverConfig) -> Self {
+        self.config = config;
+        self
+    }
 }
 
 #[async_trait]
 impl DataStoreDriver for Ftps {
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities::RAW_READ
+    }
+
     async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
-        lib::list_objects(&self.credentials, file_path).await
+        let mut keys = lib::list_objects(&self.credentials, file_path).await?;
+        self.config.cap_keys(&mut keys, file_path)?;
+
+        Ok(keys)
     }
 
     async fn fetch_object(
@@ -75,15 +92,22 @@ impl DataStoreDriver for Ftps {
         contents: &str,
         file_path: Option<&str>,
     ) -> Result<usize, String> {
-        lib::upload_object(&self.credentials, file_name, contents, file_path).await
+        lib::upload_object(
+            &self.credentials,
+            &self.config,
+            file_name,
+            contents,
+            file_path,
+        )
+        .await
     }
 
     async fn fetch_compressed_object(
         &self,
         file_name: &str,
         file_path: Option<&str>,
-    ) -> Result<(Vec<u8>, usize), String> {
-        lib::fetch_compressed_object(&self.credentials, file_name, file_path).await
+    ) -> Result<FetchResult, String> {
+        lib::fetch_compressed_object(&self.credentials, &self.config, file_name, file_path).await
     }
 
     async fn upload_compressed_object(
@@ -92,6 +116,37 @@ impl DataStoreDriver for Ftps {
         contents: &Vec<u8>,
         file_path: Option<&str>,
     ) -> Result<usize, String> {
-        lib::upload_compressed_object(&self.credentials, file_name, contents, file_path).await
+        lib::upload_compressed_object(
+            &self.credentials,
+            &self.config,
+            file_name,
+            contents,
+            file_path,
+        )
+        .await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        lib::fetch_raw_object(&self.credentials, file_name, file_path).await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        lib::upload_precompressed_object(
+            &self.credentials,
+            &self.config,
+            file_name,
+            compressed,
+            file_path,
+        )
+        .await
     }
 }
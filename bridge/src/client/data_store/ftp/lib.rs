@@ -4,10 +4,12 @@ use suppaftp::{
     AsyncNativeTlsFtpStream,
 };
 
-use crate::{
-    error::err_to_string,
-    utils::{compress, decompress, DEFAULT_COMPRESSION_LEVEL},
+use super::super::{
+    base::{DriverConfig, FetchResult},
+    format::check_encoded,
+    key::object_key,
 };
+use crate::error::err_to_string;
 
 pub struct FtpCredentials {
     pub is_secure: bool,
@@ -93,11 +95,13 @@ pub async fn fetch_object(
 
 pub async fn upload_object(
     credentials: &FtpCredentials,
+    config: &DriverConfig,
     file_name: &str,
     contents: &str,
     file_path: Option<&str>,
 ) -> Result<usize, String> {
     let size = contents.len();
+    config.check_upload(size)?;
 
     println!("Writing data file to {} (size: {})", file_name, size);
 
@@ -109,14 +113,18 @@ pub async fn upload_object(
 
 pub async fn fetch_compressed_object(
     credentials: &FtpCredentials,
+    config: &DriverConfig,
     file_name: &str,
     file_path: Option<&str>,
-) -> Result<(Vec<u8>, usize), String> {
+) -> Result<FetchResult, String> {
     let response = get_object(credentials, file_name, file_path).await;
     match response {
         Ok(buffer) => {
-            let size = buffer.len();
-            Ok((decompress(&buffer).map_err(err_to_string)?, size))
+            config
+                .decode_fetched_or_refetch(&object_key(file_name, file_path), &buffer, || {
+                    get_object(credentials, file_name, file_path)
+                })
+                .await
         }
         Err(err) => Err(format!("Failed to get json file: {}", err)),
     }
@@ -124,12 +132,14 @@ pub async fn fetch_compressed_object(
 
 pub async fn upload_compressed_object(
     credentials: &FtpCredentials,
+    config: &DriverConfig,
     file_name: &str,
     contents: &Vec<u8>,
     file_path: Option<&str>,
 ) -> Result<usize, String> {
-    let compressed_data = compress(contents, DEFAULT_COMPRESSION_LEVEL).map_err(err_to_string)?;
+    let compressed_data = config.encode_upload(contents, file_name, file_path)?;
     let size = compressed_data.len();
+    config.check_upload(size)?;
 
     println!("Writing data file to {} (size: {})", file_name, size);
 
@@ -146,6 +156,33 @@ pub async fn upload_compressed_object(
     }
 }
 
+pub async fn upload_precompressed_object(
+    credentials: &FtpCredentials,
+    config: &DriverConfig,
+    file_name: &str,
+    compressed: &[u8],
+    file_path: Option<&str>,
+) -> Result<usize, String> {
+    check_encoded(compressed).map_err(err_to_string)?;
+    let size = compressed.len();
+    config.check_upload(size)?;
+
+    println!("Writing data file to {} (size: {})", file_name, size);
+
+    match upload_file(credentials, file_name, compressed, file_path).await {
+        Ok(_) => Ok(size),
+        Err(err) => Err(format!("Failed to save json file: {}", err)),
+    }
+}
+
+pub async fn fetch_raw_object(
+    credentials: &FtpCredentials,
+    file_name: &str,
+    file_path: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    get_object(credentials, file_name, file_path).await
+}
+
 async fn get_object(
     credentials: &FtpCredentials,
     file_name: &str,
@@ -0,0 +1,172 @@
+use std::{
+    io::{Error, ErrorKind},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use sha2::{Digest, Sha256, Sha512};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::error::DataStoreError;
+
+// Hash function used to name content addressed objects and to verify checksums. Checksums are
+// written as `<algorithm>-<hex digest>`, except for SHA-256 ones which are plain hex digests, so
+// readers always know which function to verify them with and checksums written before other
+// algorithms existed stay valid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(HashAlgorithm::Sha256),
+            "sha512" | "sha-512" => Ok(HashAlgorithm::Sha512),
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            _ => Err(format!("Unknown hash algorithm {name:?}")),
+        }
+    }
+
+    /// The lowercase hex digest of `data`.
+    pub fn digest_hex(&self, data: &[u8]) -> String {
+        let mut hasher = Hasher::new(*self);
+        hasher.update(data);
+        hasher.finalize_hex()
+    }
+
+    /// The checksum of `data` tagged with this algorithm, see `split_checksum`.
+    pub fn checksum(&self, data: &[u8]) -> String {
+        match self {
+            HashAlgorithm::Sha256 => self.digest_hex(data),
+            _ => format!("{}-{}", self.name(), self.digest_hex(data)),
+        }
+    }
+}
+
+/// Splits a checksum written by `HashAlgorithm::checksum` into its algorithm and hex digest.
+/// Untagged checksums are SHA-256 digests.
+pub fn split_checksum(checksum: &str) -> Result<(HashAlgorithm, &str), String> {
+    match checksum.split_once('-') {
+        Some((name, digest)) => Ok((HashAlgorithm::parse(name)?, digest)),
+        None => Ok((HashAlgorithm::Sha256, checksum)),
+    }
+}
+
+// Running hash of any `HashAlgorithm`
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        let digest = match self {
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        };
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+// Verifies a downloaded object against its checksum while it is being read, instead of in a
+// second pass over the whole object. Every chunk read is fed into a running hash, and the read
+// that reaches the end of the stream fails with `IntegrityError` if the hash doesn't match.
+// Callers must therefore read to the end before trusting any of the data.
+pub struct VerifyingReader<R> {
+    inner: R,
+    hasher: Option<Hasher>, // Taken once verified
+    expected: String,       // Lowercase hex
+    key: String,
+}
+
+impl<R: AsyncRead + Unpin> VerifyingReader<R> {
+    /// Wraps the stream of object `key`, whose SHA-256 checksum is the hex string `expected`.
+    pub fn new(inner: R, expected: &str, key: &str) -> Self {
+        Self::with_algorithm(inner, HashAlgorithm::Sha256, expected, key)
+    }
+
+    /// Wraps the stream of object `key`, whose `algorithm` digest is the hex string `expected`.
+    pub fn with_algorithm(inner: R, algorithm: HashAlgorithm, expected: &str, key: &str) -> Self {
+        Self {
+            inner,
+            hasher: Some(Hasher::new(algorithm)),
+            expected: expected.to_ascii_lowercase(),
+            key: key.to_string(),
+        }
+    }
+
+    /// Wraps the stream of object `key`, whose checksum was written by `HashAlgorithm::checksum`.
+    pub fn with_checksum(inner: R, checksum: &str, key: &str) -> Result<Self, String> {
+        let (algorithm, expected) = split_checksum(checksum)?;
+        Ok(Self::with_algorithm(inner, algorithm, expected, key))
+    }
+
+    fn verify(&mut self, hasher: Hasher) -> std::io::Result<()> {
+        match hasher.finalize_hex() == self.expected {
+            true => Ok(()),
+            false => Err(Error::new(
+                ErrorKind::InvalidData,
+                DataStoreError::IntegrityError(self.key.clone()).to_string(),
+            )),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for VerifyingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        // An empty read only means the end of the stream if there was room to read into
+        if buf.remaining() == 0 {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        let read = &buf.filled()[filled..];
+        if !read.is_empty() {
+            if let Some(hasher) = &mut this.hasher {
+                hasher.update(read);
+            }
+        } else if let Some(hasher) = this.hasher.take() {
+            this.verify(hasher)?;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
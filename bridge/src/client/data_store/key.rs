@@ -0,0 +1,130 @@
+// Helpers for building object keys from a file path and file name, and for splitting
+// listed keys back into their components.
+
+use crate::error::DataStoreError;
+
+/// Normalizes `file_path` so that equivalent spellings produce the same keys: leading, trailing
+/// and repeated slashes are dropped, and an empty path is the same as no path.
+pub fn normalize_path(file_path: Option<&str>) -> Option<String> {
+    let segments: Vec<&str> = file_path?
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+pub fn object_key(file_name: &str, file_path: Option<&str>) -> String {
+    match normalize_path(file_path) {
+        Some(path) => format!("{path}/{file_name}"),
+        None => file_name.to_string(),
+    }
+}
+
+/// Fails with `KeyTooLong` if the key of `file_name` under `file_path` is longer than
+/// `max_key_length` bytes, see `DataStoreDriver::max_key_length`.
+pub fn check_key_length(
+    file_name: &str,
+    file_path: Option<&str>,
+    max_key_length: Option<usize>,
+) -> Result<(), String> {
+    let len = object_key(file_name, file_path).len();
+    match max_key_length {
+        Some(max) if len > max => Err(DataStoreError::KeyTooLong { len, max }.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// The prefix shared by every key under `file_path`, used when listing.
+pub fn list_prefix(file_path: Option<&str>) -> String {
+    match normalize_path(file_path) {
+        Some(path) => format!("{path}/"),
+        None => String::new(),
+    }
+}
+
+/// Top-level directory holding every graph's artifacts, laid out as
+/// `graphs/<graph id>/<artifact kind>/<file name>`.
+pub const GRAPHS_DIRECTORY: &str = "graphs";
+
+/// The file path under which every artifact of `graph_id` is stored.
+pub fn graph_path(graph_id: &str) -> String {
+    format!("{GRAPHS_DIRECTORY}/{graph_id}")
+}
+
+/// An object key split into its directory segments and file name, e.g.
+/// `graphs/abc123/peg_out/attempt_4.json` has the segments `["graphs", "abc123", "peg_out"]`
+/// and the file name `attempt_4.json`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedKey {
+    key: String,
+    segments: Vec<String>,
+    file_name: String,
+}
+
+impl ParsedKey {
+    /// The full key this was parsed from.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The directory segments in order, excluding the file name.
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// The directory segment at `index`, if there is one.
+    pub fn segment(&self, index: usize) -> Option<&str> {
+        self.segments.get(index).map(String::as_str)
+    }
+
+    /// The directory segments joined back together, i.e. the `file_path` of the object.
+    pub fn directory(&self) -> Option<String> {
+        if self.segments.is_empty() {
+            None
+        } else {
+            Some(self.segments.join("/"))
+        }
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// The artifact kind of a graph artifact key, e.g. `peg_out`, or `None` if the key does not
+    /// follow the graph storage layout.
+    pub fn artifact_kind(&self) -> Option<&str> {
+        match self.segment(0) {
+            Some(GRAPHS_DIRECTORY) => self.segment(2),
+            _ => None,
+        }
+    }
+
+    /// The file name without its extension, e.g. `attempt_4`.
+    pub fn stem(&self) -> &str {
+        self.file_name
+            .rsplit_once('.')
+            .map_or(self.file_name.as_str(), |(stem, _)| stem)
+    }
+
+    /// The file name extension without the dot, e.g. `json`.
+    pub fn extension(&self) -> Option<&str> {
+        self.file_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension)
+    }
+}
+
+pub fn parse_key(key: &str) -> ParsedKey {
+    let mut segments: Vec<String> = key
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(String::from)
+        .collect();
+    let file_name = segments.pop().unwrap_or_default();
+
+    ParsedKey {
+        key: key.to_string(),
+        segments,
+        file_name,
+    }
+}
@@ -0,0 +1,512 @@
+use std::{sync::Arc, time::SystemTime};
+
+use aws_smithy_types::date_time::{DateTime, Format};
+use md5::{Digest, Md5};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RecentObjects, RetentionMode,
+    StoreCapabilities, UploadOutcome,
+};
+use super::clock::{Clock, SystemClock};
+use super::key::{object_key, parse_key};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// Maps the logical `(file_path, file_name)` the bridge uses for an object to the file path it is
+// physically stored under. Layouts only ever change the file path, never the file name.
+pub trait KeyLayout: Send + Sync {
+    /// File path a new object named `file_name` under `file_path` is written to.
+    fn physical_path(&self, file_name: &str, file_path: Option<&str>) -> Option<String>;
+
+    /// Every physical file path that has to be listed to see all objects under `file_path`.
+    fn list_paths(&self, file_path: Option<&str>) -> Vec<Option<String>>;
+
+    /// Maps a listed physical key back to its logical key, or None if the key wasn't laid out
+    /// by this layout.
+    fn logical_key(&self, physical_key: &str) -> Option<String>;
+
+    /// Whether `physical_path` always returns the same path for the same object. If not, reads
+    /// have to search the listing of `list_paths` for the object.
+    fn is_stable(&self) -> bool {
+        true
+    }
+}
+
+// Stores objects under their logical key as is
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Flat;
+
+impl KeyLayout for Flat {
+    fn physical_path(&self, _file_name: &str, file_path: Option<&str>) -> Option<String> {
+        file_path.map(str::to_string)
+    }
+
+    fn list_paths(&self, file_path: Option<&str>) -> Vec<Option<String>> {
+        vec![file_path.map(str::to_string)]
+    }
+
+    fn logical_key(&self, physical_key: &str) -> Option<String> {
+        Some(physical_key.to_string())
+    }
+}
+
+// Stores objects under a `YYYY/MM/DD` directory of the day they were written, e.g.
+// `graphs/abc123.json` written on 2 January 2025 is stored as `graphs/2025/01/02/abc123.json`.
+// The day isn't known when reading, so reads list the file path to find the object. An object
+// rewritten on a later day is stored again under the new day: reads return the newest copy and
+// deletes remove all of them.
+#[derive(Debug)]
+pub struct DatePartitioned {
+    clock: Arc<dyn Clock>,
+}
+
+impl DatePartitioned {
+    pub fn new() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl Default for DatePartitioned {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyLayout for DatePartitioned {
+    fn physical_path(&self, _file_name: &str, file_path: Option<&str>) -> Option<String> {
+        // Formats as e.g. `2025-01-02T03:04:05Z`, of which only the date is kept
+        let date = DateTime::from(self.clock.now())
+            .fmt(Format::DateTime)
+            .expect("Current time is representable as a date");
+        Some(object_key(&date[..10].replace('-', "/"), file_path))
+    }
+
+    fn list_paths(&self, file_path: Option<&str>) -> Vec<Option<String>> {
+        vec![file_path.map(str::to_string)]
+    }
+
+    fn logical_key(&self, physical_key: &str) -> Option<String> {
+        let key = parse_key(physical_key);
+        let segments = key.segments();
+        let (path, date) = segments.split_at(segments.len().checked_sub(3)?);
+        let is_date = date.iter().zip([4, 2, 2]).all(|(segment, len)| {
+            segment.len() == len && segment.bytes().all(|b| b.is_ascii_digit())
+        });
+        is_date.then(|| object_key(key.file_name(), Some(&path.join("/"))))
+    }
+
+    fn is_stable(&self) -> bool {
+        false
+    }
+}
+
+// Spreads the objects of every file path over `shards` sub-prefixes picked by a hash of the file
+// name, to avoid S3 throttling hot prefixes. Uses the same layout as `SpreadPrefixes`, see there.
+#[derive(Clone, Copy, Debug)]
+pub struct HashPrefixed {
+    shards: u16,
+}
+
+impl HashPrefixed {
+    /// Spreads objects over `shards` sub-prefixes, clamped to between 1 and 256.
+    pub fn new(shards: u16) -> Self {
+        Self {
+            shards: shards.clamp(1, 256),
+        }
+    }
+}
+
+impl KeyLayout for HashPrefixed {
+    fn physical_path(&self, file_name: &str, file_path: Option<&str>) -> Option<String> {
+        let digest = Md5::digest(file_name.as_bytes());
+        let shard = u16::from_be_bytes([digest[0], digest[1]]) % self.shards;
+        Some(object_key(&format!("{shard:02x}"), file_path))
+    }
+
+    fn list_paths(&self, file_path: Option<&str>) -> Vec<Option<String>> {
+        (0..self.shards)
+            .map(|shard| Some(object_key(&format!("{shard:02x}"), file_path)))
+            .collect()
+    }
+
+    fn logical_key(&self, physical_key: &str) -> Option<String> {
+        let key = parse_key(physical_key);
+        let (shard, path) = key.segments().split_last()?;
+        let is_shard = shard.len() == 2 && shard.bytes().all(|b| b.is_ascii_hexdigit());
+        is_shard.then(|| object_key(key.file_name(), Some(&path.join("/"))))
+    }
+}
+
+// Stores objects of the wrapped driver according to a `KeyLayout`, while callers keep using
+// logical file paths and names. Listings return logical keys and skip objects the layout didn't
+// produce, e.g. ones written before the layout was introduced.
+pub struct KeyLayoutStore<D: DataStoreDriver, L: KeyLayout> {
+    inner: D,
+    layout: L,
+}
+
+impl<D: DataStoreDriver, L: KeyLayout> KeyLayoutStore<D, L> {
+    pub fn new(inner: D, layout: L) -> Self {
+        Self { inner, layout }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: DataStoreDriver + Send + Sync, L: KeyLayout> KeyLayoutStore<D, L> {
+    // Physical paths of every stored copy of an object, oldest first for date partitions
+    async fn locate(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<Option<String>>, String> {
+        let key = object_key(file_name, file_path);
+        let mut paths = vec![];
+        for list_path in self.layout.list_paths(file_path) {
+            for physical_key in self.inner.list_objects(list_path.as_deref()).await? {
+                if self.layout.logical_key(&physical_key).as_deref() == Some(key.as_str()) {
+                    paths.push(parse_key(&physical_key).directory());
+                }
+            }
+        }
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    // Physical path to read an object from. Missing objects of unstable layouts resolve to the
+    // path they would be written to, so the inner driver reports them as missing.
+    async fn read_path(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        if !self.layout.is_stable() {
+            if let Some(path) = self.locate(file_name, file_path).await?.pop() {
+                return Ok(path);
+            }
+        }
+
+        Ok(self.layout.physical_path(file_name, file_path))
+    }
+
+    fn logical_keys(&self, physical_keys: Vec<String>) -> Vec<String> {
+        physical_keys
+            .iter()
+            .filter_map(|key| self.layout.logical_key(key))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync, L: KeyLayout> DataStoreDriver for KeyLayoutStore<D, L> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let mut keys = vec![];
+        for list_path in self.layout.list_paths(file_path) {
+            let physical_keys = self.inner.list_objects(list_path.as_deref()).await?;
+            keys.extend(self.logical_keys(physical_keys));
+        }
+
+        Ok(keys)
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner.fetch_object(file_name, path.as_deref()).await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_object(file_name, contents, path.as_deref())
+            .await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner
+            .fetch_compressed_object(file_name, path.as_deref())
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_compressed_object(file_name, contents, path.as_deref())
+            .await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner.object_exists(file_name, path.as_deref()).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        let mut size = 0;
+        for list_path in self.layout.list_paths(file_path) {
+            size += self.inner.prefix_size(list_path.as_deref()).await?;
+        }
+
+        Ok(size)
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let mut objects = vec![];
+        for list_path in self.layout.list_paths(file_path) {
+            let listing = self
+                .inner
+                .list_object_metadata(list_path.as_deref())
+                .await?;
+            objects.extend(listing.into_iter().filter_map(|object| {
+                let key = self.layout.logical_key(&object.key)?;
+                Some(ObjectMetadata { key, ..object })
+            }));
+        }
+
+        Ok(objects)
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        let mut recent = RecentObjects::new(n);
+        for list_path in self.layout.list_paths(file_path) {
+            for (key, last_modified) in self.inner.list_recent(list_path.as_deref(), n).await? {
+                if let Some(key) = self.layout.logical_key(&key) {
+                    recent.push(key, last_modified);
+                }
+            }
+        }
+
+        Ok(recent.into_sorted())
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner.fetch_with_etag(file_name, path.as_deref()).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        // The etag belongs to the copy that was read, so overwrite that one
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner
+            .upload_if_etag_matches(file_name, contents, path.as_deref(), etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_object_locked(file_name, contents, path.as_deref(), retain_until, mode)
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_object_idempotent(file_name, contents, path.as_deref(), idempotency_key)
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner
+            .fetch_generation(file_name, path.as_deref(), generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_object_if_absent(file_name, contents, path.as_deref())
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_compressed_object_if_absent(file_name, contents, path.as_deref())
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner.touch(file_name, path.as_deref()).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner
+            .fetch_raw_object(file_name, path.as_deref())
+            .await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        let path = self.read_path(file_name, file_path).await?;
+        self.inner
+            .fetch_range(file_name, path.as_deref(), range)
+            .await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let path = self.read_path(file_name, file_path).await?;
+        let target_path = self
+            .layout
+            .physical_path(target_file_name, target_file_path);
+        self.inner
+            .copy_object(
+                file_name,
+                path.as_deref(),
+                target_file_name,
+                target_path.as_deref(),
+            )
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        if self.layout.is_stable() {
+            let path = self.layout.physical_path(file_name, file_path);
+            return self.inner.delete_object(file_name, path.as_deref()).await;
+        }
+
+        for path in self.locate(file_name, file_path).await? {
+            self.inner.delete_object(file_name, path.as_deref()).await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_object_from_reader(file_name, reader, size_hint, path.as_deref())
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let path = self.layout.physical_path(file_name, file_path);
+        self.inner
+            .upload_precompressed_object(file_name, compressed, path.as_deref())
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
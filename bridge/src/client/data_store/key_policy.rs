@@ -0,0 +1,357 @@
+use std::time::SystemTime;
+
+use crate::error::DataStoreError;
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::key::{list_prefix, object_key};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// Key prefixes a data store may access. A key is allowed if it starts with one of the `allow`
+// prefixes, or with any prefix if there are none, and doesn't start with one of the `deny`
+// prefixes. Prefixes are matched as plain strings, so `bridge_data/` must end with a slash to not
+// also allow `bridge_data_other/`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl KeyPolicy {
+    /// Parses a comma separated list of `allow:<prefix>` and `deny:<prefix>` rules, e.g.
+    /// `allow:bridge_data/,deny:bridge_data/keys/`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut policy = Self::default();
+        for rule in spec.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            match rule.split_once(':') {
+                Some(("allow", prefix)) => policy.allow.push(prefix.trim().to_string()),
+                Some(("deny", prefix)) => policy.deny.push(prefix.trim().to_string()),
+                _ => return Err(format!("Invalid key policy rule {rule:?}")),
+            }
+        }
+
+        Ok(policy)
+    }
+
+    pub fn allows(&self, key: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|prefix| key.starts_with(prefix)))
+            && !self.deny.iter().any(|prefix| key.starts_with(prefix))
+    }
+
+    // Whether every key under `prefix` may be accessed, i.e. `prefix` lies within an allowed
+    // prefix and no denied prefix lies within it
+    fn allows_all_under(&self, prefix: &str) -> bool {
+        self.allows(prefix) && !self.deny.iter().any(|denied| denied.starts_with(prefix))
+    }
+}
+
+// Rejects every operation on a key outside of `policy` with `PolicyViolation` before it reaches
+// the inner store, to keep a misconfigured instance sharing a bucket with others within its own
+// namespace. A complement to the store's own access control, not a replacement for it.
+//
+// Listings are allowed under allowed prefixes and leave out keys under denied prefixes. Totals
+// such as `prefix_size` can't leave them out, so they are rejected for prefixes containing a
+// denied prefix.
+pub struct KeyPolicyStore<D: DataStoreDriver> {
+    inner: D,
+    policy: KeyPolicy,
+}
+
+impl<D: DataStoreDriver> KeyPolicyStore<D> {
+    pub fn new(inner: D, policy: KeyPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn check_key(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let key = object_key(file_name, file_path);
+        match self.policy.allows(&key) {
+            true => Ok(()),
+            false => Err(DataStoreError::PolicyViolation(key).to_string()),
+        }
+    }
+
+    fn check_prefix(&self, file_path: Option<&str>) -> Result<(), String> {
+        let prefix = list_prefix(file_path);
+        match self.policy.allows(&prefix) {
+            true => Ok(()),
+            false => Err(DataStoreError::PolicyViolation(prefix).to_string()),
+        }
+    }
+
+    fn check_whole_prefix(&self, file_path: Option<&str>) -> Result<(), String> {
+        let prefix = list_prefix(file_path);
+        match self.policy.allows_all_under(&prefix) {
+            true => Ok(()),
+            false => Err(DataStoreError::PolicyViolation(prefix).to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for KeyPolicyStore<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.check_prefix(file_path)?;
+        let mut keys = self.inner.list_objects(file_path).await?;
+        keys.retain(|key| self.policy.allows(key));
+        Ok(keys)
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner.fetch_object(file_name, file_path).await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_object(file_name, contents, file_path)
+            .await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.check_whole_prefix(file_path)?;
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.check_prefix(file_path)?;
+        let mut objects = self.inner.list_object_metadata(file_path).await?;
+        objects.retain(|object| self.policy.allows(&object.key));
+        Ok(objects)
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        // Leaving out denied keys afterwards would return fewer than the `n` most recent objects
+        self.check_whole_prefix(file_path)?;
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.check_key(file_name, file_path)?;
+        self.inner.fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .fetch_generation(file_name, file_path, generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.check_key(file_name, file_path)?;
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.check_key(target_file_name, target_file_path)?;
+        self.inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.inner.delete_object(file_name, file_path).await
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
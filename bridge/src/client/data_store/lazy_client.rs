@@ -0,0 +1,34 @@
+use std::future::Future;
+
+use futures::future::BoxFuture;
+use tokio::sync::OnceCell;
+
+// Builds an expensive client, connection or auth token on first use instead of in the driver's
+// constructor. Concurrent first uses wait for a single initialization rather than each opening
+// their own connection. A failed initialization isn't cached, the next use tries again.
+pub struct LazyClient<T> {
+    cell: OnceCell<T>,
+    init: Box<dyn Fn() -> BoxFuture<'static, Result<T, String>> + Send + Sync>,
+}
+
+impl<T: Send + Sync> LazyClient<T> {
+    pub fn new<F, Fut>(init: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, String>> + Send + 'static,
+    {
+        Self {
+            cell: OnceCell::new(),
+            init: Box::new(move || Box::pin(init())),
+        }
+    }
+
+    /// Returns the client, initializing it first if no earlier call succeeded in doing so.
+    pub async fn get(&self) -> Result<&T, String> {
+        self.cell.get_or_try_init(|| (self.init)()).await
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.cell.initialized()
+    }
+}
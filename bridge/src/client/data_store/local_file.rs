@@ -1,19 +1,31 @@
-use crate::{
-    error::err_to_string,
-    utils::{compress, decompress, DEFAULT_COMPRESSION_LEVEL},
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
-use super::base::DataStoreDriver;
+use crate::error::{err_to_string, DataStoreError};
+
+use super::base::{
+    ByteRange, DataStoreDriver, DriverConfig, FetchResult, ObjectMetadata, RecentObjects,
+    StoreCapabilities,
+};
+use super::format::check_encoded;
+use super::key::{check_key_length, normalize_path, object_key};
 use async_trait::async_trait;
 use dotenv;
 
 pub const TEST_DATA_DIRECTORY_NAME: &str = "test_data";
 const DATA_STORE_DIRECTORY_NAME: &str = "shared_file_store";
+// Longest path Linux accepts, including the base path
+const MAX_PATH_LENGTH: usize = 4096;
 // To use this data store, create a .env file in the base directory with the following values:
 // export BRIDGE_USE_LOCAL_FILE_DATA_STORE=true
 // This data store driver will only be used in testing, DO NOT use in production
 pub struct LocalFile {
     base_path: std::path::PathBuf,
+    config: DriverConfig,
 }
 
 impl LocalFile {
@@ -42,7 +54,39 @@ impl LocalFile {
             }
         }
 
-        Some(Self { base_path })
+        Some(Self {
+            base_path,
+            config: DriverConfig::from_env(),
+        })
+    }
+
+    /// Replaces the whole `DriverConfig` read from the environment.
+    pub fn with_config(mut self, config: DriverConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Stores objects under `base_path` instead of the shared test data directory.
+    pub fn with_base_path(base_path: PathBuf) -> Self {
+        Self {
+            base_path,
+            config: DriverConfig::from_env(),
+        }
+    }
+
+    fn dir_path(&self, file_path: Option<&str>) -> PathBuf {
+        match normalize_path(file_path) {
+            Some(file_path) => self.base_path.join(file_path),
+            None => self.base_path.clone(),
+        }
+    }
+
+    fn check_key(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        check_key_length(file_name, file_path, self.max_key_length())
+    }
+
+    fn object_path(&self, file_name: &str, file_path: Option<&str>) -> PathBuf {
+        self.dir_path(file_path).join(file_name)
     }
 
     async fn get_object(
@@ -50,12 +94,7 @@ impl LocalFile {
         file_name: &str,
         file_path: Option<&str>,
     ) -> std::io::Result<Vec<u8>> {
-        let path = match file_path {
-            Some(file_path) => self.base_path.join(file_path).join(file_name),
-            None => self.base_path.join(file_name),
-        };
-
-        std::fs::read(path)
+        std::fs::read(self.object_path(file_name, file_path))
     }
 
     async fn upload_object(
@@ -64,15 +103,8 @@ impl LocalFile {
         data: Vec<u8>,
         file_path: Option<&str>,
     ) -> std::io::Result<()> {
-        let path = match file_path {
-            Some(file_path) => self.base_path.join(file_path).join(file_name),
-            None => self.base_path.join(file_name),
-        };
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)?;
-            }
-        }
+        let path = self.object_path(file_name, file_path);
+        create_parent_dir(&path)?;
 
         std::fs::write(path, data)
     }
@@ -80,23 +112,38 @@ impl LocalFile {
 
 #[async_trait]
 impl DataStoreDriver for LocalFile {
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities::PREFIX_SIZE
+            | StoreCapabilities::RANGE_READ
+            | StoreCapabilities::RAW_READ
+            | StoreCapabilities::SERVER_SIDE_COPY
+            | StoreCapabilities::DELETE
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        let base_path_length = self.base_path.as_os_str().len() + 1; // With the separator
+        Some(MAX_PATH_LENGTH.saturating_sub(base_path_length))
+    }
+
     async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
-        let path = match file_path {
-            Some(file_path) => self.base_path.join(file_path),
-            None => self.base_path.clone(),
-        };
+        let path = self.dir_path(file_path);
+        // Like an S3 prefix, a directory that doesn't exist holds no objects
         if !path.exists() {
-            std::fs::create_dir_all(&path).map_err(err_to_string)?;
+            return Ok(vec![]);
         }
-        let paths = std::fs::read_dir(path).unwrap();
+        let paths = std::fs::read_dir(path).map_err(err_to_string)?;
 
-        Ok(paths
+        let mut keys: Vec<String> = paths
             .filter_map(|path| {
                 path.ok()
                     .filter(|path| path.path().is_file())
                     .map(|p| p.path().to_string_lossy().to_string())
             })
-            .collect())
+            .take(self.config.max_keys_total.map_or(usize::MAX, |max| max + 1))
+            .collect();
+        self.config.cap_keys(&mut keys, file_path)?;
+
+        Ok(keys)
     }
 
     async fn fetch_object(
@@ -104,6 +151,7 @@ impl DataStoreDriver for LocalFile {
         file_name: &str,
         file_path: Option<&str>,
     ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
         let response = self.get_object(file_name, file_path).await;
         match response {
             Ok(buffer) => {
@@ -113,6 +161,9 @@ impl DataStoreDriver for LocalFile {
                     Err(err) => Err(format!("Failed to parse json: {}", err)),
                 }
             }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Err(DataStoreError::NotFound(object_key(file_name, file_path)).to_string())
+            }
             Err(err) => Err(format!("Failed to get json file: {}", err)),
         }
     }
@@ -123,7 +174,9 @@ impl DataStoreDriver for LocalFile {
         contents: &str,
         file_path: Option<&str>,
     ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
         let size = contents.len();
+        self.config.check_upload(size)?;
         let data = contents.as_bytes().to_vec();
 
         match self.upload_object(file_name, data, file_path).await {
@@ -136,12 +189,25 @@ impl DataStoreDriver for LocalFile {
         &self,
         file_name: &str,
         file_path: Option<&str>,
-    ) -> Result<(Vec<u8>, usize), String> {
+    ) -> Result<FetchResult, String> {
+        self.check_key(file_name, file_path)?;
         let response = self.get_object(file_name, file_path).await;
         match response {
             Ok(buffer) => {
-                let size = buffer.len();
-                Ok((decompress(&buffer).map_err(err_to_string)?, size))
+                self.config
+                    .decode_fetched_or_refetch(
+                        &object_key(file_name, file_path),
+                        &buffer,
+                        || async {
+                            self.get_object(file_name, file_path)
+                                .await
+                                .map_err(err_to_string)
+                        },
+                    )
+                    .await
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Err(DataStoreError::NotFound(object_key(file_name, file_path)).to_string())
             }
             Err(err) => Err(format!("Failed to get json file: {}", err)),
         }
@@ -153,9 +219,10 @@ impl DataStoreDriver for LocalFile {
         contents: &Vec<u8>,
         file_path: Option<&str>,
     ) -> Result<usize, String> {
-        let compressed_data =
-            compress(contents, DEFAULT_COMPRESSION_LEVEL).map_err(err_to_string)?;
+        self.check_key(file_name, file_path)?;
+        let compressed_data = self.config.encode_upload(contents, file_name, file_path)?;
         let size = compressed_data.len();
+        self.config.check_upload(size)?;
 
         match self
             .upload_object(file_name, compressed_data, file_path)
@@ -165,4 +232,172 @@ impl DataStoreDriver for LocalFile {
             Err(err) => Err(format!("Failed to save json file: {}", err)),
         }
     }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let path = self.dir_path(file_path);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut objects = vec![];
+        for entry in std::fs::read_dir(path).map_err(err_to_string)? {
+            let entry = entry.map_err(err_to_string)?;
+            let metadata = entry.metadata().map_err(err_to_string)?;
+            if metadata.is_file() {
+                objects.push(ObjectMetadata {
+                    key: object_key(&entry.file_name().to_string_lossy(), file_path),
+                    size: metadata.len(),
+                    etag: None,
+                    raw_size: None,
+                });
+            }
+        }
+
+        Ok(objects)
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        let path = self.dir_path(file_path);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut recent = RecentObjects::new(n);
+        for entry in std::fs::read_dir(path).map_err(err_to_string)? {
+            let entry = entry.map_err(err_to_string)?;
+            let metadata = entry.metadata().map_err(err_to_string)?;
+            if metadata.is_file() {
+                recent.push(
+                    object_key(&entry.file_name().to_string_lossy(), file_path),
+                    metadata.modified().map_err(err_to_string)?,
+                );
+            }
+        }
+
+        Ok(recent.into_sorted())
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        let path = self.dir_path(file_path);
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let mut total: u64 = 0;
+        for entry in std::fs::read_dir(path).map_err(err_to_string)? {
+            let metadata = entry.and_then(|e| e.metadata()).map_err(err_to_string)?;
+            if metadata.is_file() {
+                total += metadata.len();
+            }
+        }
+
+        Ok(total)
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.check_key(file_name, file_path)?;
+        self.get_object(file_name, file_path)
+            .await
+            .map_err(err_to_string)
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.check_key(file_name, file_path)?;
+        let mut file = File::open(self.object_path(file_name, file_path)).map_err(err_to_string)?;
+        let size = file.metadata().map_err(err_to_string)?.len();
+        let (start, end) = range.bounds(size);
+        let mut data = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start)).map_err(err_to_string)?;
+        file.read_exact(&mut data).map_err(err_to_string)?;
+
+        Ok((data, size))
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.check_key(target_file_name, target_file_path)?;
+        self.config.check_write()?;
+        let target = self.object_path(target_file_name, target_file_path);
+        create_parent_dir(&target).map_err(err_to_string)?;
+
+        std::fs::copy(self.object_path(file_name, file_path), target)
+            .map(|_| ())
+            .map_err(err_to_string)
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.config.check_write()?;
+        match std::fs::remove_file(self.object_path(file_name, file_path)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err_to_string(err)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.config.check_write()?;
+        let path = self.object_path(file_name, file_path);
+        if !path.is_file() {
+            return Err(DataStoreError::NotFound(object_key(file_name, file_path)).to_string());
+        }
+
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(self.config.clock.now()))
+            .map_err(|err| format!("Failed to touch {}: {}", file_name, err))
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        check_encoded(compressed).map_err(err_to_string)?;
+        let size = compressed.len();
+        self.config.check_upload(size)?;
+
+        match self
+            .upload_object(file_name, compressed.to_vec(), file_path)
+            .await
+        {
+            Ok(_) => Ok(size),
+            Err(err) => Err(format!("Failed to save json file: {}", err)),
+        }
+    }
+}
+
+fn create_parent_dir(path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,379 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::{
+    error::{err_to_string, DataStoreError},
+    utils::DEFAULT_COMPRESSION_LEVEL,
+};
+
+use md5::{Digest, Md5};
+
+use super::base::{
+    ByteRange, DataStoreDriver, DecompressFailureMode, FetchResult, ObjectMetadata,
+    StoreCapabilities, UploadOutcome,
+};
+use super::format::{
+    check_encoded, decode_object, encode_object_with_min_size, is_encoded,
+    DEFAULT_MIN_COMPRESS_SIZE,
+};
+use super::key::{check_key_length, list_prefix, object_key};
+use async_trait::async_trait;
+
+// Keeps every object in process memory. Intended for tests and local tooling,
+// nothing is persisted once the store is dropped.
+#[derive(Default)]
+pub struct MemoryStore {
+    objects: RwLock<HashMap<String, Vec<u8>>>,
+    // Idempotency key of every object written by `upload_object_idempotent`
+    idempotency_keys: RwLock<HashMap<String, String>>,
+    decompress_failure_mode: DecompressFailureMode,
+    // Keys are unlimited unless set, e.g. to test handling of another driver's limit
+    max_key_length: Option<usize>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_decompress_failure_mode(mut self, mode: DecompressFailureMode) -> Self {
+        self.decompress_failure_mode = mode;
+        self
+    }
+
+    pub fn with_max_key_length(mut self, max_key_length: usize) -> Self {
+        self.max_key_length = Some(max_key_length);
+        self
+    }
+
+    fn check_key(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        check_key_length(file_name, file_path, self.max_key_length)
+    }
+
+    fn get_object(&self, file_name: &str, file_path: Option<&str>) -> Result<Vec<u8>, String> {
+        let key = object_key(file_name, file_path);
+        self.objects
+            .read()
+            .unwrap()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| DataStoreError::NotFound(key).to_string())
+    }
+
+    fn etag(data: &[u8]) -> String {
+        format!("\"{:x}\"", Md5::digest(data))
+    }
+
+    fn upload_object(&self, file_name: &str, data: Vec<u8>, file_path: Option<&str>) {
+        let key = object_key(file_name, file_path);
+        self.idempotency_keys.write().unwrap().remove(&key);
+        self.objects.write().unwrap().insert(key, data);
+    }
+
+    fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        data: Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let key = object_key(file_name, file_path);
+        let mut objects = self.objects.write().unwrap();
+        if objects.contains_key(&key) {
+            return Err(DataStoreError::AlreadyExists(key).to_string());
+        }
+        objects.insert(key, data);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DataStoreDriver for MemoryStore {
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities::CONDITIONAL_WRITES
+            | StoreCapabilities::PREFIX_SIZE
+            | StoreCapabilities::RANGE_READ
+            | StoreCapabilities::RAW_READ
+            | StoreCapabilities::SERVER_SIDE_COPY
+            | StoreCapabilities::DELETE
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.max_key_length
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let prefix = list_prefix(file_path);
+        let mut keys: Vec<String> = self
+            .objects
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(&prefix))
+            .cloned()
+            .collect();
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
+        let buffer = self.get_object(file_name, file_path)?;
+        String::from_utf8(buffer).map_err(|err| format!("Failed to parse json: {}", err))
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.upload_object(file_name, contents.as_bytes().to_vec(), file_path);
+        Ok(contents.len())
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.check_key(file_name, file_path)?;
+        FetchResult::decode_with(
+            &self.get_object(file_name, file_path)?,
+            self.decompress_failure_mode,
+        )
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        let compressed_data = encode_object_with_min_size(
+            contents,
+            DEFAULT_COMPRESSION_LEVEL,
+            DEFAULT_MIN_COMPRESS_SIZE,
+        )
+        .map_err(err_to_string)?;
+        let size = compressed_data.len();
+        self.upload_object(file_name, compressed_data, file_path);
+
+        Ok(size)
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.check_key(file_name, file_path)?;
+        Ok(self
+            .objects
+            .read()
+            .unwrap()
+            .contains_key(&object_key(file_name, file_path)))
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let prefix = list_prefix(file_path);
+        let mut objects: Vec<ObjectMetadata> = self
+            .objects
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, data)| ObjectMetadata {
+                key: key.clone(),
+                size: data.len() as u64,
+                etag: Some(Self::etag(data)),
+                // Derived from the stored object, as there is no metadata to record it in
+                raw_size: is_encoded(data)
+                    .then(|| decode_object(data).ok())
+                    .flatten()
+                    .map(|raw| raw.len() as u64),
+            })
+            .collect();
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(objects)
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        let prefix = list_prefix(file_path);
+        Ok(self
+            .objects
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, data)| data.len() as u64)
+            .sum())
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.check_key(file_name, file_path)?;
+        let buffer = self.get_object(file_name, file_path)?;
+        let etag = Self::etag(&buffer);
+        let json =
+            String::from_utf8(buffer).map_err(|err| format!("Failed to parse json: {}", err))?;
+
+        Ok((json, etag))
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.check_key(file_name, file_path)?;
+        let key = object_key(file_name, file_path);
+        let mut objects = self.objects.write().unwrap();
+        match objects.get(&key) {
+            Some(current) if Self::etag(current) == etag => {
+                self.idempotency_keys.write().unwrap().remove(&key);
+                objects.insert(key, contents.as_bytes().to_vec());
+                Ok(Self::etag(contents.as_bytes()))
+            }
+            Some(_) => Err(DataStoreError::PreconditionFailed(key).to_string()),
+            None => Err(DataStoreError::NotFound(key).to_string()),
+        }
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.check_key(file_name, file_path)?;
+        self.get_object(file_name, file_path)
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.check_key(file_name, file_path)?;
+        let data = self.get_object(file_name, file_path)?;
+        let (start, end) = range.bounds(data.len() as u64);
+        Ok((
+            data[start as usize..end as usize].to_vec(),
+            data.len() as u64,
+        ))
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        self.check_key(target_file_name, target_file_path)?;
+        let data = self.get_object(file_name, file_path)?;
+        self.upload_object(target_file_name, data, target_file_path);
+        Ok(())
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        let key = object_key(file_name, file_path);
+        self.idempotency_keys.write().unwrap().remove(&key);
+        self.objects.write().unwrap().remove(&key);
+        Ok(())
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        check_encoded(compressed).map_err(err_to_string)?;
+        self.upload_object(file_name, compressed.to_vec(), file_path);
+
+        Ok(compressed.len())
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        self.check_key(file_name, file_path)?;
+        let key = object_key(file_name, file_path);
+        let applied = self
+            .idempotency_keys
+            .read()
+            .unwrap()
+            .get(&key)
+            .is_some_and(|key| key == idempotency_key);
+        if applied {
+            return Ok(UploadOutcome::AlreadyApplied);
+        }
+
+        self.upload_object(file_name, contents.as_bytes().to_vec(), file_path);
+        self.idempotency_keys
+            .write()
+            .unwrap()
+            .insert(key, idempotency_key.to_string());
+        Ok(UploadOutcome::Uploaded(contents.len()))
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        self.upload_object_if_absent(file_name, contents.as_bytes().to_vec(), file_path)?;
+        Ok(contents.len())
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.check_key(file_name, file_path)?;
+        let compressed_data = encode_object_with_min_size(
+            contents,
+            DEFAULT_COMPRESSION_LEVEL,
+            DEFAULT_MIN_COMPRESS_SIZE,
+        )
+        .map_err(err_to_string)?;
+        let size = compressed_data.len();
+        self.upload_object_if_absent(file_name, compressed_data, file_path)?;
+
+        Ok(size)
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.check_key(file_name, file_path)?;
+        // Objects held in memory have no modification time to bump
+        self.get_object(file_name, file_path).map(|_| ())
+    }
+}
@@ -0,0 +1,84 @@
+use futures::{stream, StreamExt};
+
+use super::base::{DataStoreDriver, StoreCapabilities};
+use super::format::is_encoded;
+use super::key::parse_key;
+
+const COPY_ALL_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub copied: usize,
+    pub skipped: usize,                // Already present at the destination
+    pub failed: Vec<(String, String)>, // Key and cause of each object that wasn't copied
+}
+
+enum CopyResult {
+    Copied,
+    Skipped,
+}
+
+/// Copies every object under `file_path` from `src` to `dst`, e.g. to move a deployment to
+/// another backend. Objects that already exist at the destination are skipped, so an interrupted
+/// migration can simply be rerun. A failed object doesn't stop the others from being copied.
+///
+/// Objects are copied as stored where the source supports raw reads, so compressed objects are
+/// neither decompressed nor compressed again on the way.
+pub async fn copy_all(
+    src: &dyn DataStoreDriver,
+    dst: &dyn DataStoreDriver,
+    file_path: Option<&str>,
+) -> Result<MigrationReport, String> {
+    let keys = src.list_objects(file_path).await?;
+    let results: Vec<(String, Result<CopyResult, String>)> = stream::iter(keys)
+        .map(|key| async move {
+            let result = copy_one(src, dst, &key).await;
+            (key, result)
+        })
+        .buffer_unordered(COPY_ALL_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut report = MigrationReport::default();
+    for (key, result) in results {
+        match result {
+            Ok(CopyResult::Copied) => report.copied += 1,
+            Ok(CopyResult::Skipped) => report.skipped += 1,
+            Err(err) => report.failed.push((key, err)),
+        }
+    }
+    report.failed.sort();
+
+    Ok(report)
+}
+
+async fn copy_one(
+    src: &dyn DataStoreDriver,
+    dst: &dyn DataStoreDriver,
+    key: &str,
+) -> Result<CopyResult, String> {
+    let key = parse_key(key);
+    let directory = key.directory();
+    let (file_name, file_path) = (key.file_name(), directory.as_deref());
+    if dst.object_exists(file_name, file_path).await? {
+        return Ok(CopyResult::Skipped);
+    }
+
+    if !src.capabilities().contains(StoreCapabilities::RAW_READ) {
+        let contents = src.fetch_object(file_name, file_path).await?;
+        dst.upload_object(file_name, &contents, file_path).await?;
+        return Ok(CopyResult::Copied);
+    }
+
+    let stored = src.fetch_raw_object(file_name, file_path).await?;
+    if is_encoded(&stored) {
+        dst.upload_precompressed_object(file_name, &stored, file_path)
+            .await?;
+    } else {
+        let contents =
+            String::from_utf8(stored).map_err(|err| format!("Failed to parse json: {}", err))?;
+        dst.upload_object(file_name, &contents, file_path).await?;
+    }
+
+    Ok(CopyResult::Copied)
+}
@@ -1,6 +1,41 @@
+pub mod access_time;
+pub mod audit;
 pub mod aws_s3;
 pub mod base;
+pub mod bulk_fetch;
+pub mod bulk_upload;
+pub mod cached_listing;
+pub mod cached_store;
+pub mod chunked;
+pub mod clock;
+pub mod compression_policy;
+pub mod compression_tuner;
 pub mod data_store;
+pub mod delayed_consistency;
+pub mod dns_cache;
+pub mod encrypted_keys;
+pub mod encryption;
+pub mod fallback;
+pub mod format;
 pub mod ftp;
+pub mod integrity;
+pub mod key;
+pub mod key_layout;
+pub mod key_policy;
+pub mod lazy_client;
 pub mod local_file;
+pub mod memory;
+pub mod migration;
+pub mod multi_region;
+pub mod prefetch;
+pub mod read_only;
+pub mod read_retry;
+pub mod recording;
+pub mod retry_after;
+pub mod retry_budget;
+pub mod schema;
 pub mod sftp;
+pub mod size_metrics;
+pub mod spread_prefixes;
+pub mod stats;
+pub mod timestamped;
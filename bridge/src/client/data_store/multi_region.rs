@@ -0,0 +1,409 @@
+use std::{future::Future, time::SystemTime};
+
+use crate::error::DataStoreError;
+
+use super::aws_s3::AwsS3;
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::key::{list_prefix, object_key};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use futures::future::join_all;
+use tokio::io::AsyncRead;
+
+// Reads from a bucket replicated to other regions, failing over to the next region when a read
+// times out or fails with a server error. Any other error, e.g. a missing object, is returned
+// right away. Writes go to the primary region only, or to every region with `with_mirror_writes`.
+//
+// Whether a secondary region has the latest version of an object depends entirely on the
+// bucket's replication setup: after a failover, reads may return stale objects or miss recently
+// written ones.
+pub struct MultiRegionStore {
+    regions: Vec<AwsS3>, // The primary region first
+    mirror_writes: bool,
+}
+
+impl MultiRegionStore {
+    pub fn new(primary: AwsS3, secondaries: Vec<AwsS3>) -> Self {
+        let mut regions = vec![primary];
+        regions.extend(secondaries);
+        Self {
+            regions,
+            mirror_writes: false,
+        }
+    }
+
+    /// Also writes every upload and delete to the secondary regions, for buckets that aren't
+    /// replicated by S3 itself. Failed writes to secondaries are logged, not returned.
+    pub fn with_mirror_writes(mut self, mirror_writes: bool) -> Self {
+        self.mirror_writes = mirror_writes;
+        self
+    }
+
+    fn primary(&self) -> &AwsS3 {
+        &self.regions[0]
+    }
+
+    async fn read<T, F, Fut>(&self, key: &str, read: F) -> Result<T, String>
+    where
+        F: Fn(usize) -> Fut + Send,
+        Fut: Future<Output = Result<T, String>> + Send,
+    {
+        let unavailable = DataStoreError::Unavailable(key.to_string()).to_string();
+        let mut last_err = String::new();
+        for region in 0..self.regions.len() {
+            match read(region).await {
+                Err(err) if err.contains(&unavailable) => last_err = err,
+                result => return result,
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn mirror<F, Fut>(&self, key: &str, write: F)
+    where
+        F: Fn(usize) -> Fut + Send,
+        Fut: Future<Output = Result<(), String>> + Send,
+    {
+        if !self.mirror_writes {
+            return;
+        }
+        for region in 1..self.regions.len() {
+            if let Err(err) = write(region).await {
+                eprintln!("Failed to mirror {key} to secondary region {region}: {err}");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DataStoreDriver for MultiRegionStore {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.primary().capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.primary().max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.regions
+            .iter()
+            .fold(StoreStats::default(), |stats, region| {
+                stats + region.stats()
+            })
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.read(&list_prefix(file_path), |region| {
+            self.regions[region].list_objects(file_path)
+        })
+        .await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.read(&object_key(file_name, file_path), |region| {
+            self.regions[region].fetch_object(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .primary()
+            .upload_object(file_name, contents, file_path)
+            .await?;
+        self.mirror(&object_key(file_name, file_path), |region| async move {
+            self.regions[region]
+                .upload_object(file_name, contents, file_path)
+                .await
+                .map(|_| ())
+        })
+        .await;
+
+        Ok(size)
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.read(&object_key(file_name, file_path), |region| {
+            self.regions[region].fetch_compressed_object(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .primary()
+            .upload_compressed_object(file_name, contents, file_path)
+            .await?;
+        self.mirror(&object_key(file_name, file_path), |region| async move {
+            self.regions[region]
+                .upload_compressed_object(file_name, contents, file_path)
+                .await
+                .map(|_| ())
+        })
+        .await;
+
+        Ok(size)
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.read(&object_key(file_name, file_path), |region| {
+            self.regions[region].object_exists(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.primary().prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.primary().list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.primary().list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        // Etags are only meaningful for conditional writes to the primary
+        self.primary().fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.primary()
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        // Retention is set per region, so locked uploads are never mirrored
+        self.primary()
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        // Idempotency keys are only checked against the primary, so these aren't mirrored
+        self.primary()
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.read(&object_key(file_name, file_path), |region| {
+            self.regions[region].fetch_generation(file_name, file_path, generation)
+        })
+        .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        // Only the primary decides whether the object exists, secondaries are overwritten
+        let size = self
+            .primary()
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await?;
+        self.mirror(&object_key(file_name, file_path), |region| async move {
+            self.regions[region]
+                .upload_object(file_name, contents, file_path)
+                .await
+                .map(|_| ())
+        })
+        .await;
+
+        Ok(size)
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        // Only the primary decides whether the object exists, secondaries are overwritten
+        let size = self
+            .primary()
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await?;
+        self.mirror(&object_key(file_name, file_path), |region| async move {
+            self.regions[region]
+                .upload_compressed_object(file_name, contents, file_path)
+                .await
+                .map(|_| ())
+        })
+        .await;
+
+        Ok(size)
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.primary().touch(file_name, file_path).await?;
+        self.mirror(&object_key(file_name, file_path), |region| {
+            self.regions[region].touch(file_name, file_path)
+        })
+        .await;
+
+        Ok(())
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.read(&object_key(file_name, file_path), |region| {
+            self.regions[region].fetch_raw_object(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.read(&object_key(file_name, file_path), |region| {
+            self.regions[region].fetch_range(file_name, file_path, range)
+        })
+        .await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.primary()
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await?;
+        self.mirror(&object_key(target_file_name, target_file_path), |region| {
+            self.regions[region].copy_object(
+                file_name,
+                file_path,
+                target_file_name,
+                target_file_path,
+            )
+        })
+        .await;
+
+        Ok(())
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.primary().delete_object(file_name, file_path).await?;
+        self.mirror(&object_key(file_name, file_path), |region| {
+            self.regions[region].delete_object(file_name, file_path)
+        })
+        .await;
+
+        Ok(())
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        // The reader can only be consumed once, so streamed uploads are never mirrored
+        self.primary()
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let size = self
+            .primary()
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await?;
+        self.mirror(&object_key(file_name, file_path), |region| async move {
+            self.regions[region]
+                .upload_precompressed_object(file_name, compressed, file_path)
+                .await
+                .map(|_| ())
+        })
+        .await;
+
+        Ok(size)
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        let results = join_all(self.regions.into_iter().map(|region| region.shutdown())).await;
+        results.into_iter().collect()
+    }
+}
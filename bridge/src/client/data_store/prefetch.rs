@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use super::base::DataStoreDriver;
+
+// Read-ahead for code that processes objects one after another in key order. A background task
+// keeps up to `depth` fetches in flight and up to `depth` fetched objects waiting, so the
+// consumer rarely waits on a round trip. Dropping the `Prefetch` cancels outstanding fetches.
+pub struct Prefetch {
+    receiver: mpsc::Receiver<(String, Result<String, String>)>,
+    task: JoinHandle<()>,
+}
+
+impl Prefetch {
+    /// Starts fetching `file_names` under `file_path` from `driver`, in order.
+    pub fn new<D, I>(driver: Arc<D>, file_names: I, file_path: Option<String>, depth: usize) -> Self
+    where
+        D: DataStoreDriver + Send + Sync + ?Sized + 'static,
+        I: IntoIterator<Item = String>,
+        I::IntoIter: Send + 'static,
+    {
+        let depth = depth.max(1);
+        let (sender, receiver) = mpsc::channel(depth);
+        let file_names = file_names.into_iter();
+        let task = tokio::spawn(async move {
+            let mut objects = stream::iter(file_names)
+                .map(|file_name| {
+                    let driver = driver.clone();
+                    let file_path = file_path.clone();
+                    async move {
+                        let object = driver.fetch_object(&file_name, file_path.as_deref()).await;
+                        (file_name, object)
+                    }
+                })
+                .buffered(depth);
+            while let Some(object) = objects.next().await {
+                if sender.send(object).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { receiver, task }
+    }
+
+    /// The next file name with its fetched contents, or `None` once every object was returned.
+    pub async fn next(&mut self) -> Option<(String, Result<String, String>)> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for Prefetch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
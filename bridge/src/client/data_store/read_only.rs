@@ -0,0 +1,250 @@
+use std::time::SystemTime;
+
+use crate::error::DataStoreError;
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// Wraps any data store driver and rejects every write, so replica and verifier nodes
+// fail fast instead of mutating shared storage.
+pub struct ReadOnly<D: DataStoreDriver> {
+    inner: D,
+}
+
+impl<D: DataStoreDriver> ReadOnly<D> {
+    pub fn new(inner: D) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for ReadOnly<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        // Conditional writes are still writes
+        self.inner
+            .capabilities()
+            .difference(StoreCapabilities::CONDITIONAL_WRITES)
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.inner.fetch_object(file_name, file_path).await
+    }
+
+    async fn upload_object(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.inner
+            .fetch_compressed_object(file_name, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        _file_name: &str,
+        _contents: &Vec<u8>,
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn list_objects_with_suffix(
+        &self,
+        file_path: Option<&str>,
+        suffix: &str,
+    ) -> Result<Vec<String>, String> {
+        self.inner.list_objects_with_suffix(file_path, suffix).await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.inner.fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+        _etag: &str,
+    ) -> Result<String, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn upload_object_locked(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+        _retain_until: SystemTime,
+        _mode: RetentionMode,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+        _idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.inner
+            .fetch_generation(file_name, file_path, generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        _file_name: &str,
+        _contents: &str,
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        _file_name: &str,
+        _contents: &Vec<u8>,
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn touch(&self, _file_name: &str, _file_path: Option<&str>) -> Result<(), String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+        _target_file_name: &str,
+        _target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn delete_object(
+        &self,
+        _file_name: &str,
+        _file_path: Option<&str>,
+    ) -> Result<(), String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        _file_name: &str,
+        _reader: &mut (dyn AsyncRead + Send + Unpin),
+        _size_hint: Option<u64>,
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        _file_name: &str,
+        _compressed: &[u8],
+        _file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        Err(DataStoreError::ReadOnly.to_string())
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    future::Future,
+    time::{Duration, SystemTime},
+};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use rand::Rng;
+use tokio::{
+    io::AsyncRead,
+    time::{sleep, Instant},
+};
+
+#[derive(Clone, Debug)]
+pub struct ReadRetryPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // Total time spent waiting between attempts before the last error is returned
+    pub max_wait: Duration,
+    // Sleep exactly the backoff instead of a random 50-100% of it, so retry timing is
+    // reproducible under a paused tokio clock. Test-only: without jitter, nodes that hit the
+    // same missing object retry in lockstep.
+    pub deterministic: bool,
+}
+
+impl Default for ReadRetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(2),
+            max_wait: Duration::from_secs(10),
+            deterministic: false,
+        }
+    }
+}
+
+// Wraps any data store driver and retries reads of missing objects with exponential backoff,
+// for objects a peer just wrote to a shared eventually consistent store. Any other error is
+// returned right away.
+pub struct ReadRetry<D: DataStoreDriver> {
+    inner: D,
+    policy: ReadRetryPolicy,
+}
+
+impl<D: DataStoreDriver> ReadRetry<D> {
+    pub fn new(inner: D, policy: ReadRetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn jittered(&self, backoff: Duration) -> Duration {
+        match self.policy.deterministic {
+            true => backoff,
+            false => backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0)),
+        }
+    }
+}
+
+impl<D: DataStoreDriver + Send + Sync> ReadRetry<D> {
+    async fn retry_not_found<T, F, Fut>(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        mut read: F,
+    ) -> Result<T, String>
+    where
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<T, String>> + Send,
+    {
+        let deadline = Instant::now() + self.policy.max_wait;
+        let mut backoff = self.policy.initial_backoff;
+        loop {
+            let err = match read().await {
+                Ok(result) => return Ok(result),
+                Err(err) => err,
+            };
+
+            // Drivers word their errors differently, so ask the store whether the failure was
+            // caused by the object being missing
+            let now = Instant::now();
+            if now >= deadline || self.inner.object_exists(file_name, file_path).await? {
+                return Err(err);
+            }
+
+            sleep(self.jittered(backoff).min(deadline - now)).await;
+            backoff = (backoff * 2).min(self.policy.max_backoff);
+        }
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for ReadRetry<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.retry_not_found(file_name, file_path, || {
+            self.inner.fetch_object(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object(file_name, contents, file_path)
+            .await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.retry_not_found(file_name, file_path, || {
+            self.inner.fetch_compressed_object(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await
+    }
+
+    async fn list_objects_with_suffix(
+        &self,
+        file_path: Option<&str>,
+        suffix: &str,
+    ) -> Result<Vec<String>, String> {
+        self.inner.list_objects_with_suffix(file_path, suffix).await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.retry_not_found(file_name, file_path, || {
+            self.inner.fetch_with_etag(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        self.inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.retry_not_found(file_name, file_path, || {
+            self.inner
+                .fetch_generation(file_name, file_path, generation)
+        })
+        .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.retry_not_found(file_name, file_path, || {
+            self.inner.fetch_raw_object(file_name, file_path)
+        })
+        .await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.retry_not_found(file_name, file_path, || {
+            self.inner.fetch_range(file_name, file_path, range)
+        })
+        .await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.delete_object(file_name, file_path).await
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
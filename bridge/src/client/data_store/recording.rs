@@ -0,0 +1,524 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{BufRead, Write},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{err_to_string, DataStoreError};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::key::{list_prefix, object_key};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedMethod {
+    ListObjects,
+    FetchObject,
+    UploadObject,
+    FetchCompressedObject,
+    UploadCompressedObject,
+    ObjectExists,
+    DeleteObject,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedOutput {
+    Done,
+    Keys(Vec<String>),
+    Contents(String),
+    Bytes(String), // Base64 encoded
+    Exists(bool),
+    Size(usize),
+}
+
+// One data store call as captured by `RecordingStore`. There are no timestamps or other
+// run-specific fields, so recordings of the same sequence of calls are identical and diffable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedOp {
+    pub method: RecordedMethod,
+    pub key: String,         // The object key, or the listed prefix for listings
+    pub size: Option<usize>, // Bytes handed to or returned by the driver, before decompression
+    pub result: Result<RecordedOutput, String>,
+}
+
+// Wraps any data store driver and appends every listing, fetch, upload, existence check and
+// delete to `writer` as one line of JSON, so the exact sequence of storage calls behind a bug
+// can be captured and served again offline by a `ReplayStore`. Other calls are passed through
+// unrecorded.
+pub struct RecordingStore<D: DataStoreDriver> {
+    inner: D,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl<D: DataStoreDriver> RecordingStore<D> {
+    pub fn new(inner: D, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            inner,
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn record(&self, op: RecordedOp) {
+        let result = serde_json::to_vec(&op)
+            .map_err(err_to_string)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                let mut writer = self.writer.lock().unwrap();
+                writer.write_all(&line).map_err(err_to_string)?;
+                writer.flush().map_err(err_to_string)
+            });
+        // Recording is a debugging aid, the call itself already happened
+        if let Err(err) = result {
+            eprintln!("Failed to record data store call for {}: {err}", op.key);
+        }
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for RecordingStore<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let result = self.inner.list_objects(file_path).await;
+        self.record(RecordedOp {
+            method: RecordedMethod::ListObjects,
+            key: list_prefix(file_path),
+            size: None,
+            result: result.clone().map(RecordedOutput::Keys),
+        });
+        result
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let result = self.inner.fetch_object(file_name, file_path).await;
+        self.record(RecordedOp {
+            method: RecordedMethod::FetchObject,
+            key: object_key(file_name, file_path),
+            size: result.as_ref().ok().map(String::len),
+            result: result.clone().map(RecordedOutput::Contents),
+        });
+        result
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.record(RecordedOp {
+            method: RecordedMethod::UploadObject,
+            key: object_key(file_name, file_path),
+            size: Some(contents.len()),
+            result: result.clone().map(RecordedOutput::Size),
+        });
+        result
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let result = self
+            .inner
+            .fetch_compressed_object(file_name, file_path)
+            .await;
+        self.record(RecordedOp {
+            method: RecordedMethod::FetchCompressedObject,
+            key: object_key(file_name, file_path),
+            size: result.as_ref().ok().map(|fetched| fetched.stored_size),
+            result: result
+                .as_ref()
+                .map(|fetched| RecordedOutput::Bytes(BASE64.encode(&fetched.data)))
+                .map_err(Clone::clone),
+        });
+        result
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.record(RecordedOp {
+            method: RecordedMethod::UploadCompressedObject,
+            key: object_key(file_name, file_path),
+            size: Some(contents.len()),
+            result: result.clone().map(RecordedOutput::Size),
+        });
+        result
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        let result = self.inner.object_exists(file_name, file_path).await;
+        self.record(RecordedOp {
+            method: RecordedMethod::ObjectExists,
+            key: object_key(file_name, file_path),
+            size: None,
+            result: result.clone().map(RecordedOutput::Exists),
+        });
+        result
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        self.inner.fetch_with_etag(file_name, file_path).await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        self.inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        self.inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        self.inner
+            .fetch_generation(file_name, file_path, generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.inner.fetch_raw_object(file_name, file_path).await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.delete_object(file_name, file_path).await;
+        self.record(RecordedOp {
+            method: RecordedMethod::DeleteObject,
+            key: object_key(file_name, file_path),
+            size: None,
+            result: result.clone().map(|_| RecordedOutput::Done),
+        });
+        result
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        let flushed = self.writer.into_inner().unwrap().flush();
+        flushed.map_err(err_to_string)?;
+        self.inner.shutdown().await
+    }
+}
+
+// Serves the calls captured by a `RecordingStore`, without any backend. Each call returns the
+// next recorded result for the same method and key, and keeps returning the last one once they
+// are used up, so a replayed run sees objects change exactly as the recorded run did. Calls that
+// were never recorded behave as on an empty store. Only meant for debugging.
+pub struct ReplayStore {
+    results: Mutex<HashMap<(RecordedMethod, String), VecDeque<RecordedOp>>>,
+}
+
+impl ReplayStore {
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, String> {
+        let mut results: HashMap<(RecordedMethod, String), VecDeque<RecordedOp>> = HashMap::new();
+        for line in reader.lines() {
+            let line = line.map_err(err_to_string)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let op: RecordedOp = serde_json::from_str(&line).map_err(err_to_string)?;
+            results
+                .entry((op.method, op.key.clone()))
+                .or_default()
+                .push_back(op);
+        }
+
+        Ok(Self {
+            results: Mutex::new(results),
+        })
+    }
+
+    fn next(&self, method: RecordedMethod, key: String) -> Option<RecordedOp> {
+        let mut results = self.results.lock().unwrap();
+        let queue = results.get_mut(&(method, key))?;
+        match queue.len() {
+            1 => queue.front().cloned(),
+            _ => queue.pop_front(),
+        }
+    }
+}
+
+fn unexpected_output(key: &str) -> String {
+    format!("Recording of {key} does not match the replayed call")
+}
+
+#[async_trait]
+impl DataStoreDriver for ReplayStore {
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let key = list_prefix(file_path);
+        match self.next(RecordedMethod::ListObjects, key.clone()) {
+            Some(op) => match op.result? {
+                RecordedOutput::Keys(keys) => Ok(keys),
+                _ => Err(unexpected_output(&key)),
+            },
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let key = object_key(file_name, file_path);
+        match self.next(RecordedMethod::FetchObject, key.clone()) {
+            Some(op) => match op.result? {
+                RecordedOutput::Contents(contents) => Ok(contents),
+                _ => Err(unexpected_output(&key)),
+            },
+            None => Err(DataStoreError::NotFound(key).to_string()),
+        }
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let key = object_key(file_name, file_path);
+        match self.next(RecordedMethod::UploadObject, key.clone()) {
+            Some(op) => match op.result? {
+                RecordedOutput::Size(size) => Ok(size),
+                _ => Err(unexpected_output(&key)),
+            },
+            None => Ok(contents.len()),
+        }
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let key = object_key(file_name, file_path);
+        match self.next(RecordedMethod::FetchCompressedObject, key.clone()) {
+            Some(op) => match op.result? {
+                RecordedOutput::Bytes(encoded) => {
+                    let data = BASE64.decode(encoded).map_err(err_to_string)?;
+                    Ok(FetchResult {
+                        stored_size: op.size.unwrap_or(data.len()),
+                        decompressed_size: data.len(),
+                        decompress_failed: false,
+                        data,
+                    })
+                }
+                _ => Err(unexpected_output(&key)),
+            },
+            None => Err(DataStoreError::NotFound(key).to_string()),
+        }
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let key = object_key(file_name, file_path);
+        match self.next(RecordedMethod::UploadCompressedObject, key.clone()) {
+            Some(op) => match op.result? {
+                RecordedOutput::Size(size) => Ok(size),
+                _ => Err(unexpected_output(&key)),
+            },
+            None => Ok(contents.len()),
+        }
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        let key = object_key(file_name, file_path);
+        match self.next(RecordedMethod::ObjectExists, key.clone()) {
+            Some(op) => match op.result? {
+                RecordedOutput::Exists(exists) => Ok(exists),
+                _ => Err(unexpected_output(&key)),
+            },
+            None => Ok(false),
+        }
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let key = object_key(file_name, file_path);
+        match self.next(RecordedMethod::DeleteObject, key) {
+            Some(op) => op.result.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
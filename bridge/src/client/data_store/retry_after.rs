@@ -0,0 +1,139 @@
+use std::{
+    borrow::Cow,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use aws_sdk_s3::{
+    config::retry::{ClassifyRetry, RetryAction},
+    primitives::{DateTime, DateTimeFormat},
+};
+use aws_smithy_async::time::TimeSource;
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::context::InterceptorContext,
+        retries::{
+            classifiers::RetryClassifierPriority, RetryStrategy, SharedRetryStrategy, ShouldAttempt,
+        },
+        runtime_components::{RuntimeComponents, RuntimeComponentsBuilder},
+        runtime_plugin::{Order, RuntimePlugin},
+    },
+};
+use aws_smithy_types::{
+    config_bag::ConfigBag,
+    retry::{ErrorKind, RetryConfig},
+};
+
+use super::clock::Clock;
+
+/// How long a `Retry-After` header value received at `now` asks the client to wait. The value is
+/// either a number of seconds or an HTTP date, `None` if it is neither.
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = DateTime::from_str(value.trim(), DateTimeFormat::HttpDate).ok()?;
+    let retry_at = SystemTime::try_from(retry_at).ok()?;
+    Some(retry_at.duration_since(now).unwrap_or_default())
+}
+
+// Makes the SDK retry throttled requests (503 and 429), which `RetryAfterPlugin` then delays by
+// at least as long as their `Retry-After` header asks
+#[derive(Debug, Default)]
+pub(crate) struct RetryAfterClassifier;
+
+impl ClassifyRetry for RetryAfterClassifier {
+    fn classify_retry(&self, ctx: &InterceptorContext) -> RetryAction {
+        match ctx.response().map(|response| response.status().as_u16()) {
+            Some(429 | 503) => RetryAction::retryable_error(ErrorKind::ThrottlingError),
+            _ => RetryAction::NoActionIndicated,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Retry-After"
+    }
+
+    fn priority(&self) -> RetryClassifierPriority {
+        RetryClassifierPriority::run_after(RetryClassifierPriority::transient_error_classifier())
+    }
+}
+
+// Wraps the SDK's retry strategy so that a retry waits for the longer of its backoff and the
+// `Retry-After` header of the failed response, capped at the retry config's `max_backoff`
+#[derive(Debug, Default)]
+pub(crate) struct RetryAfterPlugin;
+
+impl RuntimePlugin for RetryAfterPlugin {
+    fn order(&self) -> Order {
+        Order::NestedComponents
+    }
+
+    fn runtime_components(
+        &self,
+        current_components: &RuntimeComponentsBuilder,
+    ) -> Cow<'_, RuntimeComponentsBuilder> {
+        let mut components = RuntimeComponentsBuilder::new("RetryAfterPlugin");
+        if let Some(inner) = current_components.retry_strategy() {
+            components = components.with_retry_strategy(Some(RetryAfterStrategy { inner }));
+        }
+        Cow::Owned(components)
+    }
+}
+
+#[derive(Debug)]
+struct RetryAfterStrategy {
+    inner: SharedRetryStrategy,
+}
+
+impl RetryStrategy for RetryAfterStrategy {
+    fn should_attempt_initial_request(
+        &self,
+        runtime_components: &RuntimeComponents,
+        cfg: &ConfigBag,
+    ) -> Result<ShouldAttempt, BoxError> {
+        self.inner
+            .should_attempt_initial_request(runtime_components, cfg)
+    }
+
+    fn should_attempt_retry(
+        &self,
+        ctx: &InterceptorContext,
+        runtime_components: &RuntimeComponents,
+        cfg: &ConfigBag,
+    ) -> Result<ShouldAttempt, BoxError> {
+        let should_attempt = self
+            .inner
+            .should_attempt_retry(ctx, runtime_components, cfg)?;
+        let ShouldAttempt::YesAfterDelay(backoff) = should_attempt else {
+            return Ok(should_attempt);
+        };
+
+        // The time source is the store's clock, see `ClockTimeSource`
+        let retry_after = ctx
+            .response()
+            .and_then(|response| response.headers().get("retry-after"))
+            .zip(runtime_components.time_source())
+            .and_then(|(value, time_source)| parse_retry_after(value, time_source.now()));
+        let max_backoff = cfg.load::<RetryConfig>().map(RetryConfig::max_backoff);
+        match retry_after {
+            Some(retry_after) => {
+                let retry_after = max_backoff.map_or(retry_after, |max| retry_after.min(max));
+                Ok(ShouldAttempt::YesAfterDelay(backoff.max(retry_after)))
+            }
+            None => Ok(should_attempt),
+        }
+    }
+}
+
+// Lets the SDK read the time from a driver's clock, e.g. to resolve `Retry-After` dates
+#[derive(Debug)]
+pub(crate) struct ClockTimeSource(pub(crate) Arc<dyn Clock>);
+
+impl TimeSource for ClockTimeSource {
+    fn now(&self) -> SystemTime {
+        self.0.now()
+    }
+}
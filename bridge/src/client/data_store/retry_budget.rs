@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+
+use aws_sdk_s3::{
+    config::{
+        interceptors::{BeforeTransmitInterceptorContextRef, FinalizerInterceptorContextRef},
+        ConfigBag, Intercept, RuntimeComponents,
+    },
+    error::BoxError,
+};
+use aws_smithy_runtime_api::client::retries::RequestAttempts;
+
+pub const DEFAULT_RETRY_BUDGET: u32 = 500;
+const RETRY_COST: u32 = 5;
+const SUCCESS_REFILL: u32 = 1;
+
+// A token bucket shared by every request of a client: each retry spends `RETRY_COST` tokens
+// and each successful attempt returns `SUCCESS_REFILL`. Once the bucket is empty, requests fail
+// after their first attempt instead of retrying, so a widespread outage doesn't turn into a
+// retry storm against an already struggling S3.
+#[derive(Debug)]
+pub struct RetryBudget {
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    capacity: u32,
+    tokens: u32,
+}
+
+impl RetryBudget {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            state: Mutex::new(BudgetState {
+                capacity,
+                tokens: capacity,
+            }),
+        }
+    }
+
+    /// Resizes the budget and refills it completely.
+    pub fn reset(&self, capacity: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.capacity = capacity;
+        state.tokens = capacity;
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.state.lock().unwrap().tokens
+    }
+
+    fn try_spend(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.tokens.checked_sub(RETRY_COST) {
+            Some(tokens) => {
+                state.tokens = tokens;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn refill(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.tokens = (state.tokens + SUCCESS_REFILL).min(state.capacity);
+    }
+}
+
+// Enforces a `RetryBudget` on the SDK's own retries
+#[derive(Debug)]
+pub(crate) struct RetryBudgetInterceptor {
+    budget: Arc<RetryBudget>,
+}
+
+impl RetryBudgetInterceptor {
+    pub(crate) fn new(budget: Arc<RetryBudget>) -> Self {
+        Self { budget }
+    }
+}
+
+impl Intercept for RetryBudgetInterceptor {
+    fn name(&self) -> &'static str {
+        "RetryBudgetInterceptor"
+    }
+
+    fn read_before_attempt(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let attempts = cfg
+            .load::<RequestAttempts>()
+            .map_or(1, |attempts| attempts.attempts());
+        if attempts > 1 && !self.budget.try_spend() {
+            return Err("Retry budget exhausted, failing fast instead of retrying".into());
+        }
+
+        Ok(())
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if matches!(context.output_or_error(), Some(Ok(_))) {
+            self.budget.refill();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::{err_to_string, DataStoreError};
+
+use super::base::DataStoreDriver;
+
+const SCHEMA_VERSION_FIELD: &str = "_schema_version";
+const DATA_FIELD: &str = "data";
+
+// How `upload_json` lays out the JSON it uploads
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    #[default]
+    Compact, // Without whitespace, object keys in the order serde_json keeps them in
+    // Canonical form, see `to_canonical_json`. Equal data uploads as the same bytes on every
+    // node, as content-addressed keys need.
+    Canonical,
+}
+
+// Upgrades an artifact's data from one schema version to the next
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+// Versions the schema of a JSON artifact type. Artifacts are stored in an envelope
+// `{ "_schema_version": N, "data": ... }` and migrated to the current version when read.
+// Artifacts written before envelopes existed are read as version 0.
+#[derive(Clone, Debug)]
+pub struct JsonSchema {
+    current_version: u32,
+    migrations: BTreeMap<u32, Migration>, // from version -> migration to version + 1
+}
+
+impl JsonSchema {
+    pub fn new(current_version: u32) -> Self {
+        Self {
+            current_version,
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the migration from `from_version` to `from_version + 1`.
+    pub fn with_migration(mut self, from_version: u32, migration: Migration) -> Self {
+        self.migrations.insert(from_version, migration);
+        self
+    }
+
+    pub fn wrap(&self, data: Value) -> Value {
+        json!({ SCHEMA_VERSION_FIELD: self.current_version, DATA_FIELD: data })
+    }
+
+    /// Extracts the data from an envelope and migrates it to the current version.
+    pub fn unwrap(&self, envelope: Value) -> Result<Value, String> {
+        let (mut version, mut data) = match envelope {
+            Value::Object(mut fields) if fields.contains_key(SCHEMA_VERSION_FIELD) => {
+                let version = fields
+                    .get(SCHEMA_VERSION_FIELD)
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| format!("Invalid {SCHEMA_VERSION_FIELD} field"))?;
+                let data = fields.remove(DATA_FIELD).unwrap_or(Value::Null);
+                (version as u32, data)
+            }
+            legacy => (0, legacy),
+        };
+
+        if version > self.current_version {
+            return Err(DataStoreError::UnsupportedSchemaVersion {
+                version,
+                supported: self.current_version,
+            }
+            .to_string());
+        }
+        while version < self.current_version {
+            let migration = self
+                .migrations
+                .get(&version)
+                .ok_or_else(|| format!("No migration from schema version {version}"))?;
+            data = migration(data)?;
+            version += 1;
+        }
+
+        Ok(data)
+    }
+}
+
+/// Serializes `value` without whitespace and with the keys of every object sorted by their UTF-8
+/// bytes, so equal values serialize to the same bytes whatever order their maps were built in.
+/// Numbers are written as serde_json writes them, which is deterministic too.
+pub fn to_canonical_json(value: &Value) -> String {
+    let mut json = String::new();
+    write_canonical(value, &mut json);
+    json
+}
+
+// serde_json sorts object keys unless its `preserve_order` feature is enabled, which any crate in
+// the build can do, so keys are sorted here
+fn write_canonical(value: &Value, json: &mut String) {
+    match value {
+        Value::Object(fields) => {
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            json.push('{');
+            for (i, (key, value)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                json.push_str(&Value::from(key.as_str()).to_string());
+                json.push(':');
+                write_canonical(value, json);
+            }
+            json.push('}');
+        }
+        Value::Array(items) => {
+            json.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_canonical(item, json);
+            }
+            json.push(']');
+        }
+        scalar => json.push_str(&scalar.to_string()),
+    }
+}
+
+/// Serializes `data` to JSON in `format` and uploads it, wrapped in a versioned envelope if
+/// `schema` is set.
+pub async fn upload_json<T: Serialize>(
+    driver: &dyn DataStoreDriver,
+    schema: Option<&JsonSchema>,
+    format: JsonFormat,
+    file_name: &str,
+    data: &T,
+    file_path: Option<&str>,
+) -> Result<usize, String> {
+    let mut value = serde_json::to_value(data).map_err(err_to_string)?;
+    if let Some(schema) = schema {
+        value = schema.wrap(value);
+    }
+
+    let json = match format {
+        JsonFormat::Compact => value.to_string(),
+        JsonFormat::Canonical => to_canonical_json(&value),
+    };
+    driver.upload_object(file_name, &json, file_path).await
+}
+
+/// Fetches and deserializes a JSON artifact, migrating it to the current version of `schema`.
+pub async fn fetch_json<T: DeserializeOwned>(
+    driver: &dyn DataStoreDriver,
+    schema: Option<&JsonSchema>,
+    file_name: &str,
+    file_path: Option<&str>,
+) -> Result<T, String> {
+    let json = driver.fetch_object(file_name, file_path).await?;
+    let mut value: Value = serde_json::from_str(&json).map_err(err_to_string)?;
+    if let Some(schema) = schema {
+        value = schema.unwrap(value)?;
+    }
+
+    serde_json::from_value(value).map_err(err_to_string)
+}
@@ -1,13 +1,13 @@
-use crate::{
-    error::err_to_string,
-    utils::{compress, decompress, DEFAULT_COMPRESSION_LEVEL},
-};
+use crate::error::err_to_string;
 
-use super::base::DataStoreDriver;
+use super::base::{DataStoreDriver, DriverConfig, FetchResult, StoreCapabilities};
+use super::format::check_encoded;
+use super::key::object_key;
 use async_trait::async_trait;
 use dotenv;
 use futures::TryStreamExt;
 use openssh_sftp_client::{
+    error::{Error as SftpError, SftpErrorKind},
     file::TokioCompatFile,
     openssh::{KnownHosts, Session as SshSession},
     Sftp as _Sftp,
@@ -34,6 +34,7 @@ struct SftpCredentials {
 
 pub struct Sftp {
     credentials: SftpCredentials,
+    config: DriverConfig,
 }
 
 impl Sftp {
@@ -63,7 +64,10 @@ impl Sftp {
         };
 
         match test_connection(&credentials).await {
-            Ok(_) => Some(Self { credentials }),
+            Ok(_) => Some(Self {
+                credentials,
+                config: DriverConfig::from_env(),
+            }),
             Err(err) => {
                 eprintln!("{err:?}");
                 None
@@ -71,6 +75,12 @@ impl Sftp {
         }
     }
 
+    /// Replaces the whole `DriverConfig` read from the environment.
+    pub fn with_config(mut self, config: DriverConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     async fn get_object(&self, key: &str, file_path: Option<&str>) -> Result<Vec<u8>, String> {
         let mut buffer: Vec<u8> = vec![];
 
@@ -165,6 +175,10 @@ impl Sftp {
 
 #[async_trait]
 impl DataStoreDriver for Sftp {
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities::RAW_READ
+    }
+
     async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
         match connect(&self.credentials).await {
             Ok(sftp) => {
@@ -179,8 +193,15 @@ impl DataStoreDriver for Sftp {
                         drop(read_dir);
                         drop(fs);
                         disconnect(sftp).await;
+                        self.config.cap_keys(&mut buffer, file_path)?;
                         Ok(buffer)
                     }
+                    // A directory that doesn't exist holds no objects
+                    Err(SftpError::SftpError(SftpErrorKind::NoSuchFile, _)) => {
+                        drop(fs);
+                        disconnect(sftp).await;
+                        Ok(vec![])
+                    }
                     Err(err) => {
                         drop(fs);
                         disconnect(sftp).await;
@@ -217,6 +238,7 @@ impl DataStoreDriver for Sftp {
         file_path: Option<&str>,
     ) -> Result<usize, String> {
         let size = contents.len();
+        self.config.check_upload(size)?;
 
         println!("Writing data file to {} (size: {})", file_name, size);
 
@@ -233,12 +255,15 @@ impl DataStoreDriver for Sftp {
         &self,
         file_name: &str,
         file_path: Option<&str>,
-    ) -> Result<(Vec<u8>, usize), String> {
+    ) -> Result<FetchResult, String> {
         let response = self.get_object(file_name, file_path).await;
         match response {
             Ok(buffer) => {
-                let size = buffer.len();
-                Ok((decompress(&buffer).map_err(err_to_string)?, size))
+                self.config
+                    .decode_fetched_or_refetch(&object_key(file_name, file_path), &buffer, || {
+                        self.get_object(file_name, file_path)
+                    })
+                    .await
             }
             Err(err) => Err(format!("Failed to get json file: {}", err)),
         }
@@ -250,9 +275,9 @@ impl DataStoreDriver for Sftp {
         contents: &Vec<u8>,
         file_path: Option<&str>,
     ) -> Result<usize, String> {
-        let compressed_data =
-            compress(contents, DEFAULT_COMPRESSION_LEVEL).map_err(err_to_string)?;
+        let compressed_data = self.config.encode_upload(contents, file_name, file_path)?;
         let size = compressed_data.len();
+        self.config.check_upload(size)?;
 
         println!("Writing data file to {} (size: {})", file_name, size);
 
@@ -264,6 +289,32 @@ impl DataStoreDriver for Sftp {
             Err(err) => Err(format!("Failed to save json file: {}", err)),
         }
     }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        self.get_object(file_name, file_path).await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        check_encoded(compressed).map_err(err_to_string)?;
+        let size = compressed.len();
+        self.config.check_upload(size)?;
+
+        println!("Writing data file to {} (size: {})", file_name, size);
+
+        match self.upload_object(file_name, compressed, file_path).await {
+            Ok(_) => Ok(size),
+            Err(err) => Err(format!("Failed to save json file: {}", err)),
+        }
+    }
 }
 
 async fn test_connection(credentials: &SftpCredentials) -> Result<(), String> {
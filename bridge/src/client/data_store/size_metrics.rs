@@ -0,0 +1,391 @@
+use std::{collections::HashMap, sync::Mutex, time::SystemTime};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use super::format::is_encoded;
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+/// Upper bounds of the histogram buckets in bytes, from 256 B to 1 GiB in steps of 4x. Sizes
+/// above the last bound are counted in one more overflow bucket.
+pub const SIZE_BUCKETS: [u64; 12] = [
+    1 << 8,
+    1 << 10,
+    1 << 12,
+    1 << 14,
+    1 << 16,
+    1 << 18,
+    1 << 20,
+    1 << 22,
+    1 << 24,
+    1 << 26,
+    1 << 28,
+    1 << 30,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transfer {
+    Upload,
+    Download,
+}
+
+// Distribution of object sizes over `SIZE_BUCKETS`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeHistogram {
+    pub counts: [u64; SIZE_BUCKETS.len() + 1], // The last bucket counts sizes above 1 GiB
+    pub sum: u64,                              // Total bytes of every recorded object
+}
+
+impl Default for SizeHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; SIZE_BUCKETS.len() + 1],
+            sum: 0,
+        }
+    }
+}
+
+impl SizeHistogram {
+    pub fn record(&mut self, size: u64) {
+        let bucket = SIZE_BUCKETS.partition_point(|&bound| bound < size);
+        self.counts[bucket] += 1;
+        self.sum += size;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The fraction of recorded objects in buckets up to the one bounded by `bound`, e.g.
+    /// `fraction_at_most(1024)` for the share of objects of at most 1 KiB. `bound` is rounded
+    /// down to a bucket bound.
+    pub fn fraction_at_most(&self, bound: u64) -> f64 {
+        let count = self.count();
+        if count == 0 {
+            return 0.0;
+        }
+
+        let buckets = SIZE_BUCKETS.partition_point(|&b| b <= bound);
+        self.counts[..buckets].iter().sum::<u64>() as f64 / count as f64
+    }
+}
+
+// Records the size of every object uploaded or downloaded through it in a histogram per
+// transfer direction and encoding, to inform compression and chunking settings. Compressed
+// objects are recorded with their stored size, plain objects with their length.
+pub struct SizeMetrics<D: DataStoreDriver> {
+    inner: D,
+    histograms: Mutex<HashMap<(Transfer, bool), SizeHistogram>>, // By direction and compressed
+}
+
+impl<D: DataStoreDriver> SizeMetrics<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            histograms: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// A snapshot of the sizes recorded so far for one direction and encoding.
+    pub fn histogram(&self, transfer: Transfer, compressed: bool) -> SizeHistogram {
+        self.histograms
+            .lock()
+            .unwrap()
+            .get(&(transfer, compressed))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn record<T>(
+        &self,
+        transfer: Transfer,
+        compressed: bool,
+        result: Result<T, String>,
+        size: impl FnOnce(&T) -> usize,
+    ) -> Result<T, String> {
+        if let Ok(value) = &result {
+            self.histograms
+                .lock()
+                .unwrap()
+                .entry((transfer, compressed))
+                .or_default()
+                .record(size(value) as u64);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for SizeMetrics<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.inner.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let result = self.inner.fetch_object(file_name, file_path).await;
+        self.record(Transfer::Download, false, result, String::len)
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.record(Transfer::Upload, false, result, |_| contents.len())
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let result = self
+            .inner
+            .fetch_compressed_object(file_name, file_path)
+            .await;
+        self.record(Transfer::Download, true, result, |fetched| {
+            fetched.stored_size
+        })
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.record(Transfer::Upload, true, result, |size| *size)
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        self.inner.object_exists(file_name, file_path).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        self.inner.prefix_size(file_path).await
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        self.inner.list_object_metadata(file_path).await
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        self.inner.list_recent(file_path, n).await
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let result = self.inner.fetch_with_etag(file_name, file_path).await;
+        self.record(Transfer::Download, false, result, |(contents, _)| {
+            contents.len()
+        })
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await;
+        self.record(Transfer::Upload, false, result, |_| contents.len())
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await;
+        self.record(Transfer::Upload, false, result, |_| contents.len())
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let outcome = self
+            .inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await?;
+        // Skipped retries didn't transfer anything
+        match outcome {
+            UploadOutcome::Uploaded(size) => {
+                self.record(Transfer::Upload, false, Ok(outcome), |_| size)
+            }
+            UploadOutcome::AlreadyApplied => Ok(outcome),
+        }
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .fetch_generation(file_name, file_path, generation)
+            .await;
+        self.record(Transfer::Download, false, result, String::len)
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.record(Transfer::Upload, false, result, |_| contents.len())
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.record(Transfer::Upload, true, result, |size| *size)
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.touch(file_name, file_path).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let result = self.inner.fetch_raw_object(file_name, file_path).await;
+        let compressed = result.as_ref().is_ok_and(|stored| is_encoded(stored));
+        self.record(Transfer::Download, compressed, result, Vec::len)
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        self.inner.fetch_range(file_name, file_path, range).await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        self.inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        self.inner.delete_object(file_name, file_path).await
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await;
+        self.record(Transfer::Upload, false, result, |size| *size)
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await;
+        self.record(Transfer::Upload, true, result, |size| *size)
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
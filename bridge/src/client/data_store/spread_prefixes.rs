@@ -0,0 +1,396 @@
+use std::time::SystemTime;
+
+use futures::future::try_join_all;
+use md5::{Digest, Md5};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RecentObjects, RetentionMode,
+    StoreCapabilities, UploadOutcome,
+};
+use super::key::{normalize_path, object_key, parse_key};
+use super::stats::StoreStats;
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// S3 partitions its request rate by key prefix, so a single hot prefix gets throttled with 503
+// `SlowDown` errors however well callers back off. This wrapper spreads the objects of every
+// file path over `shards` sub-prefixes picked by a hash of the file name, e.g.
+// `graphs/abc123.json` is stored as `graphs/3f/abc123.json`, which lets S3 split the load over
+// several partitions.
+//
+// Callers keep using unsharded names: reads and writes go to the object's shard, and
+// `list_objects` lists every shard of the file path and returns the keys with the shard segment
+// removed. Listings only see objects directly under the file path, not in deeper directories.
+// Objects written before spreading was enabled are moved into their shards with
+// `migrate_prefix`. Changing the number of shards moves objects between shards, so it needs the
+// same migration.
+pub struct SpreadPrefixes<D: DataStoreDriver> {
+    inner: D,
+    shards: u16,
+}
+
+impl<D: DataStoreDriver> SpreadPrefixes<D> {
+    /// Spreads objects over `shards` sub-prefixes, clamped to between 1 and 256.
+    pub fn new(inner: D, shards: u16) -> Self {
+        Self {
+            inner,
+            shards: shards.clamp(1, 256),
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn shard_names(&self) -> impl Iterator<Item = String> {
+        (0..self.shards).map(|shard| format!("{shard:02x}"))
+    }
+
+    fn shard_path(&self, file_name: &str, file_path: Option<&str>) -> String {
+        let digest = Md5::digest(file_name.as_bytes());
+        let shard = u16::from_be_bytes([digest[0], digest[1]]) % self.shards;
+        object_key(&format!("{shard:02x}"), file_path)
+    }
+
+    async fn list_shards(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let listings = try_join_all(self.shard_names().map(|shard| async move {
+            let shard_path = object_key(&shard, file_path);
+            self.inner.list_objects(Some(&shard_path)).await
+        }))
+        .await?;
+
+        Ok(listings
+            .into_iter()
+            .flatten()
+            .map(|key| unshard(&key))
+            .collect())
+    }
+}
+
+impl<D: DataStoreDriver + Send + Sync> SpreadPrefixes<D> {
+    /// Moves every unsharded object directly under `file_path` into its shard, returning how
+    /// many objects were moved. Safe to rerun after a partial failure.
+    pub async fn migrate_prefix(&self, file_path: Option<&str>) -> Result<usize, String> {
+        let directory = normalize_path(file_path);
+        let mut moved = 0;
+        for object in self.inner.list_object_metadata(file_path).await? {
+            let key = parse_key(&object.key);
+            if key.directory() != directory {
+                continue;
+            }
+
+            let shard_path = self.shard_path(key.file_name(), file_path);
+            self.inner
+                .copy_object(
+                    key.file_name(),
+                    file_path,
+                    key.file_name(),
+                    Some(&shard_path),
+                )
+                .await?;
+            self.inner.delete_object(key.file_name(), file_path).await?;
+            moved += 1;
+        }
+
+        Ok(moved)
+    }
+}
+
+// Drops the shard segment in front of the file name of a listed key
+fn unshard(key: &str) -> String {
+    let Some((directory, file_name)) = key.rsplit_once('/') else {
+        return key.to_string();
+    };
+    match directory.rsplit_once('/') {
+        Some((parent, _)) => format!("{parent}/{file_name}"),
+        None => file_name.to_string(),
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for SpreadPrefixes<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.inner.stats()
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.list_shards(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner.fetch_object(file_name, Some(&shard_path)).await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_object(file_name, contents, Some(&shard_path))
+            .await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .fetch_compressed_object(file_name, Some(&shard_path))
+            .await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_compressed_object(file_name, contents, Some(&shard_path))
+            .await
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner.object_exists(file_name, Some(&shard_path)).await
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        let sizes = try_join_all(self.shard_names().map(|shard| async move {
+            let shard_path = object_key(&shard, file_path);
+            self.inner.prefix_size(Some(&shard_path)).await
+        }))
+        .await?;
+
+        Ok(sizes.into_iter().sum())
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let listings = try_join_all(self.shard_names().map(|shard| async move {
+            let shard_path = object_key(&shard, file_path);
+            self.inner.list_object_metadata(Some(&shard_path)).await
+        }))
+        .await?;
+
+        Ok(listings
+            .into_iter()
+            .flatten()
+            .map(|object| ObjectMetadata {
+                key: unshard(&object.key),
+                ..object
+            })
+            .collect())
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        let listings = try_join_all(self.shard_names().map(|shard| async move {
+            let shard_path = object_key(&shard, file_path);
+            self.inner.list_recent(Some(&shard_path), n).await
+        }))
+        .await?;
+
+        let mut recent = RecentObjects::new(n);
+        for (key, last_modified) in listings.into_iter().flatten() {
+            recent.push(unshard(&key), last_modified);
+        }
+        Ok(recent.into_sorted())
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .fetch_with_etag(file_name, Some(&shard_path))
+            .await
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_if_etag_matches(file_name, contents, Some(&shard_path), etag)
+            .await
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_object_locked(file_name, contents, Some(&shard_path), retain_until, mode)
+            .await
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_object_idempotent(file_name, contents, Some(&shard_path), idempotency_key)
+            .await
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .fetch_generation(file_name, Some(&shard_path), generation)
+            .await
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_object_if_absent(file_name, contents, Some(&shard_path))
+            .await
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_compressed_object_if_absent(file_name, contents, Some(&shard_path))
+            .await
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner.touch(file_name, Some(&shard_path)).await
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .fetch_raw_object(file_name, Some(&shard_path))
+            .await
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .fetch_range(file_name, Some(&shard_path), range)
+            .await
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        let target_shard_path = self.shard_path(target_file_name, target_file_path);
+        self.inner
+            .copy_object(
+                file_name,
+                Some(&shard_path),
+                target_file_name,
+                Some(&target_shard_path),
+            )
+            .await
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner.delete_object(file_name, Some(&shard_path)).await
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_object_from_reader(file_name, reader, size_hint, Some(&shard_path))
+            .await
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let shard_path = self.shard_path(file_name, file_path);
+        self.inner
+            .upload_precompressed_object(file_name, compressed, Some(&shard_path))
+            .await
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
@@ -0,0 +1,425 @@
+use std::{
+    ops::{Add, AddAssign},
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use super::base::{
+    ByteRange, DataStoreDriver, FetchResult, ObjectMetadata, RetentionMode, StoreCapabilities,
+    UploadOutcome,
+};
+use async_trait::async_trait;
+use tokio::io::AsyncRead;
+
+// Point-in-time activity counters of a data store, as returned by `DataStoreDriver::stats`.
+// Every field counts since the store was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    pub gets: u64,    // Fetches and existence checks
+    pub puts: u64,    // Uploads, copies and touches
+    pub deletes: u64, // Object deletions
+    pub lists: u64,   // Listings and prefix totals
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub errors: u64, // Calls of any kind that failed
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+impl StoreStats {
+    /// The share of cached reads served from a cache wrapper, or `None` if there is none or it
+    /// wasn't read from yet.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let reads = self.cache_hits + self.cache_misses;
+        (reads > 0).then(|| self.cache_hits as f64 / reads as f64)
+    }
+}
+
+impl Add for StoreStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            gets: self.gets + rhs.gets,
+            puts: self.puts + rhs.puts,
+            deletes: self.deletes + rhs.deletes,
+            lists: self.lists + rhs.lists,
+            bytes_in: self.bytes_in + rhs.bytes_in,
+            bytes_out: self.bytes_out + rhs.bytes_out,
+            errors: self.errors + rhs.errors,
+            cache_hits: self.cache_hits + rhs.cache_hits,
+            cache_misses: self.cache_misses + rhs.cache_misses,
+        }
+    }
+}
+
+impl AddAssign for StoreStats {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+// Hit and miss counters of a cache wrapper, reported through its `stats`
+#[derive(Debug, Default)]
+pub struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounters {
+    pub fn record(&self, hit: bool) {
+        match hit {
+            true => self.hits.fetch_add(1, Ordering::Relaxed),
+            false => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn snapshot(&self) -> StoreStats {
+        StoreStats {
+            cache_hits: self.hits.load(Ordering::Relaxed),
+            cache_misses: self.misses.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Call {
+    Get,
+    Put,
+    Delete,
+    List,
+}
+
+// Counts every call made through it with atomics, so `stats` can be polled at any time, e.g. by
+// a debug endpoint, without a metrics backend. Its `stats` adds these counts to those reported
+// by the wrapped store, which include the hit rates of cache wrappers below it. Wrap the driver
+// itself so that cache hits, which never reach it, aren't counted as gets, and only use one per
+// stack, as each one counts the same calls again.
+pub struct CountingStore<D: DataStoreDriver> {
+    inner: D,
+    calls: [AtomicU64; 4], // By `Call`
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl<D: DataStoreDriver> CountingStore<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            calls: Default::default(),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    // `bytes` of a successful call count as downloaded for gets and uploaded for puts
+    fn record<T>(
+        &self,
+        call: Call,
+        result: Result<T, String>,
+        bytes: impl FnOnce(&T) -> usize,
+    ) -> Result<T, String> {
+        self.calls[call as usize].fetch_add(1, Ordering::Relaxed);
+        match &result {
+            Ok(value) => {
+                let counter = match call {
+                    Call::Get => &self.bytes_in,
+                    _ => &self.bytes_out,
+                };
+                counter.fetch_add(bytes(value) as u64, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<D: DataStoreDriver + Send + Sync> DataStoreDriver for CountingStore<D> {
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_key_length(&self) -> Option<usize> {
+        self.inner.max_key_length()
+    }
+
+    fn stats(&self) -> StoreStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let own = StoreStats {
+            gets: load(&self.calls[Call::Get as usize]),
+            puts: load(&self.calls[Call::Put as usize]),
+            deletes: load(&self.calls[Call::Delete as usize]),
+            lists: load(&self.calls[Call::List as usize]),
+            bytes_in: load(&self.bytes_in),
+            bytes_out: load(&self.bytes_out),
+            errors: load(&self.errors),
+            ..Default::default()
+        };
+        self.inner.stats() + own
+    }
+
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        let result = self.inner.list_objects(file_path).await;
+        self.record(Call::List, result, |_| 0)
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        let result = self.inner.fetch_object(file_name, file_path).await;
+        self.record(Call::Get, result, String::len)
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object(file_name, contents, file_path)
+            .await;
+        self.record(Call::Put, result, |size| *size)
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        let result = self
+            .inner
+            .fetch_compressed_object(file_name, file_path)
+            .await;
+        self.record(Call::Get, result, |fetched| fetched.stored_size)
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object(file_name, contents, file_path)
+            .await;
+        self.record(Call::Put, result, |size| *size)
+    }
+
+    async fn object_exists(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool, String> {
+        let result = self.inner.object_exists(file_name, file_path).await;
+        self.record(Call::Get, result, |_| 0)
+    }
+
+    async fn prefix_size(&self, file_path: Option<&str>) -> Result<u64, String> {
+        let result = self.inner.prefix_size(file_path).await;
+        self.record(Call::List, result, |_| 0)
+    }
+
+    async fn list_object_metadata(
+        &self,
+        file_path: Option<&str>,
+    ) -> Result<Vec<ObjectMetadata>, String> {
+        let result = self.inner.list_object_metadata(file_path).await;
+        self.record(Call::List, result, |_| 0)
+    }
+
+    async fn list_recent(
+        &self,
+        file_path: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<(String, SystemTime)>, String> {
+        let result = self.inner.list_recent(file_path, n).await;
+        self.record(Call::List, result, |_| 0)
+    }
+
+    async fn fetch_with_etag(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<(String, String), String> {
+        let result = self.inner.fetch_with_etag(file_name, file_path).await;
+        self.record(Call::Get, result, |(contents, _)| contents.len())
+    }
+
+    async fn upload_if_etag_matches(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        etag: &str,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .upload_if_etag_matches(file_name, contents, file_path, etag)
+            .await;
+        self.record(Call::Put, result, |_| contents.len())
+    }
+
+    async fn upload_object_locked(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        retain_until: SystemTime,
+        mode: RetentionMode,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_locked(file_name, contents, file_path, retain_until, mode)
+            .await;
+        self.record(Call::Put, result, |size| *size)
+    }
+
+    async fn upload_object_idempotent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<UploadOutcome, String> {
+        let result = self
+            .inner
+            .upload_object_idempotent(file_name, contents, file_path, idempotency_key)
+            .await;
+        self.record(Call::Put, result, |outcome| match outcome {
+            UploadOutcome::Uploaded(size) => *size,
+            UploadOutcome::AlreadyApplied => 0,
+        })
+    }
+
+    async fn fetch_generation(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        generation: u64,
+    ) -> Result<String, String> {
+        let result = self
+            .inner
+            .fetch_generation(file_name, file_path, generation)
+            .await;
+        self.record(Call::Get, result, String::len)
+    }
+
+    async fn upload_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.record(Call::Put, result, |size| *size)
+    }
+
+    async fn upload_compressed_object_if_absent(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_compressed_object_if_absent(file_name, contents, file_path)
+            .await;
+        self.record(Call::Put, result, |size| *size)
+    }
+
+    async fn touch(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.touch(file_name, file_path).await;
+        self.record(Call::Put, result, |_| 0)
+    }
+
+    async fn fetch_raw_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let result = self.inner.fetch_raw_object(file_name, file_path).await;
+        self.record(Call::Get, result, Vec::len)
+    }
+
+    async fn fetch_range(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        range: ByteRange,
+    ) -> Result<(Vec<u8>, u64), String> {
+        let result = self.inner.fetch_range(file_name, file_path, range).await;
+        self.record(Call::Get, result, |(data, _)| data.len())
+    }
+
+    async fn copy_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+        target_file_name: &str,
+        target_file_path: Option<&str>,
+    ) -> Result<(), String> {
+        let result = self
+            .inner
+            .copy_object(file_name, file_path, target_file_name, target_file_path)
+            .await;
+        self.record(Call::Put, result, |_| 0)
+    }
+
+    async fn delete_object(&self, file_name: &str, file_path: Option<&str>) -> Result<(), String> {
+        let result = self.inner.delete_object(file_name, file_path).await;
+        self.record(Call::Delete, result, |_| 0)
+    }
+
+    async fn upload_object_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        size_hint: Option<u64>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_object_from_reader(file_name, reader, size_hint, file_path)
+            .await;
+        self.record(Call::Put, result, |size| *size)
+    }
+
+    async fn upload_precompressed_object(
+        &self,
+        file_name: &str,
+        compressed: &[u8],
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        let result = self
+            .inner
+            .upload_precompressed_object(file_name, compressed, file_path)
+            .await;
+        self.record(Call::Put, result, |size| *size)
+    }
+
+    async fn shutdown(self) -> Result<(), String> {
+        self.inner.shutdown().await
+    }
+}
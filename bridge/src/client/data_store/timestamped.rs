@@ -0,0 +1,102 @@
+use std::future::Future;
+
+use crate::error::DataStoreError;
+
+use super::base::{DataStoreDriver, StoreCapabilities};
+use super::key::object_key;
+
+/// Attempts at finding a free name, each with a fresh random part, before giving up.
+pub const MAX_NAME_ATTEMPTS: usize = 5;
+
+/// The name of a file written at `timestamp` (Unix time in milliseconds), e.g.
+/// `1700000000000-3fa85f64-bridge-client-data.json`. The random part keeps writes in the same
+/// millisecond apart, while names still sort by time.
+pub fn timestamped_file_name(timestamp: u128, suffix: &str) -> String {
+    format!("{timestamp}-{:08x}{suffix}", rand::random::<u32>())
+}
+
+/// Uploads `contents` under a fresh timestamped name and returns the name. On stores with
+/// conditional writes the upload never replaces an existing object, a name that is already
+/// taken is retried with another random part.
+pub async fn upload_timestamped(
+    driver: &dyn DataStoreDriver,
+    timestamp: u128,
+    suffix: &str,
+    contents: &str,
+    file_path: Option<&str>,
+) -> Result<String, String> {
+    let conditional = driver
+        .capabilities()
+        .contains(StoreCapabilities::CONDITIONAL_WRITES);
+    let next_name = || timestamped_file_name(timestamp, suffix);
+    upload_with_free_name(next_name, file_path, |file_name| async move {
+        match conditional {
+            true => driver
+                .upload_object_if_absent(&file_name, contents, file_path)
+                .await
+                .map(|_| file_name),
+            false => driver
+                .upload_object(&file_name, contents, file_path)
+                .await
+                .map(|_| file_name),
+        }
+    })
+    .await
+}
+
+/// Like `upload_timestamped`, but uploads `contents` as a compressed object. Returns the name
+/// and the stored size.
+pub async fn upload_compressed_timestamped(
+    driver: &dyn DataStoreDriver,
+    timestamp: u128,
+    suffix: &str,
+    contents: &Vec<u8>,
+    file_path: Option<&str>,
+) -> Result<(String, usize), String> {
+    let conditional = driver
+        .capabilities()
+        .contains(StoreCapabilities::CONDITIONAL_WRITES);
+    let next_name = || timestamped_file_name(timestamp, suffix);
+    upload_with_free_name(next_name, file_path, |file_name| async move {
+        let size = match conditional {
+            true => {
+                driver
+                    .upload_compressed_object_if_absent(&file_name, contents, file_path)
+                    .await?
+            }
+            false => {
+                driver
+                    .upload_compressed_object(&file_name, contents, file_path)
+                    .await?
+            }
+        };
+        Ok((file_name, size))
+    })
+    .await
+}
+
+/// Runs `upload` with names from `next_name` until it doesn't fail with `AlreadyExists`, at most
+/// `MAX_NAME_ATTEMPTS` times. `upload_timestamped` and `upload_compressed_timestamped` draw
+/// names from `timestamped_file_name`.
+pub async fn upload_with_free_name<T, N, F, Fut>(
+    mut next_name: N,
+    file_path: Option<&str>,
+    upload: F,
+) -> Result<T, String>
+where
+    N: FnMut() -> String,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let mut last_err = String::new();
+    for _ in 0..MAX_NAME_ATTEMPTS {
+        let file_name = next_name();
+        let taken = DataStoreError::AlreadyExists(object_key(&file_name, file_path)).to_string();
+        match upload(file_name).await {
+            Err(err) if err == taken => last_err = err,
+            result => return result,
+        }
+    }
+
+    Err(last_err)
+}
@@ -15,7 +15,8 @@ use crate::{
     transactions::base::Input,
     utils::{
         cleanup_cache_files, compress, decompress, read_disk_cache,
-        remove_script_and_control_block_from_witness, write_disk_cache,
+        remove_script_and_control_block_from_witness, write_disk_cache, DEFAULT_COMPRESSION_LEVEL,
+        ZSTD_AVAILABLE,
     },
 };
 use bitcoin::{
@@ -46,7 +47,6 @@ use bitvm::{
     // },
     signatures::{signing_winternitz::WinternitzPublicKey, winternitz},
 };
-use zstd::DEFAULT_COMPRESSION_LEVEL;
 
 // Specialized for assert leaves currently.
 pub type LockScript = fn(index: u32) -> ScriptBuf;
@@ -150,24 +150,33 @@ impl ConnectorC {
 
     pub fn taproot_script_and_control_block(&self, leaf_index: usize) -> (ScriptBuf, ControlBlock) {
         let cache_id = lock_script_cache_id(&self.commitment_public_keys, leaf_index);
-        let cache = TAPROOT_LOCK_SCRIPTS_CACHE
+        let cached = TAPROOT_LOCK_SCRIPTS_CACHE
             .write()
             .unwrap()
-            .get_or_put(cache_id, || {
+            .try_get_or_insert(cache_id, || {
                 let (script, control_block) = generate_script_and_control_block(
                     self.operator_taproot_public_key,
                     &self.lock_scripts_bytes(),
                     leaf_index,
                 );
                 let encoded_data = bitcode::encode(script.as_bytes());
-                let compressed_data = compress(&encoded_data, DEFAULT_COMPRESSION_LEVEL)
-                    .expect("Unable to compress script for caching");
-                LockScriptCacheEntry {
-                    control_block,
-                    encoded_script: compressed_data,
+                match compress(&encoded_data, DEFAULT_COMPRESSION_LEVEL) {
+                    Ok(compressed_data) => Ok(LockScriptCacheEntry {
+                        control_block,
+                        encoded_script: compressed_data,
+                    }),
+                    // E.g. in builds without zstd, the script is then used without caching it
+                    Err(err) => {
+                        eprintln!("Failed to compress script for caching: {}", err);
+                        Err((script, control_block))
+                    }
                 }
             })
-            .clone();
+            .cloned();
+        let cache = match cached {
+            Ok(cache) => cache,
+            Err(uncached) => return uncached,
+        };
         decompress(&cache.encoded_script)
             .ok()
             .map(|data| (data, cache.control_block))
@@ -196,6 +205,12 @@ impl ConnectorC {
     }
 
     fn lock_scripts_bytes(&self) -> Vec<Vec<u8>> {
+        // The disk cache is compressed, which builds without zstd can't read or write
+        if !ZSTD_AVAILABLE {
+            eprintln!("Compression is unavailable, lock scripts are not cached on disk");
+            return generate_assert_leaves(&self.commitment_public_keys);
+        }
+
         let cache_id = spend_info_cache_id(&self.commitment_public_keys);
         let file_path = get_lock_scripts_cache_path(&cache_id);
         let lock_scripts_bytes = read_disk_cache(&file_path)
@@ -60,6 +60,140 @@ pub enum ChunkerError {
     ValidProof,
 }
 
+#[derive(Debug)]
+pub enum DataStoreError {
+    ReadOnly,
+    IntegrityError(String), // String: the object key
+    NotFound(String),       // String: the object key
+    TooLarge { size: usize, max: usize },
+    KeyTooLong { len: usize, max: usize }, // In bytes
+    BucketNotFound(String),                // String: the bucket name
+    Unsupported(&'static str),             // str: the operation name
+    PreconditionFailed(String),            // String: the object key
+    AlreadyExists(String),                 // String: the object key
+    TooManyKeys { prefix: String, max: usize },
+    UnsupportedSchemaVersion { version: u32, supported: u32 },
+    AlreadyCompressed(String),            // String: the object key
+    SlowDown(String),                     // String: the object key
+    VersioningDisabled(String),           // String: the bucket name
+    AccessDenied(String),                 // String: the bucket name
+    Unavailable(String),                  // String: the object key
+    CompressionUnavailable(&'static str), // str: the codec name
+    Locked(String),                       // String: the object key
+    PolicyViolation(String),              // String: the object key or list prefix
+    DecryptionFailed(String),             // String: the object key
+    CorruptObject { key: String, checksum: String }, // checksum: SHA-256 of the stored bytes
+    UnknownEncryptionKey { key: String, key_id: String },
+    ClockSkew { skew: i64 }, // i64: seconds the local clock is ahead of the store (negative if behind)
+}
+
+impl fmt::Display for DataStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataStoreError::ReadOnly => write!(f, "Data store is read-only"),
+            DataStoreError::NotFound(key) => write!(f, "Object {key} not found"),
+            DataStoreError::BucketNotFound(bucket) => write!(
+                f,
+                "Bucket {bucket} not found, check the configured bucket name"
+            ),
+            DataStoreError::PreconditionFailed(key) => write!(
+                f,
+                "Object {key} was modified concurrently, its etag no longer matches"
+            ),
+            DataStoreError::AlreadyExists(key) => write!(f, "Object {key} already exists"),
+            DataStoreError::TooManyKeys { prefix, max } => write!(
+                f,
+                "Listing of {prefix:?} exceeds the maximum of {max} keys, list a narrower prefix"
+            ),
+            DataStoreError::UnsupportedSchemaVersion { version, supported } => write!(
+                f,
+                "Artifact has schema version {version}, this build only supports up to {supported}"
+            ),
+            DataStoreError::AlreadyCompressed(key) => write!(
+                f,
+                "Contents of {key} are already compressed and would be compressed twice, upload them with upload_precompressed_object instead"
+            ),
+            DataStoreError::Unavailable(key) => write!(
+                f,
+                "Request for {key} failed with a timeout or server error, the data store is unavailable"
+            ),
+            DataStoreError::AccessDenied(bucket) => write!(
+                f,
+                "Access to bucket {bucket} was denied, check that the credentials are valid and allowed to access it"
+            ),
+            DataStoreError::VersioningDisabled(bucket) => write!(
+                f,
+                "Versioning is not enabled on bucket {bucket}, overwritten objects can't be recovered"
+            ),
+            DataStoreError::SlowDown(key) => write!(
+                f,
+                "Request for {key} was throttled by the data store, spread hot prefixes with SpreadPrefixes if this persists"
+            ),
+            DataStoreError::ClockSkew { skew } => write!(
+                f,
+                "Request signature was rejected because the local clock is {}s {} the data store's clock, sync the system clock (e.g. enable NTP)",
+                skew.unsigned_abs(),
+                if *skew > 0 { "ahead of" } else { "behind" }
+            ),
+            DataStoreError::CompressionUnavailable(codec) => write!(
+                f,
+                "{codec} compression is not available, this build was compiled without the {codec} feature"
+            ),
+            DataStoreError::PolicyViolation(key) => write!(
+                f,
+                "Access to {key:?} is not allowed by the key policy of this data store"
+            ),
+            DataStoreError::CorruptObject { key, checksum } => write!(
+                f,
+                "Object {key} failed to decompress twice and is likely corrupt at rest, its stored bytes have checksum {checksum}"
+            ),
+            DataStoreError::DecryptionFailed(key) => write!(
+                f,
+                "Object {key} could not be decrypted, it was modified after it was encrypted"
+            ),
+            DataStoreError::UnknownEncryptionKey { key, key_id } => write!(
+                f,
+                "Object {key} is encrypted with key {key_id:?}, which is not in the keyring"
+            ),
+            DataStoreError::Locked(key) => write!(
+                f,
+                "Object {key} is under an object lock retention period and can't be deleted until it expires"
+            ),
+            DataStoreError::Unsupported(operation) => {
+                write!(f, "{operation} is not supported by this data store")
+            }
+            DataStoreError::TooLarge { size, max } => write!(
+                f,
+                "Upload of {size} bytes exceeds the maximum upload size of {max} bytes"
+            ),
+            DataStoreError::KeyTooLong { len, max } => write!(
+                f,
+                "Object key of {len} bytes exceeds the maximum key length of {max} bytes of this data store"
+            ),
+            DataStoreError::IntegrityError(key) => write!(
+                f,
+                "Upload of {key} was rejected because its body did not match its checksum"
+            ),
+        }
+    }
+}
+
+// Returned when some objects of an all-or-nothing batch could not be fetched
+#[derive(Debug)]
+pub struct BatchError {
+    pub failures: Vec<(String, String)>, // (object key, error message)
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to fetch {} object(s):", self.failures.len())?;
+        for (key, err) in &self.failures {
+            write!(f, "\n  {key}: {err}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     Esplora(esplora_client::Error),
@@ -69,6 +203,7 @@ pub enum Error {
     L2(L2Error),
     Chunker(ChunkerError),
     Validation(ValidationError),
+    DataStore(DataStoreError),
     Other(String),
 }
 
@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use bitcode::{Decode, Encode};
 use bitcoin::Network;
@@ -153,10 +156,199 @@ pub fn cleanup_cache_files(prefix: &str, cache_location: &Path, max_cache_files:
 
 pub const DEFAULT_COMPRESSION_LEVEL: i32 = 5;
 
-pub fn compress(data: &Vec<u8>, level: i32) -> std::io::Result<Vec<u8>> {
-    zstd::stream::encode_all(data.as_slice(), level)
+/// Whether this build can compress and decompress zstd, i.e. was compiled with the `zstd`
+/// feature. Without it the functions below fail with `DataStoreError::CompressionUnavailable`.
+pub const ZSTD_AVAILABLE: bool = cfg!(feature = "zstd");
+
+pub fn compress(data: &[u8], level: i32) -> std::io::Result<Vec<u8>> {
+    let mut compressed = vec![];
+    zstd_codec::compress_to(data, level, &mut compressed)?;
+    Ok(compressed)
+}
+
+/// Like `compress`, but splits the work over `threads` worker threads. The output is a regular
+/// zstd frame that `decompress` reads like any other, but unlike single threaded output it may
+/// differ between runs. One thread or less compresses exactly like `compress`.
+pub fn compress_multithreaded(data: &[u8], level: i32, threads: u32) -> std::io::Result<Vec<u8>> {
+    if threads <= 1 {
+        return compress(data, level);
+    }
+
+    let mut compressed = vec![];
+    zstd_codec::compress_multithreaded_to(data, level, threads, &mut compressed)?;
+    Ok(compressed)
+}
+
+pub fn decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decompressed = vec![];
+    zstd_codec::decompress_to(data, &mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Streams the decompressed contents of `data` into `writer`.
+pub fn decompress_to(data: &[u8], writer: &mut impl Write) -> std::io::Result<()> {
+    zstd_codec::decompress_to(data, writer)
+}
+
+// Discards everything written to it and only keeps count of the bytes
+#[derive(Default)]
+struct CountingSink {
+    count: usize,
+}
+
+impl Write for CountingSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.count += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the length `compress` would produce for `data` at `level`, without keeping the
+/// compressed output in memory, e.g. to check a storage quota before uploading a large artifact.
+pub fn estimate_compressed_size(data: &[u8], level: i32) -> Result<usize, String> {
+    let mut sink = CountingSink::default();
+    zstd_codec::compress_to(data, level, &mut sink)
+        .map_err(|err| format!("Failed to compress data: {err}"))?;
+
+    Ok(sink.count)
 }
 
-pub fn decompress(data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
-    zstd::stream::decode_all(data.as_slice())
+/// Like `compress`, but primes the compressor with a dictionary trained on similar data, which
+/// compresses small objects far better. The same dictionary is needed to decompress the output.
+pub fn compress_with_dictionary(
+    data: &[u8],
+    level: i32,
+    dictionary: &[u8],
+) -> std::io::Result<Vec<u8>> {
+    zstd_codec::compress_with_dictionary(data, level, dictionary)
+}
+
+pub fn decompress_with_dictionary(data: &[u8], dictionary: &[u8]) -> std::io::Result<Vec<u8>> {
+    zstd_codec::decompress_with_dictionary(data, dictionary)
+}
+
+/// Trains a dictionary of at most `max_size` bytes on `samples`.
+pub fn train_dictionary(samples: &[Vec<u8>], max_size: usize) -> std::io::Result<Vec<u8>> {
+    zstd_codec::train_dictionary(samples, max_size)
+}
+
+/// Id of a dictionary produced by `train_dictionary`.
+pub fn dictionary_id(dictionary: &[u8]) -> Option<u32> {
+    zstd_codec::dictionary_id(dictionary)
+}
+
+/// Id of the dictionary a compressed frame needs, or None if it was compressed without one.
+pub fn frame_dictionary_id(data: &[u8]) -> Option<u32> {
+    zstd_codec::frame_dictionary_id(data)
+}
+
+#[cfg(feature = "zstd")]
+mod zstd_codec {
+    use std::io::{Read, Write};
+
+    use zstd::zstd_safe::{get_dict_id_from_dict, get_dict_id_from_frame};
+
+    pub fn compress_to(data: &[u8], level: i32, writer: &mut impl Write) -> std::io::Result<()> {
+        zstd::stream::copy_encode(data, writer, level)
+    }
+
+    pub fn compress_multithreaded_to(
+        data: &[u8],
+        level: i32,
+        threads: u32,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let mut encoder = zstd::stream::Encoder::new(writer, level)?;
+        encoder.multithread(threads)?;
+        encoder.write_all(data)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    pub fn decompress_to(data: &[u8], writer: &mut impl Write) -> std::io::Result<()> {
+        zstd::stream::copy_decode(data, writer)
+    }
+
+    pub fn compress_with_dictionary(
+        data: &[u8],
+        level: i32,
+        dictionary: &[u8],
+    ) -> std::io::Result<Vec<u8>> {
+        zstd::bulk::Compressor::with_dictionary(level, dictionary)?.compress(data)
+    }
+
+    pub fn decompress_with_dictionary(data: &[u8], dictionary: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut decompressed = vec![];
+        zstd::stream::Decoder::with_dictionary(data, dictionary)?.read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    }
+
+    pub fn train_dictionary(samples: &[Vec<u8>], max_size: usize) -> std::io::Result<Vec<u8>> {
+        zstd::dict::from_samples(samples, max_size)
+    }
+
+    pub fn dictionary_id(dictionary: &[u8]) -> Option<u32> {
+        get_dict_id_from_dict(dictionary).map(|id| id.get())
+    }
+
+    pub fn frame_dictionary_id(data: &[u8]) -> Option<u32> {
+        get_dict_id_from_frame(data).map(|id| id.get())
+    }
+}
+
+// Builds without the `zstd` feature still compile against the same functions, but every codec
+// operation fails at runtime
+#[cfg(not(feature = "zstd"))]
+mod zstd_codec {
+    use std::io::{Error, ErrorKind, Write};
+
+    use crate::error::DataStoreError;
+
+    fn unavailable() -> Error {
+        Error::new(
+            ErrorKind::Unsupported,
+            DataStoreError::CompressionUnavailable("zstd").to_string(),
+        )
+    }
+
+    pub fn compress_to(_: &[u8], _: i32, _: &mut impl Write) -> std::io::Result<()> {
+        Err(unavailable())
+    }
+
+    pub fn compress_multithreaded_to(
+        _: &[u8],
+        _: i32,
+        _: u32,
+        _: &mut impl Write,
+    ) -> std::io::Result<()> {
+        Err(unavailable())
+    }
+
+    pub fn decompress_to(_: &[u8], _: &mut impl Write) -> std::io::Result<()> {
+        Err(unavailable())
+    }
+
+    pub fn compress_with_dictionary(_: &[u8], _: i32, _: &[u8]) -> std::io::Result<Vec<u8>> {
+        Err(unavailable())
+    }
+
+    pub fn decompress_with_dictionary(_: &[u8], _: &[u8]) -> std::io::Result<Vec<u8>> {
+        Err(unavailable())
+    }
+
+    pub fn train_dictionary(_: &[Vec<u8>], _: usize) -> std::io::Result<Vec<u8>> {
+        Err(unavailable())
+    }
+
+    pub fn dictionary_id(_: &[u8]) -> Option<u32> {
+        None
+    }
+
+    pub fn frame_dictionary_id(_: &[u8]) -> Option<u32> {
+        None
+    }
 }
@@ -0,0 +1,40 @@
+use std::{sync::Arc, time::Duration};
+
+use bridge::client::data_store::{
+    access_time::AccessTracked, base::DataStoreDriver, clock::MockClock, memory::MemoryStore,
+};
+
+const FILE_PATH: &str = "bridge_data/access_time";
+
+#[tokio::test]
+async fn test_cleanup_lru_keeps_most_recently_read() {
+    let clock = Arc::new(MockClock::default());
+    let store = AccessTracked::new(MemoryStore::new()).with_clock(clock.clone());
+
+    for file_name in ["a.json", "b.json", "c.json"] {
+        store
+            .upload_object(file_name, "{}", Some(FILE_PATH))
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(1));
+    }
+    store.fetch_object("a.json", Some(FILE_PATH)).await.unwrap();
+
+    assert_eq!(
+        store
+            .list_objects_by_access_time(Some(FILE_PATH))
+            .await
+            .unwrap(),
+        vec!["b.json", "c.json", "a.json"]
+    );
+
+    let deleted = store.cleanup_lru(Some(FILE_PATH), 2).await.unwrap();
+    assert_eq!(deleted, vec!["b.json"]);
+    assert_eq!(
+        store
+            .list_objects_by_access_time(Some(FILE_PATH))
+            .await
+            .unwrap(),
+        vec!["c.json", "a.json"]
+    );
+}
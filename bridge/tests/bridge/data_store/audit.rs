@@ -0,0 +1,60 @@
+use std::sync::{Arc, Mutex};
+
+use bridge::client::data_store::{
+    audit::{AuditEvent, AuditOp, AuditSink, Audited},
+    base::DataStoreDriver,
+    memory::MemoryStore,
+    read_only::ReadOnly,
+};
+
+const FILE_PATH: &str = "bridge_data/audit";
+
+#[derive(Default)]
+struct CollectingSink {
+    events: Mutex<Vec<AuditEvent>>,
+}
+
+impl AuditSink for CollectingSink {
+    fn record(&self, event: &AuditEvent) -> Result<(), String> {
+        self.events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_successful_upload_is_audited() {
+    let sink = Arc::new(CollectingSink::default());
+    let store = Audited::new(MemoryStore::new(), sink.clone(), "operator-1");
+
+    store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .fetch_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let events = sink.events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].op, AuditOp::Upload);
+    assert_eq!(events[0].key, format!("{FILE_PATH}/graph.json"));
+    assert_eq!(events[0].size, 2);
+    assert_eq!(events[0].actor, "operator-1");
+    assert!(events[0].result.is_ok());
+}
+
+#[tokio::test]
+async fn test_failed_upload_is_audited_with_error() {
+    let sink = Arc::new(CollectingSink::default());
+    let store = Audited::new(ReadOnly::new(MemoryStore::new()), sink.clone(), "verifier");
+
+    assert!(store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .is_err());
+
+    let events = sink.events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].result.is_err());
+}
@@ -0,0 +1,348 @@
+use bridge::{
+    client::data_store::{
+        aws_s3::{decode_listed_key, validate_region, AwsS3, PartSizing, MAX_PARTS, MIN_PART_SIZE},
+        base::{DataStoreDriver, StoreCapabilities},
+    },
+    error::DataStoreError,
+};
+
+use super::fake_s3::FakeS3;
+
+#[tokio::test]
+async fn test_anonymous_store_rejects_writes() {
+    let store = AwsS3::anonymous("us-east-1", "bitvm-public-artifacts");
+
+    assert!(!store.capabilities().contains(StoreCapabilities::DELETE));
+    assert_eq!(
+        store
+            .upload_object("graph.json", "{}", None)
+            .await
+            .unwrap_err(),
+        DataStoreError::Unsupported("upload_object").to_string()
+    );
+}
+
+#[test]
+fn test_retry_budget_resets_to_capacity() {
+    use bridge::client::data_store::retry_budget::RetryBudget;
+
+    let budget = RetryBudget::new(10);
+    assert_eq!(budget.remaining(), 10);
+    budget.reset(50);
+    assert_eq!(budget.remaining(), 50);
+}
+
+#[test]
+fn test_clock_skew_from_server_date() {
+    use bridge::client::data_store::aws_s3::clock_skew;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let server_date = "Wed, 21 Oct 2015 07:28:00 GMT";
+    let server_now = UNIX_EPOCH + Duration::from_secs(1445412480);
+
+    assert_eq!(clock_skew(server_date, server_now), Some(0));
+    assert_eq!(
+        clock_skew(server_date, server_now + Duration::from_secs(1200)),
+        Some(1200)
+    );
+    assert_eq!(
+        clock_skew(server_date, server_now - Duration::from_secs(1200)),
+        Some(-1200)
+    );
+    assert_eq!(clock_skew("yesterday", server_now), None);
+    assert!(DataStoreError::ClockSkew { skew: -1200 }
+        .to_string()
+        .contains("1200s behind"));
+}
+
+#[tokio::test]
+async fn test_wasabi_store_has_no_storage_classes() {
+    use aws_sdk_s3::types::StorageClass;
+    use std::time::Duration;
+
+    let store = AwsS3::for_wasabi("eu-central-1", "key", "secret", "bitvm-artifacts");
+
+    assert!(!store
+        .capabilities()
+        .contains(StoreCapabilities::STORAGE_CLASSES));
+    assert_eq!(
+        store
+            .archive_older_than(None, Duration::from_secs(60), StorageClass::Glacier)
+            .await
+            .unwrap_err(),
+        DataStoreError::Unsupported("archive_older_than").to_string()
+    );
+}
+
+#[test]
+fn test_retry_after_header_sets_wait() {
+    use bridge::client::data_store::retry_after::parse_retry_after;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let now = UNIX_EPOCH + Duration::from_secs(1445412480);
+
+    assert_eq!(parse_retry_after("3", now), Some(Duration::from_secs(3)));
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:05 GMT", now),
+        Some(Duration::from_secs(5))
+    );
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(parse_retry_after("soon", now), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_throttled_request_is_retried_after_retry_after() {
+    use bridge::client::data_store::{base::DriverConfig, clock::MockClock};
+    use std::{
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    let fake = FakeS3::new();
+    let clock = Arc::new(MockClock::new(UNIX_EPOCH + Duration::from_secs(1445412480)));
+    let store = fake.store().with_config(DriverConfig {
+        clock,
+        ..Default::default()
+    });
+    store.upload_object("graph.json", "{}", None).await.unwrap();
+
+    // Resolved against the store's clock, by the wall clock the date is long past
+    fake.throttle_next(1, Some("Wed, 21 Oct 2015 07:28:07 GMT"));
+    store.fetch_object("graph.json", None).await.unwrap();
+
+    let times = fake.request_times();
+    let [.., throttled, retried] = times[..] else {
+        panic!("expected a retry, got {} requests", times.len());
+    };
+    // The SDK's own backoff before the first retry is at most a second
+    let waited = retried - throttled;
+    assert!(
+        waited >= Duration::from_secs(7) && waited < Duration::from_secs(8),
+        "{waited:?}"
+    );
+}
+
+#[test]
+fn test_part_size_keeps_part_count_in_range() {
+    const MIB: u64 = 1024 * 1024;
+    let sizing = PartSizing::default();
+    let parts = |length: u64| length.div_ceil(sizing.part_size(Some(length)) as u64);
+
+    // Unknown lengths use the default part size
+    assert_eq!(sizing.part_size(None), sizing.default_part_size);
+    // Objects are split into at least `min_parts` parts, down to S3's minimum part size
+    assert_eq!(parts(200 * MIB), 25);
+    assert_eq!(parts(60 * MIB), 10);
+    assert_eq!(sizing.part_size(Some(20 * MIB)), MIN_PART_SIZE);
+    // Large objects take at most `max_parts` parts
+    assert_eq!(parts(100 * 1024 * MIB), 100);
+
+    let sizing = PartSizing {
+        max_parts: 1_000_000,
+        ..Default::default()
+    };
+    assert_eq!(
+        (5 * 1024 * 1024 * MIB).div_ceil(sizing.part_size(Some(5 * 1024 * 1024 * MIB)) as u64),
+        MAX_PARTS
+    );
+}
+
+#[test]
+fn test_validate_region() {
+    assert!(validate_region("eu-central-1", false).is_ok());
+    let err = validate_region("us-east-1a", false).unwrap_err();
+    assert!(err.contains("did you mean \"us-east-1\"?"), "{err}");
+    assert!(validate_region("us-central-1", false).is_err());
+
+    // S3-compatible backends name their regions freely
+    assert!(validate_region("garage", true).is_ok());
+}
+
+#[test]
+fn test_decode_listed_key() {
+    assert_eq!(
+        decode_listed_key("bridge_data/graph+%231/a%2Bb+%C3%BC.json").unwrap(),
+        "bridge_data/graph #1/a+b ü.json"
+    );
+    assert_eq!(decode_listed_key("plain.json").unwrap(), "plain.json");
+    assert!(decode_listed_key("truncated%2").is_err());
+    assert!(decode_listed_key("not-hex%zz").is_err());
+    assert!(decode_listed_key("invalid-utf8%ff").is_err());
+}
+
+#[tokio::test]
+async fn test_conflicting_write_under_file_path_is_retried() {
+    let fake = FakeS3::new();
+    let store = fake.store();
+
+    assert_eq!(
+        store
+            .upload_if_etag_matches("counter", "1", Some("bridge_data/counters"), "\"stale\"")
+            .await
+            .unwrap_err(),
+        DataStoreError::NotFound("bridge_data/counters/counter".to_string()).to_string()
+    );
+    store
+        .upload_object("counter", "1", Some("bridge_data/counters"))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .upload_if_etag_matches("counter", "2", Some("bridge_data/counters"), "\"stale\"")
+            .await
+            .unwrap_err(),
+        DataStoreError::PreconditionFailed("bridge_data/counters/counter".to_string()).to_string()
+    );
+
+    // Another writer wins the first attempt, so the update has to start over
+    fake.fail_next_conditional_writes(1);
+    assert_eq!(
+        store
+            .increment("counter", Some("bridge_data/counters"), 2)
+            .await
+            .unwrap(),
+        3
+    );
+    assert_eq!(fake.object("bridge_data/counters/counter").unwrap(), b"3");
+}
+
+#[tokio::test]
+async fn test_keys_with_special_characters_round_trip() {
+    let fake = FakeS3::new();
+    let store = fake.store();
+    let file_path = Some("bridge_data/graph #1");
+
+    store
+        .upload_object("a+b ü.json", "{\"n\":1}", file_path)
+        .await
+        .unwrap();
+    store
+        .copy_object("a+b ü.json", file_path, "copy of a+b ü.json", file_path)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.list_objects(file_path).await.unwrap(),
+        vec![
+            "bridge_data/graph #1/a+b ü.json",
+            "bridge_data/graph #1/copy of a+b ü.json"
+        ]
+    );
+    for file_name in ["a+b ü.json", "copy of a+b ü.json"] {
+        assert_eq!(
+            store.fetch_object(file_name, file_path).await.unwrap(),
+            "{\"n\":1}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_missing_object_is_not_found() {
+    let store = FakeS3::new().store();
+    let not_found = DataStoreError::NotFound("bridge_data/missing.json".to_string()).to_string();
+
+    assert_eq!(
+        store
+            .fetch_object("missing.json", Some("bridge_data"))
+            .await
+            .unwrap_err(),
+        not_found
+    );
+    assert_eq!(
+        store
+            .fetch_compressed_object("missing.json", Some("bridge_data"))
+            .await
+            .unwrap_err(),
+        not_found
+    );
+}
+
+#[tokio::test]
+async fn test_idempotent_upload_is_conditional() {
+    use bridge::client::data_store::base::{DriverConfig, UploadOutcome};
+
+    let fake = FakeS3::new();
+    let store = fake.store();
+    assert_eq!(
+        store
+            .upload_object_idempotent("graph.json", "{}", Some("bridge_data"), "first")
+            .await
+            .unwrap(),
+        UploadOutcome::Uploaded(2)
+    );
+
+    // Another writer replaced the object between the check and the upload
+    fake.fail_next_conditional_writes(1);
+    assert_eq!(
+        store
+            .upload_object_idempotent("graph.json", "{\"n\":1}", Some("bridge_data"), "second")
+            .await
+            .unwrap_err(),
+        DataStoreError::PreconditionFailed("bridge_data/graph.json".to_string()).to_string()
+    );
+
+    // Read-only stores fail before sending any request
+    let requests = fake.request_times().len();
+    let read_only = fake.store().with_config(DriverConfig {
+        read_only: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        read_only
+            .upload_object_idempotent("graph.json", "{}", Some("bridge_data"), "third")
+            .await
+            .unwrap_err(),
+        DataStoreError::ReadOnly.to_string()
+    );
+    assert_eq!(fake.request_times().len(), requests);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_stalled_copy_times_out() {
+    use bridge::client::data_store::aws_s3::Timeouts;
+    use std::time::Duration;
+
+    let fake = FakeS3::new();
+    let store = fake.store().with_timeouts(Timeouts {
+        copy: Duration::from_secs(5),
+        ..Default::default()
+    });
+    store
+        .upload_object("graph.json", "{}", Some("bridge_data"))
+        .await
+        .unwrap();
+
+    fake.stall_copies();
+    assert_eq!(
+        store
+            .copy_object(
+                "graph.json",
+                Some("bridge_data"),
+                "copy.json",
+                Some("bridge_data")
+            )
+            .await
+            .unwrap_err(),
+        DataStoreError::Unavailable("bridge_data/graph.json".to_string()).to_string()
+    );
+    assert!(fake.object("bridge_data/copy.json").is_none());
+}
+
+#[tokio::test]
+async fn test_upload_damaged_in_transit_is_integrity_error() {
+    let fake = FakeS3::new();
+    let store = fake.store();
+
+    fake.corrupt_next_uploads(1);
+    assert_eq!(
+        store
+            .upload_object("graph.json", "{}", Some("bridge_data"))
+            .await
+            .unwrap_err(),
+        DataStoreError::IntegrityError("bridge_data/graph.json".to_string()).to_string()
+    );
+    assert!(fake.object("bridge_data/graph.json").is_none());
+}
@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use bridge::{
+    client::data_store::{
+        base::{CompressionOverride, DataStoreDriver, FetchResult},
+        bulk_fetch::fetch_prefix_stream,
+        key_policy::{KeyPolicy, KeyPolicyStore},
+        memory::MemoryStore,
+    },
+    utils::{compress, DEFAULT_COMPRESSION_LEVEL},
+};
+use futures::StreamExt;
+
+const FILE_PATH: &str = "bridge_data/bulk_fetch";
+
+// Hides every capability of the memory store, like drivers that can't read raw objects
+struct WithoutRawReads(MemoryStore);
+
+#[async_trait]
+impl DataStoreDriver for WithoutRawReads {
+    async fn list_objects(&self, file_path: Option<&str>) -> Result<Vec<String>, String> {
+        self.0.list_objects(file_path).await
+    }
+
+    async fn fetch_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<String, String> {
+        self.0.fetch_object(file_name, file_path).await
+    }
+
+    async fn upload_object(
+        &self,
+        file_name: &str,
+        contents: &str,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.0.upload_object(file_name, contents, file_path).await
+    }
+
+    async fn fetch_compressed_object(
+        &self,
+        file_name: &str,
+        file_path: Option<&str>,
+    ) -> Result<FetchResult, String> {
+        self.0.fetch_compressed_object(file_name, file_path).await
+    }
+
+    async fn upload_compressed_object(
+        &self,
+        file_name: &str,
+        contents: &Vec<u8>,
+        file_path: Option<&str>,
+    ) -> Result<usize, String> {
+        self.0
+            .upload_compressed_object(file_name, contents, file_path)
+            .await
+    }
+}
+
+// A prefix as left behind by a partial migration to compressed uploads
+async fn mixed_prefix() -> (MemoryStore, Vec<(String, Vec<u8>)>) {
+    let store = MemoryStore::new();
+    let contents = |name: &str| format!("{{\"artifact\":\"{name}\"}}").repeat(50);
+    store
+        .upload_object("plain.json", &contents("plain"), Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_compressed_object(
+            "compressed.json",
+            &contents("compressed").into_bytes(),
+            Some(FILE_PATH),
+        )
+        .await
+        .unwrap();
+    store
+        .upload_compressed_object("tiny.json", &b"{}".to_vec(), Some(FILE_PATH))
+        .await
+        .unwrap();
+    // Written before compressed objects got a format header
+    let legacy = compress(contents("legacy").as_bytes(), DEFAULT_COMPRESSION_LEVEL).unwrap();
+    store
+        .upload_precompressed_object("legacy.json", &legacy, Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let mut expected = vec![
+        ("plain.json", contents("plain").into_bytes()),
+        ("compressed.json", contents("compressed").into_bytes()),
+        ("tiny.json", b"{}".to_vec()),
+        ("legacy.json", contents("legacy").into_bytes()),
+    ]
+    .into_iter()
+    .map(|(file_name, contents)| (format!("{FILE_PATH}/{file_name}"), contents))
+    .collect::<Vec<_>>();
+    expected.sort();
+
+    (store, expected)
+}
+
+#[tokio::test]
+async fn test_fetch_prefix_stream_fetches_every_object() {
+    let store = MemoryStore::new();
+    for i in 0..20u8 {
+        let compression = match i % 2 {
+            0 => CompressionOverride::Compressed,
+            _ => CompressionOverride::Raw,
+        };
+        store
+            .upload(
+                &format!("{i}.json"),
+                &[b'0' + i % 10; 512],
+                Some(FILE_PATH),
+                compression,
+            )
+            .await
+            .unwrap();
+    }
+    store
+        .upload_object("other.json", "{}", Some("bridge_data/other"))
+        .await
+        .unwrap();
+
+    let mut objects: Vec<(String, Vec<u8>)> = fetch_prefix_stream(&store, Some(FILE_PATH), 4)
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    objects.sort();
+    assert_eq!(objects.len(), 20);
+    assert_eq!(objects[0], (format!("{FILE_PATH}/0.json"), vec![b'0'; 512]));
+    assert_eq!(objects[1], (format!("{FILE_PATH}/1.json"), vec![b'1'; 512]));
+}
+
+#[tokio::test]
+async fn test_fetch_prefix_stream_returns_listing_error() {
+    let policy = KeyPolicy::parse("allow:bridge_data/other/").unwrap();
+    let store = KeyPolicyStore::new(MemoryStore::new(), policy);
+
+    let results: Vec<_> = fetch_prefix_stream(&store, Some(FILE_PATH), 4)
+        .collect()
+        .await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+}
+
+#[tokio::test]
+async fn test_fetch_prefix_stream_decodes_mixed_objects() {
+    let (store, expected) = mixed_prefix().await;
+    let mut objects: Vec<_> = fetch_prefix_stream(&store, Some(FILE_PATH), 2)
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    objects.sort();
+    assert_eq!(objects, expected);
+
+    let store = WithoutRawReads(store);
+    let mut objects: Vec<_> = fetch_prefix_stream(&store, Some(FILE_PATH), 2)
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    objects.sort();
+    assert_eq!(objects, expected);
+}
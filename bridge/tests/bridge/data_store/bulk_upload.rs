@@ -0,0 +1,47 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use bridge::client::data_store::{
+    base::DataStoreDriver, bulk_upload::upload_stream, memory::MemoryStore,
+};
+use futures::{stream, StreamExt};
+
+const FILE_PATH: &str = "bridge_data/bulk_upload";
+
+#[tokio::test]
+async fn test_upload_stream_uploads_every_item() {
+    let store = MemoryStore::new();
+    let items = stream::iter((0..20).map(|i| (format!("{i}.bin"), vec![i as u8; 1024])));
+
+    let results: Vec<_> = upload_stream(&store, items, 4, Some(FILE_PATH))
+        .collect()
+        .await;
+    assert_eq!(results.len(), 20);
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+    let fetched = store
+        .fetch_compressed_object("7.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(fetched.data, vec![7u8; 1024]);
+}
+
+#[tokio::test]
+async fn test_upload_stream_pulls_items_on_demand() {
+    let store = MemoryStore::new();
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let counter = pulled.clone();
+    let items = stream::iter(0..100).map(move |i| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        (format!("{i}.bin"), vec![0u8; 16])
+    });
+
+    let mut results = upload_stream(&store, items, 4, Some(FILE_PATH));
+    results.next().await.unwrap().1.unwrap();
+    assert!(pulled.load(Ordering::SeqCst) <= 4);
+
+    assert_eq!(results.count().await, 99);
+    assert_eq!(pulled.load(Ordering::SeqCst), 100);
+}
@@ -0,0 +1,61 @@
+use std::{sync::Arc, time::Duration};
+
+use bridge::client::data_store::{
+    base::DataStoreDriver, cached_listing::CachedListing, clock::MockClock, memory::MemoryStore,
+};
+
+const FILE_PATH: &str = "bridge_data/cached_listing";
+
+#[tokio::test]
+async fn test_listing_is_cached_until_ttl_expires() {
+    let clock = Arc::new(MockClock::default());
+    let store =
+        CachedListing::new(MemoryStore::new(), Duration::from_secs(10)).with_clock(clock.clone());
+    store
+        .upload_object("first.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 1);
+
+    // Written behind the wrapper's back, so the cached listing doesn't know about it yet
+    store
+        .inner()
+        .upload_object("second.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 1);
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_writes_invalidate_cached_listings() {
+    let store = CachedListing::new(MemoryStore::new(), Duration::from_secs(60));
+    store
+        .upload_object("first.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 1);
+    assert_eq!(
+        store.list_objects(Some("bridge_data")).await.unwrap().len(),
+        1
+    );
+
+    store
+        .upload_object("second.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 2);
+    assert_eq!(
+        store.list_objects(Some("bridge_data")).await.unwrap().len(),
+        2
+    );
+
+    store
+        .delete_object("first.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 1);
+}
@@ -0,0 +1,113 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver, cached_store::CachedStore, memory::MemoryStore,
+};
+
+const FILE_PATH: &str = "bridge_data/cached_store";
+
+#[tokio::test]
+async fn test_fetches_are_served_from_cache() {
+    let store = CachedStore::new(MemoryStore::new(), 1);
+    store
+        .inner()
+        .upload_object("a.json", "a", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .inner()
+        .upload_object("b.json", "b", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.fetch_object("a.json", Some(FILE_PATH)).await.unwrap(),
+        "a"
+    );
+    store
+        .inner()
+        .delete_object("a.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.fetch_object("a.json", Some(FILE_PATH)).await.unwrap(),
+        "a"
+    );
+
+    // Fetching another object evicts the least recently used one
+    assert_eq!(
+        store.fetch_object("b.json", Some(FILE_PATH)).await.unwrap(),
+        "b"
+    );
+    assert!(store.fetch_object("a.json", Some(FILE_PATH)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_writes_invalidate_cached_objects() {
+    let store = CachedStore::new(MemoryStore::new(), 10);
+    store
+        .upload_object("graph.json", "old", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .fetch_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    store
+        .upload_object("graph.json", "new", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "new"
+    );
+
+    store
+        .delete_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .fetch_object("graph.json", Some(FILE_PATH))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_populate_on_write_serves_reads_after_writes_from_cache() {
+    let store = CachedStore::new(MemoryStore::new(), 10).with_populate_on_write(true);
+    store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    // Removing the object from the inner store shows the fetch never reaches it
+    store
+        .inner()
+        .delete_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+
+    let store = CachedStore::new(MemoryStore::new(), 10);
+    store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .inner()
+        .delete_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .fetch_object("graph.json", Some(FILE_PATH))
+        .await
+        .is_err());
+}
@@ -0,0 +1,109 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver, chunked::Chunked, integrity::HashAlgorithm, memory::MemoryStore,
+};
+use rand::{distributions::Alphanumeric, Rng};
+
+const FILE_PATH: &str = "bridge_data/chunked";
+
+fn random_json(size: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(size)
+        .map(char::from)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_chunked_upload_round_trip_and_dedup() {
+    let store = Chunked::new(MemoryStore::new());
+    let graph = random_json(1024 * 1024);
+
+    let first_size = store
+        .upload_object_chunked("graph-v1.json", &graph, Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("graph-v1.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        graph
+    );
+
+    // A new version that only appends data reuses all but the last chunks
+    let graph_v2 = format!("{graph}{}", random_json(1024));
+    let second_size = store
+        .upload_object_chunked("graph-v2.json", &graph_v2, Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(second_size < first_size / 2);
+    assert_eq!(
+        store
+            .fetch_object("graph-v2.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        graph_v2
+    );
+}
+
+#[tokio::test]
+async fn test_fetch_reassembles_chunked_objects() {
+    let store = Chunked::new(MemoryStore::new());
+    let graph = random_json(256 * 1024);
+    store
+        .upload_object_chunked("graph.json", &graph, Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.fetch("graph.json", Some(FILE_PATH)).await.unwrap(),
+        graph.into_bytes()
+    );
+}
+
+#[tokio::test]
+async fn test_plain_objects_pass_through() {
+    let store = Chunked::new(MemoryStore::new());
+    store
+        .upload_object("small.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .fetch_object("small.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+}
+
+#[tokio::test]
+async fn test_chunks_are_named_by_their_hash_algorithm() {
+    let store = Chunked::new(MemoryStore::new());
+    let graph = random_json(256 * 1024);
+    store
+        .upload_object_chunked("graph-v1.json", &graph, Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    // Switching algorithms keeps reading manifests written with the previous one
+    let store = Chunked::new(store.into_inner()).with_hash_algorithm(HashAlgorithm::Blake3);
+    store
+        .upload_object_chunked("graph-v2.json", &graph, Some(FILE_PATH))
+        .await
+        .unwrap();
+    for file_name in ["graph-v1.json", "graph-v2.json"] {
+        assert_eq!(
+            store
+                .fetch_object(file_name, Some(FILE_PATH))
+                .await
+                .unwrap(),
+            graph
+        );
+    }
+
+    let chunks = store.list_objects(Some("chunks")).await.unwrap();
+    assert!(chunks.iter().any(|key| key.starts_with("chunks/blake3-")));
+    assert!(chunks.iter().any(|key| !key.contains('-')));
+}
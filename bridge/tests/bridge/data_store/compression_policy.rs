@@ -0,0 +1,104 @@
+use bridge::client::data_store::{
+    clock::{MockClock, SystemClock},
+    compression_policy::{CompressionPolicy, CompressionRule},
+    compression_tuner::{CompressionTuner, CANDIDATE_LEVELS, SAMPLE_UPLOADS},
+    format::{decode_object, DEFAULT_MIN_COMPRESS_SIZE},
+};
+
+#[test]
+fn test_policy_is_parsed_from_spec() {
+    let policy =
+        CompressionPolicy::parse("suffix:.bin=skip, kind:peg_out=19, kind:peg_in=auto").unwrap();
+
+    assert_eq!(
+        policy,
+        CompressionPolicy::new()
+            .with_suffix(".bin", CompressionRule::Skip)
+            .with_artifact_kind("peg_out", CompressionRule::Compress { level: 19 })
+            .with_artifact_kind("peg_in", CompressionRule::Auto)
+    );
+    assert!(CompressionPolicy::parse("suffix:.bin").is_err());
+    assert!(CompressionPolicy::parse("prefix:graphs=skip").is_err());
+    assert!(CompressionPolicy::parse("kind:peg_out=high").is_err());
+}
+
+#[test]
+fn test_suffix_rules_win_over_artifact_kind_rules() {
+    let policy = CompressionPolicy::new()
+        .with_suffix(".bin", CompressionRule::Compress { level: 1 })
+        .with_suffix(".proof.bin", CompressionRule::Skip)
+        .with_artifact_kind("peg_out", CompressionRule::Compress { level: 19 });
+
+    assert_eq!(
+        policy.rule_for("attempt_4.proof.bin", Some("graphs/abc123/peg_out")),
+        Some(CompressionRule::Skip)
+    );
+    assert_eq!(
+        policy.rule_for("attempt_4.bin", Some("graphs/abc123/peg_out")),
+        Some(CompressionRule::Compress { level: 1 })
+    );
+    assert_eq!(
+        policy.rule_for("attempt_4.json", Some("graphs/abc123/peg_out")),
+        Some(CompressionRule::Compress { level: 19 })
+    );
+    assert_eq!(
+        policy.rule_for("attempt_4.json", Some("graphs/abc123/peg_in")),
+        None
+    );
+}
+
+#[test]
+fn test_tuner_settles_on_a_level_per_artifact_type() {
+    let tuner = CompressionTuner::new();
+    let contents = "{\"graph\":\"abc123\"}".repeat(1000).into_bytes();
+    let file_path = Some("graphs/abc123/peg_out");
+
+    for i in 0..SAMPLE_UPLOADS {
+        assert_eq!(tuner.learned_level("peg_out"), None);
+        let encoded = tuner
+            .encode(
+                &contents,
+                &format!("attempt_{i}.json"),
+                file_path,
+                DEFAULT_MIN_COMPRESS_SIZE,
+                &SystemClock,
+            )
+            .unwrap();
+        assert_eq!(decode_object(&encoded).unwrap(), contents);
+    }
+
+    let level = tuner.learned_level("peg_out").unwrap();
+    assert!(CANDIDATE_LEVELS.contains(&level));
+    assert_eq!(tuner.learned_levels().len(), 1);
+    assert_eq!(tuner.learned_level("peg_in"), None);
+    assert_eq!(
+        CompressionTuner::artifact_type("attempt_4.json", Some("graphs/abc123/peg_in")),
+        "peg_in"
+    );
+    assert_eq!(CompressionTuner::artifact_type("state.bin", None), "bin");
+}
+
+#[test]
+fn test_tuner_choice_is_reproducible_with_a_fixed_clock() {
+    let contents = "{\"graph\":\"abc123\"}".repeat(1000).into_bytes();
+    let clock = MockClock::default();
+    let tune = || {
+        let tuner = CompressionTuner::new();
+        let encoded: Vec<Vec<u8>> = (0..SAMPLE_UPLOADS)
+            .map(|i| {
+                tuner
+                    .encode(
+                        &contents,
+                        &format!("attempt_{i}.json"),
+                        Some("graphs/abc123/peg_out"),
+                        DEFAULT_MIN_COMPRESS_SIZE,
+                        &clock,
+                    )
+                    .unwrap()
+            })
+            .collect();
+        (tuner.learned_level("peg_out").unwrap(), encoded)
+    };
+
+    assert_eq!(tune(), tune());
+}
@@ -0,0 +1,153 @@
+// Behaviour every data store driver must share. Each driver's tests call `run_conformance`
+// with a fresh, empty store.
+
+use bridge::client::data_store::{
+    base::{DataStoreDriver, StoreCapabilities},
+    key::parse_key,
+};
+
+const FILE_PATH: &str = "bridge_data/conformance";
+
+async fn listed_file_names<D: DataStoreDriver>(driver: &D, file_path: Option<&str>) -> Vec<String> {
+    let mut file_names: Vec<String> = driver
+        .list_objects(file_path)
+        .await
+        .unwrap()
+        .iter()
+        .map(|key| parse_key(key).file_name().to_string())
+        .collect();
+    file_names.sort();
+    file_names
+}
+
+pub async fn run_conformance<D: DataStoreDriver>(driver: D) {
+    // Round trips
+    driver
+        .upload_object("a.json", "{\"dog\":\"cat\"}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        driver
+            .fetch_object("a.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"dog\":\"cat\"}"
+    );
+
+    let contents = "{\"dog\":\"cat\"}".repeat(100).into_bytes();
+    driver
+        .upload_compressed_object("b.bin", &contents, Some(FILE_PATH))
+        .await
+        .unwrap();
+    let fetched = driver
+        .fetch_compressed_object("b.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(fetched.data, contents);
+    assert_eq!(fetched.decompressed_size, contents.len());
+    assert!(fetched.stored_size < contents.len());
+
+    // Empty objects
+    driver
+        .upload_object("empty.json", "", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        driver
+            .fetch_object("empty.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        ""
+    );
+    driver
+        .upload_compressed_object("empty.bin", &vec![], Some(FILE_PATH))
+        .await
+        .unwrap();
+    let fetched = driver
+        .fetch_compressed_object("empty.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(fetched.data.is_empty());
+
+    // Listing and prefixes
+    assert_eq!(
+        listed_file_names(&driver, Some(FILE_PATH)).await,
+        vec!["a.json", "b.bin", "empty.bin", "empty.json"]
+    );
+    driver
+        .upload_object("c.json", "{}", Some(&format!("{FILE_PATH}-other")))
+        .await
+        .unwrap();
+    assert_eq!(listed_file_names(&driver, Some(FILE_PATH)).await.len(), 4);
+    assert!(listed_file_names(&driver, Some("bridge_data/missing"))
+        .await
+        .is_empty());
+    assert!(
+        listed_file_names(&driver, Some(&format!("{FILE_PATH}/missing")))
+            .await
+            .is_empty()
+    );
+    assert!(driver
+        .list_objects_with_suffix(Some("bridge_data/missing"), ".json")
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(driver
+        .object_exists("a.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+
+    // Missing objects
+    assert!(driver
+        .fetch_object("missing.json", Some(FILE_PATH))
+        .await
+        .is_err());
+    assert!(driver
+        .fetch_compressed_object("missing.bin", Some(FILE_PATH))
+        .await
+        .is_err());
+    assert!(!driver
+        .object_exists("missing.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+
+    // Keys with characters that need escaping in URLs and listings
+    let tricky_path = format!("{FILE_PATH}-tricky/graph #1");
+    for file_name in ["a b.json", "a+b.json", "a%2Bb.json", "ünïcødé ✓.json"] {
+        driver
+            .upload_object(file_name, file_name, Some(&tricky_path))
+            .await
+            .unwrap();
+        assert_eq!(
+            driver
+                .fetch_object(file_name, Some(&tricky_path))
+                .await
+                .unwrap(),
+            file_name
+        );
+    }
+    let mut keys = driver.list_objects(Some(&tricky_path)).await.unwrap();
+    keys.sort();
+    assert_eq!(
+        keys,
+        ["a b.json", "a%2Bb.json", "a+b.json", "ünïcødé ✓.json"]
+            .map(|file_name| format!("{tricky_path}/{file_name}"))
+    );
+
+    // Deletes
+    if driver.capabilities().contains(StoreCapabilities::DELETE) {
+        driver
+            .delete_object("a.json", Some(FILE_PATH))
+            .await
+            .unwrap();
+        assert!(driver
+            .fetch_object("a.json", Some(FILE_PATH))
+            .await
+            .is_err());
+        driver
+            .delete_object("missing.json", Some(FILE_PATH))
+            .await
+            .unwrap();
+        assert_eq!(listed_file_names(&driver, Some(FILE_PATH)).await.len(), 3);
+    }
+}
@@ -0,0 +1,141 @@
+use std::{sync::Arc, time::Duration};
+
+use bridge::client::data_store::{
+    base::DataStoreDriver,
+    clock::MockClock,
+    delayed_consistency::DelayedConsistencyStore,
+    memory::MemoryStore,
+    read_retry::{ReadRetry, ReadRetryPolicy},
+};
+
+const FILE_PATH: &str = "bridge_data/consistency";
+
+// Advances `clock` in lockstep with tokio's (paused) time so the store's consistency window
+// elapses while `verify_after_write` is polling
+fn drive_clock(clock: Arc<MockClock>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            clock.advance(Duration::from_secs(1));
+        }
+    });
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_verify_after_write_waits_out_consistency_window() {
+    let clock = Arc::new(MockClock::default());
+    let store = DelayedConsistencyStore::new(MemoryStore::new(), Duration::from_secs(5))
+        .with_clock(clock.clone());
+
+    store
+        .upload_object("graph.json", "{\"dog\":\"cat\"}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert!(!store
+        .object_exists("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+    assert!(store
+        .fetch_object("graph.json", Some(FILE_PATH))
+        .await
+        .is_err());
+
+    drive_clock(clock);
+    store
+        .verify_after_write("graph.json", Some(FILE_PATH), Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"dog\":\"cat\"}"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_verify_after_write_gives_up_after_timeout() {
+    let clock = Arc::new(MockClock::default());
+    let store = DelayedConsistencyStore::new(MemoryStore::new(), Duration::from_secs(60))
+        .with_clock(clock.clone());
+
+    store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    drive_clock(clock);
+    let result = store
+        .verify_after_write("graph.json", Some(FILE_PATH), Duration::from_secs(10))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_read_retry_waits_for_object_written_by_peer() {
+    let clock = Arc::new(MockClock::default());
+    let store = ReadRetry::new(
+        DelayedConsistencyStore::new(MemoryStore::new(), Duration::from_secs(5))
+            .with_clock(clock.clone()),
+        ReadRetryPolicy::default(),
+    );
+
+    store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    drive_clock(clock);
+    assert_eq!(
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_read_retry_gives_up_after_max_wait() {
+    let store = ReadRetry::new(MemoryStore::new(), ReadRetryPolicy::default());
+
+    let start = tokio::time::Instant::now();
+    let result = store.fetch_object("graph.json", Some(FILE_PATH)).await;
+    assert!(result.is_err());
+    assert!(start.elapsed() <= ReadRetryPolicy::default().max_wait + Duration::from_secs(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_deterministic_read_retry_has_fixed_timing() {
+    let policy = ReadRetryPolicy {
+        initial_backoff: Duration::from_secs(1),
+        max_backoff: Duration::from_secs(4),
+        max_wait: Duration::from_secs(10),
+        deterministic: true,
+    };
+    let store = ReadRetry::new(MemoryStore::new(), policy);
+
+    // Sleeps 1s, 2s, 4s, then the remaining 3s before giving up
+    let start = tokio::time::Instant::now();
+    assert!(store
+        .fetch_object("graph.json", Some(FILE_PATH))
+        .await
+        .is_err());
+    assert_eq!(start.elapsed(), Duration::from_secs(10));
+}
+
+#[test]
+fn test_mock_clock_only_moves_when_advanced() {
+    use bridge::client::data_store::clock::Clock;
+
+    let clock = MockClock::default();
+    let start = clock.now();
+    assert_eq!(clock.now(), start);
+
+    clock.advance(Duration::from_millis(1500));
+    assert_eq!(clock.elapsed_since(start), Duration::from_millis(1500));
+    assert_eq!(clock.unix_millis(), 1500);
+}
@@ -0,0 +1,62 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver, encrypted_keys::EncryptedKeys, memory::MemoryStore,
+};
+
+const FILE_PATH: &str = "bridge_data/graph-1234";
+
+#[tokio::test]
+async fn test_stored_keys_are_opaque_but_listed_logically() {
+    let store = EncryptedKeys::new(MemoryStore::new(), b"secret");
+    store
+        .upload_object("peg-out-5000.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.list_objects(Some(FILE_PATH)).await.unwrap(),
+        vec![format!("{FILE_PATH}/peg-out-5000.json")]
+    );
+    assert_eq!(
+        store
+            .fetch_object("peg-out-5000.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+
+    let mapping = store.export_mapping();
+    let inner = store.into_inner();
+    let stored_keys = inner.list_objects(None).await.unwrap();
+    assert_eq!(stored_keys.len(), 1);
+    assert!(!stored_keys[0].contains("graph-1234"));
+    assert!(!stored_keys[0].contains("peg-out"));
+
+    // A later instance with the same secret can read by logical name, and list once it has
+    // imported the mapping
+    let store = EncryptedKeys::new(inner, b"secret");
+    store.import_mapping(mapping);
+    assert_eq!(
+        store.list_objects(Some(FILE_PATH)).await.unwrap(),
+        vec![format!("{FILE_PATH}/peg-out-5000.json")]
+    );
+}
+
+#[tokio::test]
+async fn test_logical_keys_are_not_limited_by_the_inner_store() {
+    // A stored key under FILE_PATH takes three 64 character segments
+    let store = EncryptedKeys::new(MemoryStore::new().with_max_key_length(200), b"secret");
+    let file_name = format!("{}.json", "a".repeat(300));
+
+    assert_eq!(store.max_key_length(), None);
+    store
+        .upload_object(&file_name, "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object(&file_name, Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+}
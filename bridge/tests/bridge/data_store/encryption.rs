@@ -0,0 +1,130 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver,
+    encryption::{EncryptedStore, Keyring},
+    memory::MemoryStore,
+};
+
+const FILE_PATH: &str = "bridge_data/encryption";
+
+#[tokio::test]
+async fn test_contents_are_encrypted_and_read_back() {
+    let store = EncryptedStore::new(MemoryStore::new(), Keyring::new("k1", [1; 32]).unwrap());
+    let contents = "{\"graph_id\":\"abc\"}".repeat(100);
+    store
+        .upload_object("plain.json", &contents, Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_compressed_object(
+            "compressed.json",
+            &contents.as_bytes().to_vec(),
+            Some(FILE_PATH),
+        )
+        .await
+        .unwrap();
+
+    let stored = store
+        .inner()
+        .fetch_object("plain.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(stored.starts_with("BVME1:k1:"));
+    assert!(!stored.contains("graph_id"));
+
+    assert_eq!(
+        store
+            .fetch_object("plain.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        contents
+    );
+    let fetched = store
+        .fetch_compressed_object("compressed.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(fetched.data, contents.as_bytes());
+    assert!(fetched.stored_size < contents.len());
+
+    // Objects written before encryption was enabled are read as they are
+    store
+        .inner()
+        .upload_object("legacy.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("legacy.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+}
+
+#[tokio::test]
+async fn test_rotate_re_encrypts_objects_under_old_keys() {
+    let store = EncryptedStore::new(MemoryStore::new(), Keyring::new("k1", [1; 32]).unwrap());
+    store
+        .upload_object("old.json", "old", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let keyring = Keyring::new("k2", [2; 32])
+        .unwrap()
+        .with_old_key("k1", [1; 32])
+        .unwrap();
+    let store = EncryptedStore::new(store.into_inner(), keyring);
+    store
+        .upload_object("new.json", "new", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("old.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "old"
+    );
+
+    assert_eq!(store.rotate(Some(FILE_PATH)).await.unwrap(), 1);
+    assert_eq!(store.rotate(Some(FILE_PATH)).await.unwrap(), 0);
+
+    // The old key can be dropped once every object was rotated
+    let store = EncryptedStore::new(store.into_inner(), Keyring::new("k2", [2; 32]).unwrap());
+    assert_eq!(
+        store
+            .fetch_object("old.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "old"
+    );
+    assert_eq!(
+        store
+            .fetch_object("new.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "new"
+    );
+
+    let store = EncryptedStore::new(store.into_inner(), Keyring::new("k1", [1; 32]).unwrap());
+    let err = store
+        .fetch_object("old.json", Some(FILE_PATH))
+        .await
+        .unwrap_err();
+    assert!(err.contains("\"k2\""), "{err}");
+}
+
+#[tokio::test]
+async fn test_wrong_key_fails_to_decrypt() {
+    let store = EncryptedStore::new(MemoryStore::new(), Keyring::new("k1", [1; 32]).unwrap());
+    store
+        .upload_object("object.json", "secret", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let store = EncryptedStore::new(store.into_inner(), Keyring::new("k1", [9; 32]).unwrap());
+    let err = store
+        .fetch_object("object.json", Some(FILE_PATH))
+        .await
+        .unwrap_err();
+    assert!(err.contains("could not be decrypted"), "{err}");
+}
@@ -0,0 +1,341 @@
+// An in-process stand-in for the parts of the S3 API that `AwsS3` uses for plain objects: PUT
+// (including copies and conditional writes), GET, HEAD, DELETE and ListObjectsV2. Requests are
+// answered from memory, so driver behaviour that depends on S3's responses can be tested
+// without a bucket. Failures are injected with `fail_next_conditional_writes`, `throttle_next`,
+// `stall_copies` and `corrupt_next_uploads`.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use aws_smithy_runtime_api::client::{
+    http::{
+        HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+    },
+    orchestrator::{HttpRequest, HttpResponse},
+    runtime_components::RuntimeComponents,
+};
+use aws_smithy_types::body::SdkBody;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bridge::client::data_store::aws_s3::AwsS3;
+use http::{Request, Response};
+use md5::{Digest, Md5};
+use tokio::time::Instant;
+
+const BUCKET: &str = "bitvm-artifacts";
+
+#[derive(Debug, Default)]
+struct State {
+    objects: BTreeMap<String, Vec<u8>>,
+    conflicts: usize, // Conditional writes still to reject with 412
+    throttled: usize, // Requests still to reject with 503
+    retry_after: Option<String>,
+    stall_copies: bool,     // Copies never get a response
+    corrupt_uploads: usize, // Uploads still to flip a byte of before checking their Content-MD5
+    requests: Vec<Instant>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FakeS3 {
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeS3 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store whose requests are all answered by this fake.
+    pub fn store(&self) -> AwsS3 {
+        AwsS3::for_wasabi("eu-central-1", "key", "secret", BUCKET).with_http_client(self.clone())
+    }
+
+    pub fn object(&self, key: &str) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().objects.get(key).cloned()
+    }
+
+    /// Rejects the next `n` PUTs with an `If-Match` or `If-None-Match` header as if another
+    /// writer got there first.
+    pub fn fail_next_conditional_writes(&self, n: usize) {
+        self.state.lock().unwrap().conflicts = n;
+    }
+
+    /// Rejects the next `n` requests with 503 `SlowDown`, with a `Retry-After` header if set.
+    pub fn throttle_next(&self, n: usize, retry_after: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        state.throttled = n;
+        state.retry_after = retry_after.map(str::to_string);
+    }
+
+    /// Damages the body of the next `n` uploads in transit, so they fail their Content-MD5 check.
+    pub fn corrupt_next_uploads(&self, n: usize) {
+        self.state.lock().unwrap().corrupt_uploads = n;
+    }
+
+    /// Leaves every copy request hanging without a response.
+    pub fn stall_copies(&self) {
+        self.state.lock().unwrap().stall_copies = true;
+    }
+
+    /// When each request was received, in order.
+    pub fn request_times(&self) -> Vec<Instant> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    fn handle(&self, request: Request<SdkBody>) -> Response<SdkBody> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(Instant::now());
+        if state.throttled > 0 {
+            state.throttled -= 1;
+            let mut response = Response::builder().status(503);
+            if let Some(retry_after) = &state.retry_after {
+                response = response.header("Retry-After", retry_after);
+            }
+            return response.body(error_body("SlowDown")).unwrap();
+        }
+
+        let query = request.uri().query().unwrap_or_default().to_string();
+        if request.method() == "GET" && query_param(&query, "list-type").is_some() {
+            let prefix = query_param(&query, "prefix").unwrap_or_default();
+            let url_encoded = query_param(&query, "encoding-type").as_deref() == Some("url");
+            return list(&state.objects, &prefix, url_encoded);
+        }
+
+        let key = object_key_of(&request);
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        match request.method().as_str() {
+            "PUT" => {
+                let conditional = header("if-match").is_some() || header("if-none-match").is_some();
+                if conditional && state.conflicts > 0 {
+                    state.conflicts -= 1;
+                    return error(412, "PreconditionFailed");
+                }
+                if let Some(etag) = header("if-match") {
+                    match state.objects.get(&key) {
+                        None => return error(404, "NoSuchKey"),
+                        Some(data) if etag_of(data) != etag => {
+                            return error(412, "PreconditionFailed")
+                        }
+                        _ => {}
+                    }
+                }
+                if header("if-none-match").as_deref() == Some("*")
+                    && state.objects.contains_key(&key)
+                {
+                    return error(412, "PreconditionFailed");
+                }
+
+                match header("x-amz-copy-source") {
+                    Some(copy_source) => {
+                        // S3 only accepts URL-encoded copy sources
+                        if !copy_source.chars().all(|c| c == '%' || is_unreserved(c)) {
+                            return error(400, "InvalidArgument");
+                        }
+                        let source = percent_decode(&copy_source);
+                        let Some(data) = source
+                            .strip_prefix(&format!("{BUCKET}/"))
+                            .and_then(|source| state.objects.get(source))
+                            .cloned()
+                        else {
+                            return error(404, "NoSuchKey");
+                        };
+                        let body = format!(
+                            "<CopyObjectResult><ETag>{}</ETag></CopyObjectResult>",
+                            xml_escape(&etag_of(&data))
+                        );
+                        state.objects.insert(key, data);
+                        Response::builder().status(200).body(body.into()).unwrap()
+                    }
+                    None => {
+                        let mut data = request.body().bytes().unwrap_or_default().to_vec();
+                        if state.corrupt_uploads > 0 {
+                            state.corrupt_uploads -= 1;
+                            if let Some(byte) = data.first_mut() {
+                                *byte ^= 0xff;
+                            }
+                        }
+                        if let Some(content_md5) = header("content-md5") {
+                            if content_md5 != STANDARD.encode(Md5::digest(&data)) {
+                                return error(400, "BadDigest");
+                            }
+                        }
+                        let etag = etag_of(&data);
+                        state.objects.insert(key, data);
+                        Response::builder()
+                            .status(200)
+                            .header("ETag", etag)
+                            .body(SdkBody::empty())
+                            .unwrap()
+                    }
+                }
+            }
+            "GET" | "HEAD" => match state.objects.get(&key) {
+                Some(data) => {
+                    let body = match request.method() == "GET" {
+                        true => SdkBody::from(data.clone()),
+                        false => SdkBody::empty(),
+                    };
+                    Response::builder()
+                        .status(200)
+                        .header("ETag", etag_of(data))
+                        .header("Content-Length", data.len())
+                        .header("Last-Modified", "Mon, 01 Jan 2024 00:00:00 GMT")
+                        .body(body)
+                        .unwrap()
+                }
+                None if request.method() == "HEAD" => Response::builder()
+                    .status(404)
+                    .body(SdkBody::empty())
+                    .unwrap(),
+                None => error(404, "NoSuchKey"),
+            },
+            "DELETE" => {
+                state.objects.remove(&key);
+                Response::builder()
+                    .status(204)
+                    .body(SdkBody::empty())
+                    .unwrap()
+            }
+            _ => error(405, "MethodNotAllowed"),
+        }
+    }
+}
+
+impl HttpConnector for FakeS3 {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let request = request.try_into_http1x().unwrap();
+        if request.headers().contains_key("x-amz-copy-source")
+            && self.state.lock().unwrap().stall_copies
+        {
+            return HttpConnectorFuture::new(std::future::pending());
+        }
+        let response = HttpResponse::try_from(self.handle(request)).unwrap();
+        HttpConnectorFuture::ready(Ok(response))
+    }
+}
+
+impl HttpClient for FakeS3 {
+    fn http_connector(
+        &self,
+        _settings: &HttpConnectorSettings,
+        _components: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(self.clone())
+    }
+}
+
+// The store addresses the bucket virtual-hosted-style, so the path is the URL-encoded key
+fn object_key_of(request: &Request<SdkBody>) -> String {
+    percent_decode(request.uri().path().trim_start_matches('/'))
+}
+
+fn list(objects: &BTreeMap<String, Vec<u8>>, prefix: &str, url_encoded: bool) -> Response<SdkBody> {
+    let encode = |key: &str| match url_encoded {
+        true => url_encode(key),
+        false => xml_escape(key),
+    };
+    let contents: String = objects
+        .range(prefix.to_string()..)
+        .take_while(|(key, _)| key.starts_with(prefix))
+        .map(|(key, data)| {
+            format!(
+                "<Contents><Key>{}</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified>\
+                 <ETag>{}</ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+                encode(key),
+                xml_escape(&etag_of(data)),
+                data.len()
+            )
+        })
+        .collect();
+    let encoding_type = match url_encoded {
+        true => "<EncodingType>url</EncodingType>",
+        false => "",
+    };
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+         <Name>{BUCKET}</Name><Prefix>{}</Prefix>{encoding_type}\
+         <IsTruncated>false</IsTruncated>{contents}</ListBucketResult>",
+        encode(prefix)
+    );
+    Response::builder().status(200).body(body.into()).unwrap()
+}
+
+fn error(status: u16, code: &str) -> Response<SdkBody> {
+    Response::builder()
+        .status(status)
+        .body(error_body(code))
+        .unwrap()
+}
+
+fn error_body(code: &str) -> SdkBody {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <Error><Code>{code}</Code><Message>{code}</Message></Error>"
+    )
+    .into()
+}
+
+fn etag_of(data: &[u8]) -> String {
+    let digest: String = Md5::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("\"{digest}\"")
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then(|| percent_decode(value))
+    })
+}
+
+fn is_unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~' | '/')
+}
+
+fn percent_decode(value: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail) {
+            (b'%', [high, low, tail @ ..]) => {
+                let hex = std::str::from_utf8(&[*high, *low]).unwrap().to_string();
+                bytes.push(u8::from_str_radix(&hex, 16).unwrap());
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).unwrap()
+}
+
+// Encodes keys the way S3 does in listings with `encoding-type=url`
+fn url_encode(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b' ' => "+".to_string(),
+            byte if is_unreserved(byte as char) => (byte as char).to_string(),
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,168 @@
+use bridge::client::data_store::{
+    base::{DataStoreDriver, DecompressFailureMode},
+    fallback::FallbackStore,
+    memory::MemoryStore,
+};
+
+const FILE_PATH: &str = "bridge_data/fallback";
+
+async fn tiered_store(populate: bool) -> FallbackStore {
+    let archive = MemoryStore::new();
+    archive
+        .upload_object("archived.json", "{\"tier\":2}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    FallbackStore::new(vec![
+        Box::new(MemoryStore::new()),
+        Box::new(MemoryStore::new()),
+        Box::new(archive),
+    ])
+    .with_primary(1)
+    .with_populate(populate)
+}
+
+#[tokio::test]
+async fn test_reads_fall_through_to_lower_tiers() {
+    let store = tiered_store(false).await;
+
+    assert_eq!(
+        store
+            .fetch_object("archived.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"tier\":2}"
+    );
+    assert!(!store
+        .tier(0)
+        .object_exists("archived.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+    assert!(store
+        .fetch_object("missing.json", Some(FILE_PATH))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_reads_populate_higher_tiers() {
+    let store = tiered_store(true).await;
+
+    store
+        .fetch_object("archived.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    for tier in 0..2 {
+        assert_eq!(
+            store
+                .tier(tier)
+                .fetch_object("archived.json", Some(FILE_PATH))
+                .await
+                .unwrap(),
+            "{\"tier\":2}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_objects_failing_to_decompress_are_not_populated() {
+    let archive = MemoryStore::new().with_decompress_failure_mode(DecompressFailureMode::ReturnRaw);
+    archive
+        .upload_object("corrupt.bin", "not compressed", Some(FILE_PATH))
+        .await
+        .unwrap();
+    let store = FallbackStore::new(vec![Box::new(MemoryStore::new()), Box::new(archive)])
+        .with_populate(true);
+
+    let fetched = store
+        .fetch_compressed_object("corrupt.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(fetched.decompress_failed);
+    assert!(!store
+        .tier(0)
+        .object_exists("corrupt.bin", Some(FILE_PATH))
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_writes_go_to_primary_tier() {
+    let store = tiered_store(false).await;
+
+    store
+        .upload_object("new.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .tier(1)
+        .object_exists("new.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+    assert!(!store
+        .tier(0)
+        .object_exists("new.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_writes_invalidate_higher_tiers() {
+    let store = tiered_store(true).await;
+
+    store
+        .fetch_object("archived.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_object("archived.json", "{\"tier\":1}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("archived.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"tier\":1}"
+    );
+
+    store
+        .delete_object("archived.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    for tier in 0..3 {
+        assert!(!store
+            .tier(tier)
+            .object_exists("archived.json", Some(FILE_PATH))
+            .await
+            .unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_conditional_writes_go_to_primary_tier() {
+    let store = tiered_store(false).await;
+
+    assert_eq!(
+        store
+            .increment("counter", Some(FILE_PATH), 2)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        store
+            .increment("counter", Some(FILE_PATH), 1)
+            .await
+            .unwrap(),
+        3
+    );
+    let (contents, _) = store
+        .tier(1)
+        .fetch_with_etag("counter", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(contents, "3");
+
+    store.shutdown().await.unwrap();
+}
@@ -0,0 +1,156 @@
+use bridge::{
+    client::data_store::format::{
+        content_encoding, decode_object, decode_object_spilling, decode_object_with_dictionaries,
+        encode_object, encode_object_multithreaded, encode_object_uncompressed,
+        encode_object_with_dictionary, encode_object_with_min_size, train_dictionary,
+        DecompressedOutput, Dictionaries, DEFAULT_MIN_COMPRESS_SIZE,
+    },
+    utils::{
+        compress, compress_multithreaded, estimate_compressed_size, DEFAULT_COMPRESSION_LEVEL,
+    },
+};
+use rand::RngCore;
+use std::io::Read;
+
+#[test]
+fn test_compressible_object_round_trip() {
+    let contents = "{\"dog\":\"cat\"}".repeat(1000).into_bytes();
+
+    let encoded = encode_object(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+    assert!(encoded.len() < contents.len());
+    assert_eq!(decode_object(&encoded).unwrap(), contents);
+}
+
+#[test]
+fn test_incompressible_object_is_stored_uncompressed() {
+    let mut contents = vec![0u8; 64 * 1024];
+    rand::thread_rng().fill_bytes(&mut contents);
+
+    let encoded = encode_object(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+    assert!(encoded.len() <= contents.len() + 4);
+    assert_eq!(decode_object(&encoded).unwrap(), contents);
+}
+
+#[test]
+fn test_legacy_zstd_object_is_decoded() {
+    let contents = "{\"dog\":\"cat\"}".as_bytes().to_vec();
+    let legacy = compress(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+
+    assert_eq!(decode_object(&legacy).unwrap(), contents);
+}
+
+#[test]
+fn test_small_object_is_not_spilled() {
+    let contents = "{\"dog\":\"cat\"}".repeat(10).into_bytes();
+    let encoded = encode_object(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+
+    match decode_object_spilling(&encoded, 1024).unwrap() {
+        DecompressedOutput::Bytes(bytes) => assert_eq!(bytes, contents),
+        DecompressedOutput::File(_) => panic!("Object below the threshold was spilled"),
+    }
+}
+
+#[test]
+fn test_large_object_is_spilled_to_file() {
+    let contents = "{\"dog\":\"cat\"}".repeat(1000).into_bytes();
+    let encoded = encode_object(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+
+    match decode_object_spilling(&encoded, 1024).unwrap() {
+        DecompressedOutput::Bytes(_) => panic!("Object above the threshold was not spilled"),
+        DecompressedOutput::File(mut file) => {
+            let mut bytes = vec![];
+            file.read_to_end(&mut bytes).unwrap();
+            assert_eq!(bytes, contents);
+        }
+    }
+}
+
+#[test]
+fn test_dictionary_compression_round_trip() {
+    let samples: Vec<Vec<u8>> = (0..200)
+        .map(|i| {
+            format!(
+                "{{\"graph_id\":\"{i:064x}\",\"peg_out\":{{\"txid\":\"{:064x}\",\"vout\":{}}}}}",
+                i * 7,
+                i % 3
+            )
+            .into_bytes()
+        })
+        .collect();
+    let dictionary = train_dictionary(&samples).unwrap();
+    let mut dictionaries = Dictionaries::new();
+    dictionaries.insert(dictionary.clone()).unwrap();
+
+    let contents = &samples[42];
+    let plain = encode_object(contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+    let with_dictionary =
+        encode_object_with_dictionary(contents, DEFAULT_COMPRESSION_LEVEL, &dictionary).unwrap();
+    assert!(with_dictionary.len() < plain.len());
+    assert_eq!(
+        decode_object_with_dictionaries(&with_dictionary, &dictionaries).unwrap(),
+        *contents
+    );
+    assert!(decode_object(&with_dictionary).is_err());
+}
+
+#[test]
+fn test_content_encoding_only_for_plain_zstd_frames() {
+    let samples: Vec<Vec<u8>> = (0..200)
+        .map(|i| format!("{{\"graph_id\":\"{i:064x}\",\"vout\":{}}}", i % 3).into_bytes())
+        .collect();
+    let dictionary = train_dictionary(&samples).unwrap();
+    let contents = "{\"dog\":\"cat\"}".repeat(1000).into_bytes();
+
+    let compressed = encode_object(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+    assert_eq!(content_encoding(&compressed), Some("zstd"));
+    assert_eq!(
+        content_encoding(&encode_object_uncompressed(&contents)),
+        None
+    );
+    let with_dictionary =
+        encode_object_with_dictionary(&samples[42], DEFAULT_COMPRESSION_LEVEL, &dictionary)
+            .unwrap();
+    assert_eq!(content_encoding(&with_dictionary), None);
+}
+
+#[test]
+fn test_tiny_object_is_stored_uncompressed() {
+    let contents = b"{\"dog\":\"cat\"}".to_vec();
+
+    let encoded = encode_object_with_min_size(
+        &contents,
+        DEFAULT_COMPRESSION_LEVEL,
+        DEFAULT_MIN_COMPRESS_SIZE,
+    )
+    .unwrap();
+    assert_eq!(encoded.len(), contents.len() + 4);
+    assert_eq!(decode_object(&encoded).unwrap(), contents);
+}
+
+#[test]
+fn test_estimated_compressed_size_matches_compressed_length() {
+    let contents = "{\"dog\":\"cat\"}".repeat(1000).into_bytes();
+    let compressed = compress(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap();
+
+    assert_eq!(
+        estimate_compressed_size(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap(),
+        compressed.len()
+    );
+}
+
+#[test]
+fn test_multithreaded_compression_round_trip() {
+    let contents = "{\"graph\":[1,2,3],\"tx\":\"abcdef\"}"
+        .repeat(200_000)
+        .into_bytes();
+
+    let encoded = encode_object_multithreaded(&contents, DEFAULT_COMPRESSION_LEVEL, 0, 4).unwrap();
+    assert!(encoded.len() < contents.len());
+    assert_eq!(decode_object(&encoded).unwrap(), contents);
+
+    // A single thread compresses exactly like `compress`
+    assert_eq!(
+        compress_multithreaded(&contents, DEFAULT_COMPRESSION_LEVEL, 1).unwrap(),
+        compress(&contents, DEFAULT_COMPRESSION_LEVEL).unwrap()
+    );
+}
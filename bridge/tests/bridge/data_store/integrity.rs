@@ -0,0 +1,57 @@
+use bridge::{
+    client::data_store::integrity::{split_checksum, HashAlgorithm, VerifyingReader},
+    error::DataStoreError,
+};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_intact_stream_is_verified() {
+    let data = vec![7u8; 100_000];
+    let mut reader = VerifyingReader::new(data.as_slice(), &sha256_hex(&data), "graph.json");
+
+    let mut read = vec![];
+    reader.read_to_end(&mut read).await.unwrap();
+    assert_eq!(read, data);
+}
+
+#[tokio::test]
+async fn test_corrupted_stream_fails_at_end() {
+    let data = vec![7u8; 100_000];
+    let mut corrupted = data.clone();
+    corrupted[50_000] ^= 1;
+    let mut reader = VerifyingReader::new(corrupted.as_slice(), &sha256_hex(&data), "graph.json");
+
+    let err = reader.read_to_end(&mut vec![]).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        DataStoreError::IntegrityError("graph.json".to_string()).to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_checksum_records_its_algorithm() {
+    let data = vec![7u8; 100_000];
+    let checksum = HashAlgorithm::Blake3.checksum(&data);
+    assert_eq!(
+        split_checksum(&checksum).unwrap(),
+        (HashAlgorithm::Blake3, blake3::hash(&data).to_hex().as_str())
+    );
+    // SHA-256 checksums stay plain hex digests
+    assert_eq!(HashAlgorithm::Sha256.checksum(&data), sha256_hex(&data));
+
+    let mut reader =
+        VerifyingReader::with_checksum(data.as_slice(), &checksum, "graph.json").unwrap();
+    reader.read_to_end(&mut vec![]).await.unwrap();
+
+    let sha512 = HashAlgorithm::Sha512.checksum(&data);
+    let mut reader = VerifyingReader::with_checksum(&data[1..], &sha512, "graph.json").unwrap();
+    assert!(reader.read_to_end(&mut vec![]).await.is_err());
+}
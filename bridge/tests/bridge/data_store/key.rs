@@ -0,0 +1,131 @@
+use bridge::{
+    client::data_store::{
+        base::DataStoreDriver,
+        key::{list_prefix, normalize_path, object_key, parse_key},
+        local_file::LocalFile,
+        memory::MemoryStore,
+    },
+    error::DataStoreError,
+};
+
+#[test]
+fn test_parse_key() {
+    let parsed = parse_key("graphs/abc123/peg_out/attempt_4.json");
+
+    assert_eq!(parsed.segments(), ["graphs", "abc123", "peg_out"]);
+    assert_eq!(parsed.segment(1), Some("abc123"));
+    assert_eq!(parsed.segment(3), None);
+    assert_eq!(
+        parsed.directory(),
+        Some(String::from("graphs/abc123/peg_out"))
+    );
+    assert_eq!(parsed.file_name(), "attempt_4.json");
+    assert_eq!(parsed.stem(), "attempt_4");
+    assert_eq!(parsed.extension(), Some("json"));
+}
+
+#[test]
+fn test_parse_key_without_directory() {
+    let parsed = parse_key("1721392247764-bridge-client-data");
+
+    assert!(parsed.segments().is_empty());
+    assert_eq!(parsed.directory(), None);
+    assert_eq!(parsed.file_name(), "1721392247764-bridge-client-data");
+    assert_eq!(parsed.extension(), None);
+}
+
+#[test]
+fn test_parse_key_round_trips_object_key() {
+    let key = object_key("attempt_4.json", Some("graphs/abc123"));
+    let parsed = parse_key(&key);
+
+    assert_eq!(
+        object_key(parsed.file_name(), parsed.directory().as_deref()),
+        key
+    );
+}
+
+// Every spelling of the same directory, `None` and `Some("")` both meaning the root
+const EQUIVALENT_PATHS: [(Option<&str>, Option<&str>); 7] = [
+    (None, None),
+    (Some(""), None),
+    (Some("/"), None),
+    (Some("graphs"), Some("graphs")),
+    (Some("/graphs/"), Some("graphs")),
+    (Some("graphs//abc123"), Some("graphs/abc123")),
+    (Some("//graphs/abc123//"), Some("graphs/abc123")),
+];
+
+#[test]
+fn test_path_normalization() {
+    for (file_path, normalized) in EQUIVALENT_PATHS {
+        assert_eq!(normalize_path(file_path).as_deref(), normalized);
+        assert_eq!(
+            object_key("key", file_path),
+            object_key("key", normalized),
+            "{file_path:?}"
+        );
+        assert_eq!(list_prefix(file_path), list_prefix(normalized));
+    }
+    assert_eq!(object_key("key", Some("/graphs/")), "graphs/key");
+    assert_eq!(list_prefix(Some("")), "");
+}
+
+#[tokio::test]
+async fn test_equivalent_paths_address_same_object_across_drivers() {
+    let base_path = tempfile::tempdir().unwrap();
+    let drivers: Vec<Box<dyn DataStoreDriver + Send + Sync>> = vec![
+        Box::new(MemoryStore::new()),
+        Box::new(LocalFile::with_base_path(base_path.path().to_path_buf())),
+    ];
+
+    for driver in drivers {
+        for (file_path, normalized) in EQUIVALENT_PATHS {
+            let file_name = format!("{}.json", normalized.unwrap_or("root").replace('/', "-"));
+            driver
+                .upload_object(&file_name, "{}", file_path)
+                .await
+                .unwrap();
+            assert_eq!(
+                driver.fetch_object(&file_name, normalized).await.unwrap(),
+                "{}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_keys_over_the_driver_limit_are_rejected() {
+    let base_path = tempfile::tempdir().unwrap();
+    let drivers: Vec<Box<dyn DataStoreDriver + Send + Sync>> = vec![
+        Box::new(MemoryStore::new().with_max_key_length(64)),
+        Box::new(LocalFile::with_base_path(base_path.path().to_path_buf())),
+    ];
+
+    for driver in drivers {
+        let max = driver.max_key_length().unwrap();
+        let file_name = "k".repeat(max - "graphs/".len() + 1);
+        let expected = DataStoreError::KeyTooLong { len: max + 1, max }.to_string();
+        assert_eq!(
+            driver
+                .upload_object(&file_name, "{}", Some("graphs"))
+                .await
+                .unwrap_err(),
+            expected
+        );
+        assert_eq!(
+            driver
+                .fetch_object(&file_name, Some("graphs"))
+                .await
+                .unwrap_err(),
+            expected
+        );
+    }
+
+    let store = MemoryStore::new().with_max_key_length(64);
+    let file_name = "k".repeat(64 - "graphs/".len());
+    store
+        .upload_object(&file_name, "{}", Some("graphs"))
+        .await
+        .unwrap();
+}
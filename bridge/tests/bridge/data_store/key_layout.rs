@@ -0,0 +1,110 @@
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use bridge::client::data_store::{
+    base::DataStoreDriver,
+    clock::MockClock,
+    key_layout::{DatePartitioned, Flat, HashPrefixed, KeyLayoutStore},
+    memory::MemoryStore,
+};
+
+const FILE_PATH: &str = "bridge_data/key_layout";
+
+#[tokio::test]
+async fn test_flat_layout_stores_logical_keys() {
+    let store = KeyLayoutStore::new(MemoryStore::new(), Flat);
+    store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert!(store
+        .inner()
+        .object_exists("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+    assert_eq!(
+        store.list_objects(Some(FILE_PATH)).await.unwrap(),
+        vec![format!("{FILE_PATH}/graph.json")]
+    );
+}
+
+#[tokio::test]
+async fn test_hash_prefixed_layout_lists_logical_keys() {
+    let store = KeyLayoutStore::new(MemoryStore::new(), HashPrefixed::new(16));
+    for i in 0..10 {
+        store
+            .upload_object(&format!("graph_{i}.json"), "{}", Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+
+    let mut keys = store.list_objects(Some(FILE_PATH)).await.unwrap();
+    keys.sort();
+    let mut expected: Vec<String> = (0..10)
+        .map(|i| format!("{FILE_PATH}/graph_{i}.json"))
+        .collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+    assert!(!store
+        .inner()
+        .object_exists("graph_0.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+    assert_eq!(
+        store
+            .fetch_object("graph_0.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+}
+
+#[tokio::test]
+async fn test_date_partitioned_layout_reads_newest_copy() {
+    let clock = Arc::new(MockClock::new(
+        UNIX_EPOCH + Duration::from_secs(1_735_787_045),
+    ));
+    let layout = DatePartitioned::new().with_clock(clock.clone());
+    let store = KeyLayoutStore::new(MemoryStore::new(), layout);
+
+    store
+        .upload_object("graph.json", "{\"day\":1}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .inner()
+        .object_exists("graph.json", Some(&format!("{FILE_PATH}/2025/01/02")))
+        .await
+        .unwrap());
+
+    clock.advance(Duration::from_secs(24 * 60 * 60));
+    store
+        .upload_object("graph.json", "{\"day\":2}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"day\":2}"
+    );
+
+    store
+        .delete_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(!store
+        .object_exists("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+    assert!(store
+        .inner()
+        .list_objects(Some(FILE_PATH))
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,75 @@
+use bridge::{
+    client::data_store::{
+        base::DataStoreDriver,
+        key_policy::{KeyPolicy, KeyPolicyStore},
+        memory::MemoryStore,
+    },
+    error::DataStoreError,
+};
+
+#[test]
+fn test_parse_key_policy() {
+    let policy = KeyPolicy::parse("allow:bridge_data/, deny:bridge_data/keys/").unwrap();
+    assert!(policy.allows("bridge_data/graph.json"));
+    assert!(!policy.allows("bridge_data/keys/operator.json"));
+    assert!(!policy.allows("other_tenant/graph.json"));
+
+    assert!(KeyPolicy::parse("bridge_data/").is_err());
+}
+
+#[tokio::test]
+async fn test_keys_outside_policy_are_rejected() {
+    let inner = MemoryStore::new();
+    for (file_name, file_path) in [
+        ("graph.json", "bridge_data"),
+        ("operator.json", "bridge_data/keys"),
+        ("graph.json", "other_tenant"),
+    ] {
+        inner
+            .upload_object(file_name, "{}", Some(file_path))
+            .await
+            .unwrap();
+    }
+    let store = KeyPolicyStore::new(
+        inner,
+        KeyPolicy::parse("allow:bridge_data/,deny:bridge_data/keys/").unwrap(),
+    );
+
+    assert!(store
+        .fetch_object("graph.json", Some("bridge_data"))
+        .await
+        .is_ok());
+    for (file_name, file_path) in [
+        ("operator.json", "bridge_data/keys"),
+        ("graph.json", "other_tenant"),
+    ] {
+        assert_eq!(
+            store
+                .fetch_object(file_name, Some(file_path))
+                .await
+                .unwrap_err(),
+            DataStoreError::PolicyViolation(format!("{file_path}/{file_name}")).to_string()
+        );
+        assert!(store
+            .upload_object(file_name, "{}", Some(file_path))
+            .await
+            .is_err());
+    }
+    assert!(store
+        .copy_object(
+            "graph.json",
+            Some("bridge_data"),
+            "graph.json",
+            Some("other_tenant")
+        )
+        .await
+        .is_err());
+
+    // Listings leave out denied keys, but totals including them are rejected
+    assert_eq!(
+        store.list_objects(Some("bridge_data")).await.unwrap(),
+        vec!["bridge_data/graph.json"]
+    );
+    assert!(store.list_objects(None).await.is_err());
+    assert!(store.prefix_size(Some("bridge_data")).await.is_err());
+}
@@ -0,0 +1,52 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bridge::client::data_store::lazy_client::LazyClient;
+use futures::future::join_all;
+
+#[tokio::test]
+async fn test_concurrent_first_uses_initialize_once() {
+    let initializations = Arc::new(AtomicUsize::new(0));
+    let counter = initializations.clone();
+    let client = Arc::new(LazyClient::new(move || {
+        let counter = counter.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(counter.fetch_add(1, Ordering::SeqCst))
+        }
+    }));
+
+    let uses = (0..32).map(|_| {
+        let client = client.clone();
+        tokio::spawn(async move { *client.get().await.unwrap() })
+    });
+    for result in join_all(uses).await {
+        assert_eq!(result.unwrap(), 0);
+    }
+    assert_eq!(initializations.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_failed_initialization_is_retried() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    let client = LazyClient::new(move || {
+        let attempt = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            match attempt {
+                0 => Err("Connection refused".to_string()),
+                _ => Ok(attempt),
+            }
+        }
+    });
+
+    assert!(client.get().await.is_err());
+    assert!(!client.is_initialized());
+    assert_eq!(*client.get().await.unwrap(), 1);
+    assert!(client.is_initialized());
+}
@@ -0,0 +1,267 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use bridge::{
+    client::data_store::{
+        base::{DataStoreDriver, DecompressFailureMode, DriverConfig},
+        clock::MockClock,
+        compression_policy::{CompressionPolicy, CompressionRule},
+        format::compress_once,
+        integrity::HashAlgorithm,
+        local_file::LocalFile,
+    },
+    error::DataStoreError,
+};
+
+use super::conformance::run_conformance;
+
+const FILE_PATH: &str = "bridge_data/local_file";
+
+async fn store_with_objects(config: DriverConfig, count: usize) -> (LocalFile, tempfile::TempDir) {
+    let base_path = tempfile::tempdir().unwrap();
+    let store = LocalFile::with_base_path(base_path.path().to_path_buf()).with_config(config);
+    for i in 0..count {
+        store
+            .upload_object(&format!("{i}.json"), "{}", Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+
+    (store, base_path)
+}
+
+#[tokio::test]
+async fn test_listing_is_capped_at_max_keys_total() {
+    let config = DriverConfig {
+        max_keys_total: Some(3),
+        ..Default::default()
+    };
+    let (store, _base_path) = store_with_objects(config, 5).await;
+
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_strict_listing_fails_over_max_keys_total() {
+    let config = DriverConfig {
+        max_keys_total: Some(3),
+        strict_max_keys: true,
+        ..Default::default()
+    };
+    let (store, _base_path) = store_with_objects(config.clone(), 3).await;
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 3);
+
+    let (store, _base_path) = store_with_objects(config, 4).await;
+    assert!(store.list_objects(Some(FILE_PATH)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_conformance() {
+    let base_path = tempfile::tempdir().unwrap();
+    run_conformance(LocalFile::with_base_path(base_path.path().to_path_buf())).await;
+}
+
+#[tokio::test]
+async fn test_strict_double_compression_is_rejected() {
+    let config = DriverConfig {
+        strict_double_compression: true,
+        ..Default::default()
+    };
+    let (store, _base_path) = store_with_objects(config, 0).await;
+    let compressed = compress_once(&"{}".repeat(1000).into_bytes()).unwrap();
+
+    assert_eq!(
+        store
+            .upload_compressed_object("twice.bin", &compressed, Some(FILE_PATH))
+            .await
+            .unwrap_err(),
+        DataStoreError::AlreadyCompressed(format!("{FILE_PATH}/twice.bin")).to_string()
+    );
+    store
+        .upload_compressed_object("once.bin", &"{}".repeat(1000).into_bytes(), Some(FILE_PATH))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_read_only_rejects_compressed_upload_before_encoding() {
+    let config = DriverConfig {
+        read_only: true,
+        strict_double_compression: true,
+        ..Default::default()
+    };
+    let (store, _base_path) = store_with_objects(config, 0).await;
+    let compressed = compress_once(&"{}".repeat(1000).into_bytes()).unwrap();
+
+    // Rejected as read-only rather than as already compressed
+    assert_eq!(
+        store
+            .upload_compressed_object("twice.bin", &compressed, Some(FILE_PATH))
+            .await
+            .unwrap_err(),
+        DataStoreError::ReadOnly.to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_list_recent_returns_newest_first() {
+    let (store, base_path) = store_with_objects(DriverConfig::default(), 5).await;
+    let dir = base_path.path().join(FILE_PATH);
+    for i in 0..5 {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + i * 60);
+        std::fs::File::options()
+            .write(true)
+            .open(dir.join(format!("{i}.json")))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    let recent = store.list_recent(Some(FILE_PATH), 3).await.unwrap();
+    let keys: Vec<&str> = recent.iter().map(|(key, _)| key.as_str()).collect();
+    assert_eq!(
+        keys,
+        vec![
+            format!("{FILE_PATH}/4.json"),
+            format!("{FILE_PATH}/3.json"),
+            format!("{FILE_PATH}/2.json"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_compression_policy_skips_matching_uploads() {
+    let config = DriverConfig {
+        compression_policy: CompressionPolicy::new().with_suffix(".bin", CompressionRule::Skip),
+        ..Default::default()
+    };
+    let (store, _base_path) = store_with_objects(config, 0).await;
+    let contents = "{}".repeat(1000).into_bytes();
+
+    let skipped = store
+        .upload_compressed_object("proof.bin", &contents, Some(FILE_PATH))
+        .await
+        .unwrap();
+    let compressed = store
+        .upload_compressed_object("graph.json", &contents, Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(skipped, contents.len() + 4);
+    assert!(compressed < contents.len());
+    assert_eq!(
+        store
+            .fetch_compressed_object("proof.bin", Some(FILE_PATH))
+            .await
+            .unwrap()
+            .data,
+        contents
+    );
+}
+
+#[tokio::test]
+async fn test_listing_missing_directory_does_not_create_it() {
+    let (store, base_path) = store_with_objects(DriverConfig::default(), 0).await;
+
+    assert!(store
+        .list_objects(Some(FILE_PATH))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(!base_path.path().join(FILE_PATH).exists());
+}
+
+#[tokio::test]
+async fn test_touch_bumps_modification_time() {
+    let touched_at = SystemTime::UNIX_EPOCH + Duration::from_secs(4_000_000_000);
+    let clock = Arc::new(MockClock::new(touched_at));
+    let config = DriverConfig {
+        clock: clock.clone(),
+        ..Default::default()
+    };
+    let (store, _base_path) = store_with_objects(config, 2).await;
+
+    store.touch("0.json", Some(FILE_PATH)).await.unwrap();
+    let recent = store.list_recent(Some(FILE_PATH), 2).await.unwrap();
+    assert_eq!(recent[0], (format!("{FILE_PATH}/0.json"), touched_at));
+    assert_eq!(
+        store.fetch_object("0.json", Some(FILE_PATH)).await.unwrap(),
+        "{}"
+    );
+
+    let err = store
+        .touch("missing.json", Some(FILE_PATH))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        DataStoreError::NotFound(format!("{FILE_PATH}/missing.json")).to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_corrupt_object_is_refetched_and_reported_with_checksum() {
+    let config = DriverConfig {
+        refetch_corrupt_objects: true,
+        ..Default::default()
+    };
+    let (store, base_path) = store_with_objects(config, 0).await;
+    let contents = "{}".repeat(1000).into_bytes();
+    store
+        .upload_compressed_object("graph.json", &contents, Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let object_path = base_path.path().join(FILE_PATH).join("graph.json");
+    let mut stored = std::fs::read(&object_path).unwrap();
+    stored.truncate(stored.len() / 2);
+    std::fs::write(&object_path, &stored).unwrap();
+
+    assert_eq!(
+        store
+            .fetch_compressed_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap_err(),
+        DataStoreError::CorruptObject {
+            key: format!("{FILE_PATH}/graph.json"),
+            checksum: HashAlgorithm::default().checksum(&stored),
+        }
+        .to_string()
+    );
+
+    // Other failure modes still apply once the refetched object fails too
+    let store = store.with_config(DriverConfig {
+        refetch_corrupt_objects: true,
+        decompress_failure_mode: DecompressFailureMode::ReturnRaw,
+        ..Default::default()
+    });
+    let fetched = store
+        .fetch_compressed_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(fetched.decompress_failed);
+    assert_eq!(fetched.data, stored);
+}
+
+#[tokio::test]
+async fn test_missing_object_is_not_found() {
+    let (store, _base_path) = store_with_objects(DriverConfig::default(), 0).await;
+    let not_found = DataStoreError::NotFound(format!("{FILE_PATH}/missing.json")).to_string();
+
+    assert_eq!(
+        store
+            .fetch_object("missing.json", Some(FILE_PATH))
+            .await
+            .unwrap_err(),
+        not_found
+    );
+    assert_eq!(
+        store
+            .fetch_compressed_object("missing.json", Some(FILE_PATH))
+            .await
+            .unwrap_err(),
+        not_found
+    );
+}
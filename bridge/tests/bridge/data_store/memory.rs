@@ -0,0 +1,678 @@
+use std::sync::Arc;
+
+use bridge::client::data_store::{
+    base::{
+        Access, CompressionOverride, DataStoreDriver, DecompressFailureMode, StoreCapabilities,
+        UploadOutcome,
+    },
+    format::{compress_once, is_encoded},
+    memory::MemoryStore,
+    prefetch::Prefetch,
+    read_only::ReadOnly,
+    stats::CountingStore,
+};
+
+use super::conformance::run_conformance;
+
+const FILE_PATH: &str = "bridge_data/memory";
+
+#[tokio::test]
+async fn test_conformance() {
+    run_conformance(MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_upload_if_etag_matches_detects_lost_update() {
+    let store = MemoryStore::new();
+    store
+        .upload_object("log.json", "[]", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let (_, etag) = store
+        .fetch_with_etag("log.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    // A concurrent writer updates the object first
+    let (_, concurrent_etag) = store
+        .fetch_with_etag("log.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_if_etag_matches("log.json", "[1]", Some(FILE_PATH), &concurrent_etag)
+        .await
+        .unwrap();
+
+    let result = store
+        .upload_if_etag_matches("log.json", "[2]", Some(FILE_PATH), &etag)
+        .await;
+    assert!(result.is_err());
+
+    let (contents, etag) = store
+        .fetch_with_etag("log.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(contents, "[1]");
+    store
+        .upload_if_etag_matches("log.json", "[1,2]", Some(FILE_PATH), &etag)
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("log.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "[1,2]"
+    );
+}
+
+#[tokio::test]
+async fn test_latest_pointer_follows_promotions() {
+    let store = MemoryStore::new();
+    assert!(store.resolve_latest(Some(FILE_PATH)).await.is_err());
+
+    store
+        .set_latest(Some(FILE_PATH), "snapshot-1.json")
+        .await
+        .unwrap();
+    assert_eq!(
+        store.resolve_latest(Some(FILE_PATH)).await.unwrap(),
+        "snapshot-1.json"
+    );
+
+    store
+        .set_latest(Some(FILE_PATH), "snapshot-2.json")
+        .await
+        .unwrap();
+    assert_eq!(
+        store.resolve_latest(Some(FILE_PATH)).await.unwrap(),
+        "snapshot-2.json"
+    );
+}
+
+#[test]
+fn test_read_only_drops_conditional_writes_capability() {
+    let store = MemoryStore::new();
+    assert!(store
+        .capabilities()
+        .contains(StoreCapabilities::CONDITIONAL_WRITES | StoreCapabilities::RAW_READ));
+
+    let read_only = ReadOnly::new(store);
+    assert!(!read_only
+        .capabilities()
+        .contains(StoreCapabilities::CONDITIONAL_WRITES));
+    assert!(read_only
+        .capabilities()
+        .contains(StoreCapabilities::RAW_READ));
+}
+
+#[tokio::test]
+async fn test_swap_objects_exchanges_contents() {
+    let store = MemoryStore::new();
+    store
+        .upload_object("current.json", "old", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_object("candidate.json", "new", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    store
+        .swap_objects("current.json", "candidate.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .fetch_object("current.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "new"
+    );
+    assert_eq!(
+        store
+            .fetch_object("candidate.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "old"
+    );
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_swap_objects_rolls_back_when_second_object_is_missing() {
+    let store = MemoryStore::new();
+    store
+        .upload_object("current.json", "old", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert!(store
+        .swap_objects("current.json", "missing.json", Some(FILE_PATH))
+        .await
+        .is_err());
+    assert_eq!(
+        store.list_objects(Some(FILE_PATH)).await.unwrap(),
+        vec![format!("{FILE_PATH}/current.json")]
+    );
+    assert_eq!(
+        store
+            .fetch_object("current.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "old"
+    );
+}
+
+#[tokio::test]
+async fn test_upload_object_from_reader() {
+    let store = MemoryStore::new();
+    let mut reader: &[u8] = b"{\"dog\":\"cat\"}";
+
+    let size = store
+        .upload_object_from_reader("graph.json", &mut reader, None, Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(size, 13);
+    assert_eq!(
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"dog\":\"cat\"}"
+    );
+}
+
+#[tokio::test]
+async fn test_diff_prefixes() {
+    let store = MemoryStore::new();
+    for (file_name, contents, file_path) in [
+        ("same.json", "{}", "staging"),
+        ("same.json", "{}", "production"),
+        ("changed.json", "[1]", "staging"),
+        ("changed.json", "[2]", "production"),
+        ("new.json", "{}", "staging"),
+        ("old.json", "{}", "production"),
+    ] {
+        store
+            .upload_object(file_name, contents, Some(file_path))
+            .await
+            .unwrap();
+    }
+
+    let diff = store
+        .diff_prefixes(Some("staging"), Some("production"))
+        .await
+        .unwrap();
+    assert_eq!(diff.only_in_a, vec!["new.json"]);
+    assert_eq!(diff.only_in_b, vec!["old.json"]);
+    assert_eq!(diff.different, vec!["changed.json"]);
+}
+
+#[tokio::test]
+async fn test_precompressed_object_fans_out_to_stores() {
+    let contents = "{\"dog\":\"cat\"}".repeat(100).into_bytes();
+    let compressed = compress_once(&contents).unwrap();
+
+    for store in [MemoryStore::new(), MemoryStore::new()] {
+        store
+            .upload_precompressed_object("graph.bin", &compressed, Some(FILE_PATH))
+            .await
+            .unwrap();
+        let fetched = store
+            .fetch_compressed_object("graph.bin", Some(FILE_PATH))
+            .await
+            .unwrap();
+        assert_eq!(fetched.data, contents);
+    }
+
+    let store = MemoryStore::new();
+    assert!(store
+        .upload_precompressed_object("graph.bin", &contents, Some(FILE_PATH))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_decompress_failure_modes() {
+    let contents = "{\"dog\":\"cat\"}".repeat(100).into_bytes();
+    let compressed = compress_once(&contents).unwrap();
+    let corrupt = compressed[..compressed.len() - 4].to_vec();
+
+    let store = MemoryStore::new();
+    store
+        .upload_precompressed_object("graph.bin", &corrupt, Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .fetch_compressed_object("graph.bin", Some(FILE_PATH))
+        .await
+        .is_err());
+
+    let store = MemoryStore::new().with_decompress_failure_mode(DecompressFailureMode::ReturnRaw);
+    store
+        .upload_precompressed_object("graph.bin", &corrupt, Some(FILE_PATH))
+        .await
+        .unwrap();
+    let fetched = store
+        .fetch_compressed_object("graph.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(fetched.decompress_failed);
+    assert_eq!(fetched.data, corrupt);
+
+    let store = MemoryStore::new().with_decompress_failure_mode(DecompressFailureMode::ReturnLossy);
+    store
+        .upload_precompressed_object("graph.bin", &corrupt, Some(FILE_PATH))
+        .await
+        .unwrap();
+    let fetched = store
+        .fetch_compressed_object("graph.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(fetched.decompress_failed);
+    assert!(contents.starts_with(&fetched.data));
+
+    // Intact objects decode as usual in every mode
+    store
+        .upload_precompressed_object("intact.bin", &compressed, Some(FILE_PATH))
+        .await
+        .unwrap();
+    let fetched = store
+        .fetch_compressed_object("intact.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(!fetched.decompress_failed);
+    assert_eq!(fetched.data, contents);
+}
+
+#[tokio::test]
+async fn test_try_fetch_returns_none_for_missing_objects() {
+    let store = MemoryStore::new();
+    store
+        .upload_object("present.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_compressed_object("present.bin", &b"{}".to_vec(), Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .try_fetch_object("present.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        Some("{}".to_string())
+    );
+    assert_eq!(
+        store
+            .try_fetch_object("missing.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        None
+    );
+
+    let fetched = store
+        .try_fetch_compressed_object("present.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(fetched.unwrap().data, b"{}");
+    assert!(store
+        .try_fetch_compressed_object("missing.bin", Some(FILE_PATH))
+        .await
+        .unwrap()
+        .is_none());
+
+    // Objects that exist but can't be read still fail
+    store
+        .upload_object("corrupt.bin", "not compressed", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .try_fetch_compressed_object("corrupt.bin", Some(FILE_PATH))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_objects_exist() {
+    let store = MemoryStore::new();
+    for file_name in ["peg_in.json", "peg_out.json"] {
+        store
+            .upload_object(file_name, "{}", Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+
+    let file_names = ["peg_in.json", "peg_out.json", "assert.json"].map(String::from);
+    let present = store
+        .objects_exist(&file_names, Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(present.len(), 3);
+    assert!(present["peg_in.json"] && present["peg_out.json"]);
+    assert!(!present["assert.json"]);
+}
+
+#[tokio::test]
+async fn test_prefix_footprint_reports_raw_and_stored_size() {
+    let store = MemoryStore::new();
+    let contents = "{\"dog\":\"cat\"}".repeat(1000).into_bytes();
+    let stored = store
+        .upload_compressed_object("graph.bin", &contents, Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_object("plain.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let objects = store.list_object_metadata(Some(FILE_PATH)).await.unwrap();
+    assert_eq!(objects[0].raw_size, Some(contents.len() as u64));
+    assert_eq!(objects[1].raw_size, None);
+
+    let footprint = store.prefix_footprint(Some(FILE_PATH)).await.unwrap();
+    assert_eq!(footprint.stored_size, stored as u64 + 2);
+    assert_eq!(footprint.raw_size, contents.len() as u64 + 2);
+    assert!(footprint.compression_ratio() > 1.0);
+}
+
+#[tokio::test]
+async fn test_update_object_applies_every_update() {
+    let store = Arc::new(MemoryStore::new());
+    let increment = |current: Option<String>| {
+        let count = current.map_or(0, |count| count.parse::<u32>().unwrap());
+        (count + 1).to_string()
+    };
+
+    let tasks: Vec<_> = (0..10)
+        .map(|_| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                store
+                    .update_object("status.json", Some(FILE_PATH), &increment)
+                    .await
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    assert_eq!(
+        store
+            .fetch_object("status.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "10"
+    );
+}
+
+#[tokio::test]
+async fn test_increment_counts_concurrent_increments() {
+    let store = Arc::new(MemoryStore::new());
+    let tasks: Vec<_> = (0..10)
+        .map(|_| {
+            let store = store.clone();
+            tokio::spawn(async move { store.increment("attempts", Some(FILE_PATH), 2).await })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    assert_eq!(
+        store
+            .increment("attempts", Some(FILE_PATH), -5)
+            .await
+            .unwrap(),
+        15
+    );
+    assert_eq!(
+        store
+            .fetch_object("attempts", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "15"
+    );
+
+    store
+        .upload_object("status.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .increment("status.json", Some(FILE_PATH), 1)
+        .await
+        .is_err());
+    assert_eq!(
+        store
+            .fetch_object("status.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{}"
+    );
+}
+
+#[tokio::test]
+async fn test_fetch_first() {
+    let store = MemoryStore::new();
+    assert_eq!(store.fetch_first(Some(FILE_PATH)).await.unwrap(), None);
+
+    store
+        .upload_object("b.json", "b", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_object("a.json", "a", Some(&format!("{FILE_PATH}/nested")))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.fetch_first(Some(FILE_PATH)).await.unwrap(),
+        Some((format!("{FILE_PATH}/b.json"), "b".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn test_list_graph_artifacts() {
+    let store = MemoryStore::new();
+    for (file_name, file_path) in [
+        ("attempt_2.json", "graphs/abc123/peg_out"),
+        ("attempt_1.json", "graphs/abc123/peg_out"),
+        ("tx.json", "graphs/abc123/kick_off"),
+        ("summary.json", "graphs/abc123"),
+        ("tx.json", "graphs/abc1234/kick_off"),
+    ] {
+        store
+            .upload_object(file_name, "{}", Some(file_path))
+            .await
+            .unwrap();
+    }
+
+    let artifacts = store.list_graph_artifacts("abc123").await.unwrap();
+    let listed: Vec<(&str, &str)> = artifacts
+        .iter()
+        .map(|artifact| (artifact.artifact_kind().unwrap(), artifact.file_name()))
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            ("kick_off", "tx.json"),
+            ("peg_out", "attempt_1.json"),
+            ("peg_out", "attempt_2.json"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_prefetch_returns_objects_in_order() {
+    let store = Arc::new(MemoryStore::new());
+    for i in 0..10 {
+        store
+            .upload_object(&format!("{i}.json"), &i.to_string(), Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+
+    let file_names: Vec<String> = (0..10)
+        .map(|i| format!("{i}.json"))
+        .chain(["missing.json".to_string()])
+        .collect();
+    let mut prefetch = Prefetch::new(store, file_names, Some(FILE_PATH.to_string()), 3);
+    for i in 0..10 {
+        let (file_name, object) = prefetch.next().await.unwrap();
+        assert_eq!(file_name, format!("{i}.json"));
+        assert_eq!(object.unwrap(), i.to_string());
+    }
+    let (file_name, object) = prefetch.next().await.unwrap();
+    assert_eq!(file_name, "missing.json");
+    assert!(object.is_err());
+    assert!(prefetch.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_idempotent_upload_is_applied_once() {
+    let store = MemoryStore::new();
+    let upload = |contents| {
+        store.upload_object_idempotent("graph.json", contents, Some(FILE_PATH), "attempt-1")
+    };
+
+    assert_eq!(
+        upload("{\"try\":1}").await.unwrap(),
+        UploadOutcome::Uploaded(9)
+    );
+    assert_eq!(
+        upload("{\"try\":2}").await.unwrap(),
+        UploadOutcome::AlreadyApplied
+    );
+    assert_eq!(
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"try\":1}"
+    );
+
+    // A plain overwrite drops the idempotency key, so the next idempotent upload goes through
+    store
+        .upload_object("graph.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(
+        upload("{\"try\":3}").await.unwrap(),
+        UploadOutcome::Uploaded(9)
+    );
+}
+
+#[tokio::test]
+async fn test_check_access_probes_without_leaving_objects() {
+    let store = MemoryStore::new();
+    store
+        .check_access(Some(FILE_PATH), Access::ReadWrite)
+        .await
+        .unwrap();
+    assert!(store
+        .list_objects(Some(FILE_PATH))
+        .await
+        .unwrap()
+        .is_empty());
+
+    let read_only = ReadOnly::new(MemoryStore::new());
+    read_only
+        .check_access(Some(FILE_PATH), Access::Read)
+        .await
+        .unwrap();
+    let err = read_only
+        .check_access(Some(FILE_PATH), Access::Write)
+        .await
+        .unwrap_err();
+    assert!(err.starts_with("No write access to \"bridge_data/memory/\""));
+}
+
+#[tokio::test]
+async fn test_upload_overrides_compression_per_call() {
+    let store = MemoryStore::new();
+    let contents = b"{\"graph\":1}";
+    for (file_name, compression) in [
+        ("default.json", CompressionOverride::Default),
+        ("compressed.json", CompressionOverride::Compressed),
+        ("raw.json", CompressionOverride::Raw),
+    ] {
+        store
+            .upload(file_name, contents, Some(FILE_PATH), compression)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.fetch(file_name, Some(FILE_PATH)).await.unwrap(),
+            contents
+        );
+    }
+
+    let raw = store
+        .fetch_raw_object("raw.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(raw, contents);
+    let compressed = store
+        .fetch_raw_object("compressed.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(is_encoded(&compressed));
+}
+
+#[tokio::test]
+async fn test_read_last_lines_only_fetches_the_tail() {
+    let store = CountingStore::new(MemoryStore::new());
+    let log: String = (0..100_000).map(|i| format!("event {i}\n")).collect();
+    store
+        .upload_object("events.log", &log, Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .read_last_lines("events.log", Some(FILE_PATH), 3)
+            .await
+            .unwrap(),
+        vec!["event 99997", "event 99998", "event 99999"]
+    );
+    assert!(store.stats().bytes_in < log.len() as u64 / 10);
+}
+
+#[tokio::test]
+async fn test_read_last_lines_spanning_several_ranges() {
+    let store = MemoryStore::new();
+    let long_line = "x".repeat(100_000);
+    let log = format!("first\r\n{long_line}\r\nlast");
+    store
+        .upload_object("events.log", &log, Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .read_last_lines("events.log", Some(FILE_PATH), 2)
+            .await
+            .unwrap(),
+        vec![long_line.clone(), "last".to_string()]
+    );
+    assert_eq!(
+        store
+            .read_last_lines("events.log", Some(FILE_PATH), 10)
+            .await
+            .unwrap(),
+        vec!["first".to_string(), long_line, "last".to_string()]
+    );
+
+    store
+        .upload_object("empty.log", "", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .read_last_lines("empty.log", Some(FILE_PATH), 2)
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,83 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver,
+    memory::MemoryStore,
+    migration::{copy_all, MigrationReport},
+    read_only::ReadOnly,
+};
+
+const FILE_PATH: &str = "bridge_data/migration";
+
+async fn source() -> MemoryStore {
+    let src = MemoryStore::new();
+    src.upload_object("a.json", "{\"a\":1}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    src.upload_compressed_object("b.bin", &vec![1u8; 4096], Some(FILE_PATH))
+        .await
+        .unwrap();
+    src.upload_object("c.json", "{\"c\":3}", Some(&format!("{FILE_PATH}/nested")))
+        .await
+        .unwrap();
+    src
+}
+
+#[tokio::test]
+async fn test_copy_all_skips_existing_and_keeps_stored_bytes() {
+    let src = source().await;
+    let dst = MemoryStore::new();
+    dst.upload_object("a.json", "{\"a\":0}", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let report = copy_all(&src, &dst, Some(FILE_PATH)).await.unwrap();
+    assert_eq!(
+        report,
+        MigrationReport {
+            copied: 2,
+            skipped: 1,
+            failed: vec![],
+        }
+    );
+
+    // Existing objects are left as they are
+    assert_eq!(
+        dst.fetch_object("a.json", Some(FILE_PATH)).await.unwrap(),
+        "{\"a\":0}"
+    );
+    assert_eq!(
+        dst.fetch_raw_object("b.bin", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        src.fetch_raw_object("b.bin", Some(FILE_PATH))
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        dst.fetch_object("c.json", Some(&format!("{FILE_PATH}/nested")))
+            .await
+            .unwrap(),
+        "{\"c\":3}"
+    );
+
+    // Rerunning the migration has nothing left to copy
+    let report = copy_all(&src, &dst, Some(FILE_PATH)).await.unwrap();
+    assert_eq!((report.copied, report.skipped), (0, 3));
+}
+
+#[tokio::test]
+async fn test_copy_all_reports_failed_objects() {
+    let src = source().await;
+    let dst = ReadOnly::new(MemoryStore::new());
+
+    let report = copy_all(&src, &dst, Some(FILE_PATH)).await.unwrap();
+    assert_eq!((report.copied, report.skipped), (0, 0));
+    let failed: Vec<&str> = report.failed.iter().map(|(key, _)| key.as_str()).collect();
+    assert_eq!(
+        failed,
+        [
+            "bridge_data/migration/a.json",
+            "bridge_data/migration/b.bin",
+            "bridge_data/migration/nested/c.json",
+        ]
+    );
+}
@@ -1,3 +1,33 @@
+pub mod access_time;
+pub mod audit;
+pub mod aws_s3;
+pub mod bulk_fetch;
+pub mod bulk_upload;
+pub mod cached_listing;
+pub mod cached_store;
+pub mod chunked;
+pub mod compression_policy;
+pub mod conformance;
+pub mod consistency;
+pub mod encrypted_keys;
+pub mod encryption;
+pub mod fake_s3;
+pub mod fallback;
+pub mod format;
 pub mod ftp;
 pub mod ftps;
+pub mod integrity;
+pub mod key;
+pub mod key_layout;
+pub mod key_policy;
+pub mod lazy_client;
+pub mod local_file;
+pub mod memory;
+pub mod migration;
+pub mod recording;
+pub mod schema;
 pub mod sftp;
+pub mod size_metrics;
+pub mod spread_prefixes;
+pub mod stats;
+pub mod timestamped;
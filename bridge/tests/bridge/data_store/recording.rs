@@ -0,0 +1,147 @@
+use std::{
+    io::{Cursor, Write},
+    sync::{Arc, Mutex},
+};
+
+use bridge::client::data_store::{
+    base::DataStoreDriver,
+    cached_store::CachedStore,
+    memory::MemoryStore,
+    recording::{RecordingStore, ReplayStore},
+};
+
+const FILE_PATH: &str = "bridge_data/recording";
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn record_session(buffer: SharedBuffer) {
+    let store = RecordingStore::new(MemoryStore::new(), buffer);
+    store
+        .upload_object("state.json", "{\"step\":1}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .fetch_object("state.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_object("state.json", "{\"step\":2}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .fetch_object("state.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .upload_compressed_object("graph.bin", &b"graph".repeat(100), Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .fetch_compressed_object("graph.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store.list_objects(Some(FILE_PATH)).await.unwrap();
+    assert!(store
+        .fetch_object("missing.json", Some(FILE_PATH))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_recording_is_deterministic() {
+    let first = SharedBuffer::default();
+    let second = SharedBuffer::default();
+    record_session(first.clone()).await;
+    record_session(second.clone()).await;
+
+    let first = first.0.lock().unwrap().clone();
+    assert_eq!(first, *second.0.lock().unwrap());
+    assert_eq!(first.iter().filter(|byte| **byte == b'\n').count(), 8);
+}
+
+#[tokio::test]
+async fn test_replay_serves_recorded_results_in_order() {
+    let buffer = SharedBuffer::default();
+    record_session(buffer.clone()).await;
+    let recording = buffer.0.lock().unwrap().clone();
+    let replay = ReplayStore::from_reader(Cursor::new(recording)).unwrap();
+
+    assert_eq!(
+        replay
+            .fetch_object("state.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"step\":1}"
+    );
+    assert_eq!(
+        replay
+            .fetch_object("state.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"step\":2}"
+    );
+    assert_eq!(
+        replay
+            .fetch_object("state.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"step\":2}"
+    );
+    let graph = replay
+        .fetch_compressed_object("graph.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(graph.data, b"graph".repeat(100));
+    assert_eq!(
+        replay.list_objects(Some(FILE_PATH)).await.unwrap(),
+        vec![
+            format!("{FILE_PATH}/graph.bin"),
+            format!("{FILE_PATH}/state.json")
+        ]
+    );
+    assert!(replay
+        .fetch_object("missing.json", Some(FILE_PATH))
+        .await
+        .is_err());
+}
+
+struct UnflushableWriter;
+
+impl Write for UnflushableWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Err(std::io::Error::other("disk full"))
+    }
+}
+
+#[tokio::test]
+async fn test_shutdown_drains_every_wrapper() {
+    let buffer = SharedBuffer::default();
+    let store = CachedStore::new(RecordingStore::new(MemoryStore::new(), buffer), 16);
+    store
+        .upload_object("state.json", "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    store.shutdown().await.unwrap();
+
+    // The recording below the cache still gets flushed, and its failure is returned
+    let store = CachedStore::new(
+        RecordingStore::new(MemoryStore::new(), UnflushableWriter),
+        16,
+    );
+    assert!(store.shutdown().await.unwrap_err().contains("disk full"));
+}
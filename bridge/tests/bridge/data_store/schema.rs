@@ -0,0 +1,93 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver,
+    memory::MemoryStore,
+    schema::{fetch_json, to_canonical_json, upload_json, JsonFormat, JsonSchema},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const FILE_PATH: &str = "bridge_data/schema";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PegOut {
+    amount: u64,
+    fee: u64,
+}
+
+// Version 1 stored the amount only, version 2 added the fee
+fn add_fee(mut data: Value) -> Result<Value, String> {
+    data["fee"] = json!(0);
+    Ok(data)
+}
+
+#[tokio::test]
+async fn test_old_artifacts_are_migrated() {
+    let store = MemoryStore::new();
+    let v1 = JsonSchema::new(1);
+    upload_json(
+        &store,
+        Some(&v1),
+        JsonFormat::Compact,
+        "peg_out.json",
+        &json!({ "amount": 5 }),
+        Some(FILE_PATH),
+    )
+    .await
+    .unwrap();
+
+    let v2 = JsonSchema::new(2).with_migration(1, add_fee);
+    let peg_out: PegOut = fetch_json(&store, Some(&v2), "peg_out.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert_eq!(peg_out, PegOut { amount: 5, fee: 0 });
+}
+
+#[tokio::test]
+async fn test_future_schema_version_is_rejected() {
+    let store = MemoryStore::new();
+    store
+        .upload_object(
+            "peg_out.json",
+            "{\"_schema_version\":3,\"data\":{}}",
+            Some(FILE_PATH),
+        )
+        .await
+        .unwrap();
+
+    let result: Result<PegOut, String> = fetch_json(
+        &store,
+        Some(&JsonSchema::new(2)),
+        "peg_out.json",
+        Some(FILE_PATH),
+    )
+    .await;
+    assert!(result.unwrap_err().contains("schema version 3"));
+}
+
+#[tokio::test]
+async fn test_canonical_json_sorts_keys_without_whitespace() {
+    let value = json!({ "fee": 1, "amount": { "sats": 5, "btc": [2, 1] }, "memo": null });
+    assert_eq!(
+        to_canonical_json(&value),
+        "{\"amount\":{\"btc\":[2,1],\"sats\":5},\"fee\":1,\"memo\":null}"
+    );
+
+    let store = MemoryStore::new();
+    upload_json(
+        &store,
+        Some(&JsonSchema::new(1)),
+        JsonFormat::Canonical,
+        "peg_out.json",
+        &PegOut { fee: 1, amount: 5 },
+        Some(FILE_PATH),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        store
+            .fetch_object("peg_out.json", Some(FILE_PATH))
+            .await
+            .unwrap(),
+        "{\"_schema_version\":1,\"data\":{\"amount\":5,\"fee\":1}}"
+    );
+}
@@ -0,0 +1,58 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver,
+    memory::MemoryStore,
+    size_metrics::{SizeHistogram, SizeMetrics, Transfer, SIZE_BUCKETS},
+};
+
+const FILE_PATH: &str = "bridge_data/size_metrics";
+
+#[test]
+fn test_sizes_are_counted_in_exponential_buckets() {
+    let mut histogram = SizeHistogram::default();
+    for size in [0, 256, 257, 1024, 5000, 2 << 30] {
+        histogram.record(size);
+    }
+
+    assert_eq!(histogram.counts[0], 2); // Up to 256 B
+    assert_eq!(histogram.counts[1], 2); // Up to 1 KiB
+    assert_eq!(histogram.counts[3], 1); // Up to 16 KiB
+    assert_eq!(histogram.counts[SIZE_BUCKETS.len()], 1);
+    assert_eq!(histogram.count(), 6);
+    assert_eq!(histogram.fraction_at_most(1024), 4.0 / 6.0);
+}
+
+#[tokio::test]
+async fn test_uploads_and_downloads_are_recorded_by_encoding() {
+    let store = SizeMetrics::new(MemoryStore::new());
+    for i in 0..9 {
+        store
+            .upload_object(&format!("{i}.json"), "{}", Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+    store
+        .upload_object("large.json", &"x".repeat(100_000), Some(FILE_PATH))
+        .await
+        .unwrap();
+    let stored = store
+        .upload_compressed_object("graph.bin", &vec![0u8; 100_000], Some(FILE_PATH))
+        .await
+        .unwrap();
+    store
+        .fetch_compressed_object("graph.bin", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .fetch_object("missing.json", Some(FILE_PATH))
+        .await
+        .is_err());
+
+    let plain = store.histogram(Transfer::Upload, false);
+    assert_eq!(plain.count(), 10);
+    assert_eq!(plain.fraction_at_most(1024), 0.9);
+    let compressed = store.histogram(Transfer::Upload, true);
+    assert_eq!(compressed.sum, stored as u64);
+    assert_eq!(store.histogram(Transfer::Download, true).sum, stored as u64);
+    // Failed transfers aren't recorded
+    assert_eq!(store.histogram(Transfer::Download, false).count(), 0);
+}
@@ -0,0 +1,66 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver, memory::MemoryStore, spread_prefixes::SpreadPrefixes,
+};
+
+const FILE_PATH: &str = "bridge_data/spread_prefixes";
+
+#[tokio::test]
+async fn test_objects_are_spread_and_listed_unsharded() {
+    let store = SpreadPrefixes::new(MemoryStore::new(), 16);
+    for i in 0..20 {
+        store
+            .upload_object(&format!("{i}.json"), &i.to_string(), Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+
+    let mut keys = store.list_objects(Some(FILE_PATH)).await.unwrap();
+    keys.sort();
+    let mut expected: Vec<String> = (0..20).map(|i| format!("{FILE_PATH}/{i}.json")).collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+    assert_eq!(
+        store.fetch_object("7.json", Some(FILE_PATH)).await.unwrap(),
+        "7"
+    );
+
+    let inner = store.into_inner();
+    let mut shards: Vec<String> = inner
+        .list_objects(Some(FILE_PATH))
+        .await
+        .unwrap()
+        .iter()
+        .map(|key| key.split('/').nth(2).unwrap().to_string())
+        .collect();
+    shards.dedup();
+    assert!(shards.len() > 1);
+    assert!(!inner
+        .object_exists("7.json", Some(FILE_PATH))
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_migrate_prefix_moves_unsharded_objects() {
+    let inner = MemoryStore::new();
+    for i in 0..5 {
+        inner
+            .upload_object(&format!("{i}.json"), "{}", Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+    let store = SpreadPrefixes::new(inner, 4);
+    assert!(store
+        .list_objects(Some(FILE_PATH))
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(store.migrate_prefix(Some(FILE_PATH)).await.unwrap(), 5);
+    assert_eq!(store.migrate_prefix(Some(FILE_PATH)).await.unwrap(), 0);
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 5);
+    assert_eq!(
+        store.fetch_object("3.json", Some(FILE_PATH)).await.unwrap(),
+        "{}"
+    );
+}
@@ -0,0 +1,43 @@
+use bridge::client::data_store::{
+    base::DataStoreDriver, cached_store::CachedStore, memory::MemoryStore, stats::CountingStore,
+};
+
+const FILE_PATH: &str = "bridge_data/stats";
+
+#[tokio::test]
+async fn test_stats_count_calls_through_the_stack() {
+    let store = CachedStore::new(CountingStore::new(MemoryStore::new()), 16);
+    assert_eq!(store.stats().cache_hit_rate(), None);
+
+    store
+        .upload_object("graph.json", "{\"graph\":1}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    for _ in 0..3 {
+        store
+            .fetch_object("graph.json", Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+    store
+        .fetch_object("missing.json", Some(FILE_PATH))
+        .await
+        .unwrap_err();
+    store.list_objects(Some(FILE_PATH)).await.unwrap();
+    store
+        .delete_object("graph.json", Some(FILE_PATH))
+        .await
+        .unwrap();
+
+    let stats = store.stats();
+    assert_eq!(stats.puts, 1);
+    assert_eq!(stats.bytes_out, 11);
+    // Only the first read of graph.json and the read of the missing object reach the driver
+    assert_eq!(stats.gets, 2);
+    assert_eq!(stats.bytes_in, 11);
+    assert_eq!(stats.errors, 1);
+    assert_eq!(stats.lists, 1);
+    assert_eq!(stats.deletes, 1);
+    assert_eq!((stats.cache_hits, stats.cache_misses), (2, 2));
+    assert_eq!(stats.cache_hit_rate(), Some(0.5));
+}
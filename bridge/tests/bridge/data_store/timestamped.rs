@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use bridge::{
+    client::data_store::{
+        base::DataStoreDriver,
+        memory::MemoryStore,
+        timestamped::{
+            timestamped_file_name, upload_compressed_timestamped, upload_timestamped,
+            upload_with_free_name, MAX_NAME_ATTEMPTS,
+        },
+    },
+    error::DataStoreError,
+};
+use futures::future::join_all;
+
+const FILE_PATH: &str = "bridge_data/timestamped";
+const SUFFIX: &str = "-bridge-client-data.json";
+const TIMESTAMP: u128 = 1_700_000_000_000;
+
+#[test]
+fn test_names_sort_by_timestamp() {
+    let earlier = timestamped_file_name(TIMESTAMP, SUFFIX);
+    let later = timestamped_file_name(TIMESTAMP + 1, SUFFIX);
+
+    assert!(earlier.starts_with("1700000000000-"));
+    assert!(earlier.ends_with(SUFFIX));
+    assert!(earlier < later);
+}
+
+#[tokio::test]
+async fn test_concurrent_writes_in_the_same_millisecond_are_all_kept() {
+    let store = MemoryStore::new();
+    let contents: Vec<String> = (0..200).map(|i| format!("{{\"write\":{i}}}")).collect();
+
+    let writes = contents
+        .iter()
+        .map(|contents| upload_timestamped(&store, TIMESTAMP, SUFFIX, contents, Some(FILE_PATH)));
+    let file_names: Vec<String> = join_all(writes)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(file_names.iter().collect::<HashSet<_>>().len(), 200);
+    for (file_name, contents) in file_names.iter().zip(&contents) {
+        assert_eq!(
+            &store
+                .fetch_object(file_name, Some(FILE_PATH))
+                .await
+                .unwrap(),
+            contents
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_compressed_writes_never_replace_existing_objects() {
+    let store = MemoryStore::new();
+    let contents = vec![1u8; 1024];
+
+    let writes = (0..50).map(|_| {
+        upload_compressed_timestamped(&store, TIMESTAMP, SUFFIX, &contents, Some(FILE_PATH))
+    });
+    for result in join_all(writes).await {
+        result.unwrap();
+    }
+
+    assert_eq!(store.list_objects(Some(FILE_PATH)).await.unwrap().len(), 50);
+    let taken = timestamped_file_name(TIMESTAMP, SUFFIX);
+    store
+        .upload_object_if_absent(&taken, "{}", Some(FILE_PATH))
+        .await
+        .unwrap();
+    assert!(store
+        .upload_compressed_object_if_absent(&taken, &contents, Some(FILE_PATH))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_taken_names_are_retried_until_attempts_run_out() {
+    let store = MemoryStore::new();
+    for i in 0..MAX_NAME_ATTEMPTS {
+        store
+            .upload_object(&format!("taken-{i}"), "{}", Some(FILE_PATH))
+            .await
+            .unwrap();
+    }
+    let upload = |file_name: String| {
+        let store = &store;
+        async move {
+            store
+                .upload_object_if_absent(&file_name, "{\"new\":true}", Some(FILE_PATH))
+                .await
+                .map(|_| file_name)
+        }
+    };
+
+    // Every name but the last one collides
+    let mut names = (0..MAX_NAME_ATTEMPTS - 1)
+        .map(|i| format!("taken-{i}"))
+        .chain(["free".to_string()]);
+    let file_name = upload_with_free_name(|| names.next().unwrap(), Some(FILE_PATH), upload)
+        .await
+        .unwrap();
+    assert_eq!(file_name, "free");
+
+    let mut names = (0..).map(|i| format!("taken-{}", i % MAX_NAME_ATTEMPTS));
+    let err = upload_with_free_name(|| names.next().unwrap(), Some(FILE_PATH), upload)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        DataStoreError::AlreadyExists(format!("{FILE_PATH}/taken-{}", MAX_NAME_ATTEMPTS - 1))
+            .to_string()
+    );
+    for i in 0..MAX_NAME_ATTEMPTS {
+        assert_eq!(
+            store
+                .fetch_object(&format!("taken-{i}"), Some(FILE_PATH))
+                .await
+                .unwrap(),
+            "{}"
+        );
+    }
+}